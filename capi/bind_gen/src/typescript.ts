@@ -192,9 +192,16 @@ export interface SplitsComponentIconChangeJson {
     segment_index: number,
     /**
      * The segment's icon encoded as a Data URL. The String itself may be empty.
-     * This indicates that there is no icon.
+     * This indicates that there is no icon. If the component deduplicates
+     * icons, it is also empty if the icon has already been provided for another
+     * segment.
      */
     icon: string,
+    /**
+     * The ID of the segment's icon. Segments sharing the same icon have the
+     * same ID. This is 0 if there is no icon.
+     */
+    icon_id: number,
 }
 
 /** The state object that describes a single segment's information to visualize. */
@@ -627,10 +634,67 @@ export interface RunEditorStateJson {
     segments: RunEditorRowJson[],
     /** The names of all the custom comparisons that exist for this Run. */
     comparison_names: string[],
+    /**
+     * Additional information about the Run, like the platform and region of
+     * the game.
+     */
+    metadata: RunEditorMetadataJson,
+    /**
+     * All the elements of the active segment's Segment History for the active
+     * timing method.
+     */
+    segment_history: RunEditorSegmentHistoryElementJson[],
     /** Describes which actions are currently available. */
     buttons: RunEditorButtonsJson,
 }
 
+/** Describes a single element of the active segment's Segment History. */
+export interface RunEditorSegmentHistoryElementJson {
+    /** The index of the attempt the segment time belongs to. */
+    attempt_index: number,
+    /**
+     * The date the attempt was started at, or ended at if that isn't known.
+     * This is null if neither is known.
+     */
+    date: string | null,
+    /** The segment time for the active timing method. */
+    time: string,
+    /**
+     * Describes whether the segment time is a statistical outlier. Outliers
+     * are often caused by missed or accidental splits.
+     */
+    is_outlier: boolean,
+}
+
+/** Describes the Run's metadata. All of this information is optional. */
+export interface RunEditorMetadataJson {
+    /**
+     * The speedrun.com Run ID of the run. This may be empty if there's no
+     * association.
+     */
+    run_id: string,
+    /**
+     * The name of the platform this game is run on. This may be empty if it's
+     * not specified.
+     */
+    platform_name: string,
+    /**
+     * Specifies whether this speedrun is done on an emulator. `false` may
+     * also mean that this information is simply not known.
+     */
+    uses_emulator: boolean,
+    /**
+     * The name of the region this game is from. This may be empty if it's
+     * not specified.
+     */
+    region_name: string,
+    /**
+     * The names and values of all the variables, in the order they were
+     * added.
+     */
+    variables: [string, string][],
+}
+
 /**
  * Describes which actions are currently available. Depending on how many
  * segments exist and which ones are selected, only some actions can be
@@ -654,6 +718,10 @@ export interface RunEditorButtonsJson {
      * moved.
      */
     can_move_down: boolean,
+    /** Describes whether there is a change that can be undone. */
+    can_undo: boolean,
+    /** Describes whether there is an undone change that can be redone. */
+    can_redo: boolean,
 }
 
 /** Describes the current state of a segment. */
@@ -3,7 +3,8 @@
 //! then reset, an Attempt describing general information about it is created.
 
 use livesplit_core::{Attempt, Time};
-use super::{acc, alloc, output_time, output_time_span};
+use super::{acc, alloc, output_str, output_time, output_time_span};
+use std::os::raw::c_char;
 use std::ptr;
 use atomic_date_time::NullableOwnedAtomicDateTime;
 use time_span::NullableTimeSpan;
@@ -59,3 +60,31 @@ pub unsafe extern "C" fn Attempt_ended(this: *const Attempt) -> NullableOwnedAto
         ptr::null_mut()
     }
 }
+
+/// Returns <TRUE> if the attempt has been marked as invalid, for example
+/// because it was cheated, glitched or done in the wrong category. The segment
+/// times of invalid attempts are not kept in the Segment History.
+#[no_mangle]
+pub unsafe extern "C" fn Attempt_is_invalid(this: *const Attempt) -> bool {
+    acc(this).is_invalid()
+}
+
+/// Accesses the note the runner attached to the attempt. This may be empty if
+/// there is no note.
+#[no_mangle]
+pub unsafe extern "C" fn Attempt_note(this: *const Attempt) -> *const c_char {
+    output_str(acc(this).note())
+}
+
+/// Returns the amount of tags attached to the attempt.
+#[no_mangle]
+pub unsafe extern "C" fn Attempt_tags_len(this: *const Attempt) -> usize {
+    acc(this).tags().len()
+}
+
+/// Accesses the tag with the index provided. You may not provide an out of
+/// bounds index.
+#[no_mangle]
+pub unsafe extern "C" fn Attempt_tag(this: *const Attempt, index: usize) -> *const c_char {
+    output_str(&acc(this).tags()[index])
+}
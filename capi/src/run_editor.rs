@@ -4,6 +4,7 @@
 //! state objects that can be visualized by any kind of User Interface.
 
 use livesplit_core::{Run, RunEditor, TimingMethod};
+use livesplit_core::run::editor::csv::{Delimiter, Matching};
 use super::{acc, acc_mut, alloc, output_vec, own, str, Json};
 use run::OwnedRun;
 use sum_of_best_cleaner::OwnedSumOfBestCleaner;
@@ -136,6 +137,67 @@ pub unsafe extern "C" fn RunEditor_remove_game_icon(this: *mut RunEditor) {
     acc_mut(this).remove_game_icon();
 }
 
+/// Sets the speedrun.com Run ID of the run. You need to ensure that the record
+/// on speedrun.com matches up with the Personal Best of this run. This may be
+/// empty if there's no association.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_run_id(this: *mut RunEditor, id: *const c_char) {
+    acc_mut(this).set_run_id(str(id));
+}
+
+/// Sets the name of the platform this game is run on. This may be empty if
+/// it's not specified.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_platform_name(this: *mut RunEditor, name: *const c_char) {
+    acc_mut(this).set_platform_name(str(name));
+}
+
+/// Specifies whether this speedrun is done on an emulator. Keep in mind that
+/// <FALSE> may also mean that this information is simply not known.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_emulator_usage(this: *mut RunEditor, uses_emulator: bool) {
+    acc_mut(this).set_emulator_usage(uses_emulator);
+}
+
+/// Sets the name of the region this game is from. This may be empty if it's
+/// not specified.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_region_name(this: *mut RunEditor, name: *const c_char) {
+    acc_mut(this).set_region_name(str(name));
+}
+
+/// Sets the value of a variable. If there's no variable with that name yet, it
+/// is added after all the other variables. A variable is an arbitrary key
+/// value pair storing additional information about the category.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_variable(
+    this: *mut RunEditor,
+    name: *const c_char,
+    value: *const c_char,
+) {
+    acc_mut(this).set_variable(str(name), str(value));
+}
+
+/// Removes the variable with the name provided, if it exists.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_remove_variable(this: *mut RunEditor, name: *const c_char) {
+    acc_mut(this).remove_variable(str(name));
+}
+
+/// Renames a variable while keeping its value. The variable can't be renamed
+/// if there already is a variable with the new name. Returns <TRUE> if the
+/// variable got renamed.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_rename_variable(
+    this: *mut RunEditor,
+    old_name: *const c_char,
+    new_name: *const c_char,
+) -> bool {
+    acc_mut(this)
+        .rename_variable(str(old_name), str(new_name))
+        .is_ok()
+}
+
 /// Inserts a new empty segment above the active segment and adjusts the
 /// Run's history information accordingly. The newly created segment is then
 /// the only selected segment and also the active segment.
@@ -178,6 +240,45 @@ pub unsafe extern "C" fn RunEditor_move_segments_down(this: *mut RunEditor) {
     acc_mut(this).move_segments_down();
 }
 
+/// Checks if the active segment can be merged with the segment after it. The
+/// last segment can't be merged.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_can_merge_segments(this: *const RunEditor) -> bool {
+    acc(this).can_merge_segments()
+}
+
+/// Merges the active segment with the segment after it, unless the active
+/// segment is the last segment. The merged segment keeps the name, the icon
+/// and the split times of the segment after the active segment. The segment
+/// times of both segments are combined in its Segment History. Its Best
+/// Segment Time is the sum of both Best Segment Times, unless the Segment
+/// History contains a faster time. If either segment has no Best Segment Time,
+/// it is recalculated from the Segment History instead. The merged segment
+/// then becomes the only selected segment and also the active segment.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_merge_segments(this: *mut RunEditor) {
+    acc_mut(this).merge_segments();
+}
+
+/// Splits the active segment into two segments by inserting a new empty
+/// segment above it. The new segment is considered skipped in all the attempts
+/// and the comparisons, so the active segment keeps all of its times. The new
+/// segment is then the only selected segment and also the active segment.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_split_segment(this: *mut RunEditor) {
+    acc_mut(this).split_segment(None);
+}
+
+/// Splits the active segment into two segments by inserting a new empty
+/// segment above it. All the segment times of the active segment are
+/// distributed between the two segments, with the new segment receiving the
+/// given share of each of them. The ratio is clamped to be between 0 and 1. The
+/// new segment is then the only selected segment and also the active segment.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_split_segment_with_ratio(this: *mut RunEditor, ratio: f64) {
+    acc_mut(this).split_segment(Some(ratio));
+}
+
 /// Sets the icon of the active segment.
 #[no_mangle]
 pub unsafe extern "C" fn RunEditor_active_set_icon(
@@ -302,6 +403,99 @@ pub unsafe extern "C" fn RunEditor_rename_comparison(
         .is_ok()
 }
 
+/// Sets the amount of median absolute deviations a segment time needs to be
+/// away from the median segment time in order to be considered an outlier in
+/// the active segment's Segment History.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_outlier_threshold(this: *mut RunEditor, threshold: f64) {
+    acc_mut(this).set_outlier_threshold(threshold);
+}
+
+/// Removes the segment time of the attempt with the index provided from the
+/// active segment's Segment History. If it was the Best Segment Time, the Best
+/// Segment Time is recalculated from the remaining history.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_remove_segment_history_element(
+    this: *mut RunEditor,
+    attempt_index: i32,
+) {
+    acc_mut(this).remove_segment_history_element(attempt_index);
+}
+
+/// Parses a segment time from a string and sets it for the attempt with the
+/// index provided in the active segment's Segment History for the active
+/// timing method. Returns <FALSE> if the time couldn't be parsed.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_parse_and_set_segment_history_time(
+    this: *mut RunEditor,
+    attempt_index: i32,
+    time: *const c_char,
+) -> bool {
+    acc_mut(this)
+        .parse_and_set_segment_history_time(attempt_index, str(time))
+        .is_ok()
+}
+
+/// Removes all the segment times from the active segment's Segment History
+/// that are flagged as outliers for the active timing method.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_remove_outliers(this: *mut RunEditor) {
+    acc_mut(this).remove_outliers();
+}
+
+/// Removes the attempt with the index provided from the Attempt History, along
+/// with all of its segment times. Best Segment Times achieved in the attempt
+/// are recalculated from the remaining history. If the attempt was the
+/// Personal Best, the fastest remaining valid attempt becomes the new Personal
+/// Best.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_remove_attempt(this: *mut RunEditor, index: i32) {
+    acc_mut(this).remove_attempt(index);
+}
+
+/// Marks the attempt with the index provided as invalid, for example because
+/// it was cheated, glitched or done in the wrong category. The attempt stays in
+/// the Attempt History, but its segment times are removed just like when
+/// removing the attempt.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_invalidate_attempt(this: *mut RunEditor, index: i32) {
+    acc_mut(this).invalidate_attempt(index);
+}
+
+/// Sets the note attached to the attempt with the index provided. The note may
+/// be empty to remove it. Returns `false` if there is no attempt with that
+/// index.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_set_attempt_note(
+    this: *mut RunEditor,
+    index: i32,
+    note: *const c_char,
+) -> bool {
+    acc_mut(this).set_attempt_note(index, str(note))
+}
+
+/// Attaches a tag to the attempt with the index provided. Returns `false` if
+/// there is no attempt with that index.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_add_attempt_tag(
+    this: *mut RunEditor,
+    index: i32,
+    tag: *const c_char,
+) -> bool {
+    acc_mut(this).add_attempt_tag(index, str(tag))
+}
+
+/// Removes a tag from the attempt with the index provided. Returns `false` if
+/// there is no attempt with that index.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_remove_attempt_tag(
+    this: *mut RunEditor,
+    index: i32,
+    tag: *const c_char,
+) -> bool {
+    acc_mut(this).remove_attempt_tag(index, str(tag))
+}
+
 /// Clears out the Attempt History and the Segment Histories of all the
 /// segments.
 #[no_mangle]
@@ -318,6 +512,75 @@ pub unsafe extern "C" fn RunEditor_clear_times(this: *mut RunEditor) {
     acc_mut(this).clear_times();
 }
 
+/// Exports the segment times of the Run as a table. The columns are separated
+/// by tabs if <TRUE> is provided and by commas otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_export_csv(this: *const RunEditor, tabs: bool) -> *const c_char {
+    let delimiter = if tabs {
+        Delimiter::Tab
+    } else {
+        Delimiter::Comma
+    };
+    output_vec(|o| {
+        acc(this).export_csv(o, delimiter).unwrap();
+    })
+}
+
+/// Imports the segment times from a table, like the ones exported by
+/// RunEditor_export_csv. The columns are separated by tabs if <TRUE> is
+/// provided and by commas otherwise. The rows are matched with the segments by
+/// name or by position. Either all the changes are applied or none of them.
+/// Returns <FALSE> if the table couldn't be imported.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_import_csv(
+    this: *mut RunEditor,
+    table: *const c_char,
+    tabs: bool,
+    match_by_name: bool,
+) -> bool {
+    let delimiter = if tabs {
+        Delimiter::Tab
+    } else {
+        Delimiter::Comma
+    };
+    let matching = if match_by_name {
+        Matching::ByName
+    } else {
+        Matching::ByPosition
+    };
+    acc_mut(this)
+        .import_csv(str(table).as_bytes(), delimiter, matching)
+        .is_ok()
+}
+
+/// Checks if there is a change that can be undone.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_can_undo(this: *const RunEditor) -> bool {
+    acc(this).can_undo()
+}
+
+/// Checks if there is an undone change that can be redone.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_can_redo(this: *const RunEditor) -> bool {
+    acc(this).can_redo()
+}
+
+/// Undoes the most recent change made to the Run. This includes all the
+/// changes made through Segment Rows, as well as the ones made with the Sum
+/// of Best Cleaner. If there's nothing to undo, nothing happens.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_undo(this: *mut RunEditor) {
+    acc_mut(this).undo();
+}
+
+/// Redoes the most recently undone change. Any change made to the Run after
+/// undoing discards all the changes that could've been redone. If there's
+/// nothing to redo, nothing happens.
+#[no_mangle]
+pub unsafe extern "C" fn RunEditor_redo(this: *mut RunEditor) {
+    acc_mut(this).redo();
+}
+
 /// Creates a Sum of Best Cleaner which allows you to interactively remove
 /// potential issues in the segment history that lead to an inaccurate Sum
 /// of Best. If you skip a split, whenever you will do the next split, the
@@ -60,6 +60,18 @@ pub unsafe extern "C" fn SplitsComponent_state(
     alloc(acc_mut(this).state(acc(timer), acc(layout_settings)))
 }
 
+/// Sets whether each distinct icon is only provided once in the state objects.
+/// Segments sharing an icon that has already been provided only report the
+/// icon's ID then, so the icon needs to be looked up by its ID. Remounting the
+/// component provides every icon again.
+#[no_mangle]
+pub unsafe extern "C" fn SplitsComponent_set_deduplicate_icons(
+    this: *mut SplitsComponent,
+    deduplicate_icons: bool,
+) {
+    acc_mut(this).set_deduplicate_icons(deduplicate_icons);
+}
+
 /// Scrolls up the window of the segments that are shown. Doesn't move the
 /// scroll window if it reaches the top of the segments.
 #[no_mangle]
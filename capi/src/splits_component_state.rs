@@ -60,6 +60,17 @@ pub unsafe extern "C" fn SplitsComponentState_icon_change_icon(
     output_str(&acc(this).icon_changes[icon_change_index].icon)
 }
 
+/// The ID of the segment's icon of the icon change with the specified index.
+/// Segments sharing the same icon have the same ID. This is 0 if there is no
+/// icon. You may not provide an out of bounds index.
+#[no_mangle]
+pub unsafe extern "C" fn SplitsComponentState_icon_change_icon_id(
+    this: *const SplitsComponentState,
+    icon_change_index: usize,
+) -> usize {
+    acc(this).icon_changes[icon_change_index].icon_id
+}
+
 /// The name of the segment with the specified index. You may not provide an out
 /// of bounds index.
 #[no_mangle]
@@ -197,6 +197,25 @@ pub unsafe extern "C" fn Timer_switch_to_previous_comparison(this: *mut Timer) {
     acc_mut(this).switch_to_previous_comparison();
 }
 
+/// Switches the current comparison to a comparison generated from the
+/// attempt with the index provided. Only a single past attempt can be
+/// compared against at a time. The comparison is not stored in the splits
+/// file. If there's no attempt with the index provided or a custom comparison
+/// already has the name of the attempt's comparison, the current comparison
+/// stays the same and <FALSE> is returned.
+#[no_mangle]
+pub unsafe extern "C" fn Timer_compare_against_attempt(this: *mut Timer, index: i32) -> bool {
+    acc_mut(this).compare_against_attempt(index).is_ok()
+}
+
+/// Removes the comparison of the past attempt that is being compared against,
+/// if there is one. If it is the current comparison, the Personal Best becomes
+/// the current comparison.
+#[no_mangle]
+pub unsafe extern "C" fn Timer_clear_attempt_comparison(this: *mut Timer) {
+    acc_mut(this).clear_attempt_comparison();
+}
+
 /// Returns whether Game Time is currently initialized. Game Time
 /// automatically gets uninitialized for each new attempt.
 #[no_mangle]
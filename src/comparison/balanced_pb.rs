@@ -50,12 +50,20 @@ fn interpolate(
     TimeSpan::from_milliseconds(perc_up + perc_down)
 }
 
-fn generate(
+/// Generates a balanced comparison with the name provided. The goal function
+/// receives the segments that have segment times and determines the amount of
+/// segments to balance, along with the split time the last of these segments
+/// should end at.
+pub(super) fn generate<F>(
     segments: &mut [Segment],
     method: TimingMethod,
+    comparison: &str,
     time_span_buf: &mut Vec<TimeSpan>,
     all_weighted_segment_times: &mut [Vec<(f64, TimeSpan)>],
-) {
+    goal: F,
+) where
+    F: FnOnce(&[Segment]) -> (usize, TimeSpan),
+{
     let mut len = segments.len();
 
     for ((i, segment), weighted_segment_times) in segments
@@ -118,14 +126,8 @@ fn generate(
 
     // Limit the slice to only the segments that have segment times.
     let all_weighted_segment_times = &mut all_weighted_segment_times[..len];
-    // Limit the slice again to the last split that actually has a split time we can work with.
-    let (new_len, goal_time) = segments[..len]
-        .iter()
-        .enumerate()
-        .rev()
-        .filter_map(|(i, s)| s.personal_best_split_time()[method].map(|t| (i + 1, t)))
-        .next()
-        .unwrap_or_default();
+    // Limit the slice again to the segments the goal is for.
+    let (new_len, goal_time) = goal(&segments[..len]);
     let all_weighted_segment_times = &mut all_weighted_segment_times[..new_len];
 
     let (mut perc_min, mut perc_max) = (0.0, 1.0);
@@ -180,10 +182,10 @@ fn generate(
     }
 
     for (segment, &val) in segments.iter_mut().zip(time_span_buf.iter()) {
-        segment.comparison_mut(NAME)[method] = Some(val);
+        segment.comparison_mut(comparison)[method] = Some(val);
     }
     for segment in &mut segments[time_span_buf.len()..] {
-        segment.comparison_mut(NAME)[method] = None;
+        segment.comparison_mut(comparison)[method] = None;
     }
 }
 
@@ -196,17 +198,27 @@ impl ComparisonGenerator for BalancedPB {
         let mut all_weighted_segment_times = vec![Vec::new(); segments.len()];
         let mut time_span_buf = Vec::with_capacity(segments.len());
 
-        generate(
-            segments,
-            TimingMethod::RealTime,
-            &mut time_span_buf,
-            &mut all_weighted_segment_times,
-        );
-        generate(
-            segments,
-            TimingMethod::GameTime,
-            &mut time_span_buf,
-            &mut all_weighted_segment_times,
-        );
+        for &method in &TimingMethod::all() {
+            generate(
+                segments,
+                method,
+                NAME,
+                &mut time_span_buf,
+                &mut all_weighted_segment_times,
+                |segments| {
+                    // Balance up to the last split that actually has a split
+                    // time we can work with.
+                    segments
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .filter_map(|(i, s)| {
+                            s.personal_best_split_time()[method].map(|t| (i + 1, t))
+                        })
+                        .next()
+                        .unwrap_or_default()
+                },
+            );
+        }
     }
 }
@@ -0,0 +1,133 @@
+//! Defines the Comparison Generator for calculating a comparison which ends at
+//! a goal time the runner wants to achieve. All the split times are balanced
+//! by the runner's history the same way the Balanced PB is balanced, so the
+//! comparison describes how the runner would need to perform in each segment
+//! to achieve the goal time.
+
+use super::{ComparisonGenerator, Parameters};
+use super::balanced_pb::generate;
+use {Attempt, Segment, TimeSpan, TimingMethod};
+use time::formatter::{Accuracy, Regular, TimeFormatter};
+
+/// The Comparison Generator for calculating a comparison which ends at a goal
+/// time the runner wants to achieve, like a sub 1:30:00. All the split times
+/// are balanced by the runner's history the same way the Balanced PB is
+/// balanced, so the comparison describes how the runner would need to perform
+/// in each segment to achieve the goal time. The comparison can only be
+/// generated if there are segment times for all the segments.
+///
+/// Any amount of Goal Time generators can be added to a Run. They are stored
+/// in the splits file, so they don't need to be added again when loading the
+/// Run.
+#[derive(Clone, Debug)]
+pub struct GoalTime {
+    goal: TimeSpan,
+    name: String,
+}
+
+const NAME_PREFIX: &str = "Goal (";
+const NAME_SUFFIX: &str = ")";
+
+impl GoalTime {
+    /// Creates a new Goal Time generator for the goal time provided. The goal
+    /// time is used for both timing methods.
+    pub fn new(goal: TimeSpan) -> Self {
+        let accuracy = if goal.total_milliseconds() % 1000.0 == 0.0 {
+            Accuracy::Seconds
+        } else {
+            Accuracy::Hundredths
+        };
+        let name = format!(
+            "{}{}{}",
+            NAME_PREFIX,
+            Regular::with_accuracy(accuracy).format(goal),
+            NAME_SUFFIX
+        );
+
+        Self {
+            goal: goal,
+            name: name,
+        }
+    }
+
+    /// Recreates the Goal Time generator that generates the comparison with
+    /// the name provided. If the name doesn't belong to a Goal Time generator,
+    /// `None` is returned.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if !name.starts_with(NAME_PREFIX) || !name.ends_with(NAME_SUFFIX) {
+            return None;
+        }
+        let goal = name[NAME_PREFIX.len()..name.len() - NAME_SUFFIX.len()]
+            .parse()
+            .ok()?;
+        let generator = GoalTime::new(goal);
+        if generator.name == name {
+            Some(generator)
+        } else {
+            None
+        }
+    }
+
+    /// Accesses the goal time the comparison ends at.
+    pub fn goal(&self) -> TimeSpan {
+        self.goal
+    }
+}
+
+/// Shortens the name of a comparison generated by a Goal Time generator. If
+/// the name doesn't belong to a Goal Time generator, `None` is returned.
+pub fn short_name(name: &str) -> Option<String> {
+    GoalTime::from_name(name)?;
+    let goal = &name[NAME_PREFIX.len()..name.len() - NAME_SUFFIX.len()];
+    Some(format!("Goal {}", goal))
+}
+
+impl ComparisonGenerator for GoalTime {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, segments: &mut [Segment], _: &[Attempt]) {
+        let mut all_weighted_segment_times = vec![Vec::new(); segments.len()];
+        let mut time_span_buf = Vec::with_capacity(segments.len());
+        let (len, goal) = (segments.len(), self.goal);
+
+        for &method in &TimingMethod::all() {
+            generate(
+                segments,
+                method,
+                &self.name,
+                &mut time_span_buf,
+                &mut all_weighted_segment_times,
+                |segments| {
+                    if segments.len() == len {
+                        (len, goal)
+                    } else {
+                        Default::default()
+                    }
+                },
+            );
+
+            // The balancing only approximates the goal time and can't reach
+            // it at all if it's out of reach of the segment times, so the
+            // split times are scaled to end exactly at the goal time.
+            let last = catch! { segments.last()?.comparison(&self.name)[method]? };
+            if let Some(last) = last {
+                if last != goal && last > TimeSpan::zero() {
+                    let factor = goal.total_seconds() / last.total_seconds();
+                    for segment in segments.iter_mut() {
+                        let time = &mut segment.comparison_mut(&self.name)[method];
+                        *time = time.map(|t| TimeSpan::from_seconds(factor * t.total_seconds()));
+                    }
+                }
+                if let Some(segment) = segments.last_mut() {
+                    segment.comparison_mut(&self.name)[method] = Some(goal);
+                }
+            }
+        }
+    }
+
+    fn parameters(&self) -> Option<Parameters> {
+        Some(Parameters::GoalTime { goal: self.goal })
+    }
+}
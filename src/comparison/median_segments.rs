@@ -0,0 +1,34 @@
+//! Defines the Comparison Generator for calculating the Median Segments of a
+//! Run. The Median Segments are calculated through a weighted median that gives
+//! more recent segments a larger weight so that the Median Segments are more
+//! suited to represent the current performance of a runner. Unlike the Average
+//! Segments, the Median Segments are not skewed by a few disastrous segments.
+
+use super::ComparisonGenerator;
+use super::percentile_segments::generate;
+use {Attempt, Segment, TimingMethod};
+
+/// The Comparison Generator for calculating the Median Segments of a Run. The
+/// Median Segments are calculated through a weighted median that gives more
+/// recent segments a larger weight so that the Median Segments are more suited
+/// to represent the current performance of a runner. Unlike the Average
+/// Segments, the Median Segments are not skewed by a few disastrous segments.
+#[derive(Copy, Clone, Debug)]
+pub struct MedianSegments;
+
+/// The short name of this comparison. Suitable for situations where not a lot
+/// of space for text is available.
+pub const SHORT_NAME: &str = "Median";
+/// The name of this comparison.
+pub const NAME: &str = "Median Segments";
+
+impl ComparisonGenerator for MedianSegments {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn generate(&mut self, segments: &mut [Segment], _: &[Attempt]) {
+        generate(segments, TimingMethod::RealTime, NAME, 0.5);
+        generate(segments, TimingMethod::GameTime, NAME, 0.5);
+    }
+}
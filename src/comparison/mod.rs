@@ -10,20 +10,32 @@ pub mod average_segments;
 pub mod balanced_pb;
 pub mod best_segments;
 pub mod best_split_times;
+pub mod goal_time;
 pub mod latest_run;
+pub mod median_segments;
 pub mod none;
+pub mod past_attempt;
+pub mod percentile_segments;
+pub mod recent_attempts;
 pub mod worst_segments;
 
 pub use self::average_segments::AverageSegments;
 pub use self::balanced_pb::BalancedPB;
 pub use self::best_segments::BestSegments;
 pub use self::best_split_times::BestSplitTimes;
+pub use self::goal_time::GoalTime;
 pub use self::latest_run::LatestRun;
+pub use self::median_segments::MedianSegments;
 pub use self::none::None;
+pub use self::past_attempt::PastAttempt;
+pub use self::percentile_segments::PercentileSegments;
+pub use self::recent_attempts::RecentAttempts;
 pub use self::worst_segments::WorstSegments;
 
+use std::borrow::Cow;
 use std::fmt::Debug;
-use {Attempt, Segment, Timer};
+use {Attempt, Segment, TimeSpan, Timer};
+use self::recent_attempts::{Kind, Window};
 
 /// Defines the Personal Best comparison. This module mostly just serves for
 /// providing the names of the comparison, as the Personal Best is not a
@@ -47,6 +59,37 @@ pub trait ComparisonGenerator: Debug + Sync + Send + ComparisonGeneratorClone {
     /// provided, in case the comparison generator requires information from the
     /// previous attempts.
     fn generate(&mut self, segments: &mut [Segment], attempts: &[Attempt]);
+    /// The parameters the comparison generator is stored with in splits files.
+    /// Comparison generators that aren't stored in splits files don't have any.
+    fn parameters(&self) -> Option<Parameters> {
+        Option::None
+    }
+}
+
+/// The parameters of a Comparison Generator that is stored in splits files.
+/// They are everything that is needed to recreate the Comparison Generator
+/// when the splits file is loaded again.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Parameters {
+    /// The parameters of a Percentile Segments generator.
+    PercentileSegments {
+        /// The percentile of the segment times that the comparison consists of.
+        percentile: u8,
+    },
+    /// The parameters of a Goal Time generator.
+    GoalTime {
+        /// The goal time the comparison ends at.
+        goal: TimeSpan,
+    },
+    /// The parameters of a Recent Attempts generator.
+    RecentAttempts {
+        /// The kind of comparison that is calculated out of the recent
+        /// attempts.
+        kind: Kind,
+        /// The window that determines which attempts are considered to be
+        /// recent.
+        window: Window,
+    },
 }
 
 /// Provides the ability to clone a Comparison Generator, even when it is stored
@@ -78,6 +121,7 @@ pub fn default_generators() -> Vec<Box<ComparisonGenerator>> {
         Box::new(BestSegments),
         Box::new(BestSplitTimes),
         Box::new(AverageSegments),
+        Box::new(MedianSegments),
         Box::new(WorstSegments),
         Box::new(BalancedPB),
         Box::new(LatestRun),
@@ -87,20 +131,26 @@ pub fn default_generators() -> Vec<Box<ComparisonGenerator>> {
 
 /// Shortens a comparison name. If the name of the comparison matches one of the
 /// comparison generators, the short name of that comparison generator is
-/// returned. Otherwise the comparison name is returned without being shortened.
-/// Additional shortening logic for other comparison names may happen in the
-/// future.
-pub fn shorten(comparison: &str) -> &str {
+/// returned. This includes the comparisons of the Percentile Segments, Goal
+/// Time and Recent Attempts generators. Otherwise the comparison name is
+/// returned without being shortened. Additional shortening logic for other
+/// comparison names may happen in the future.
+pub fn shorten(comparison: &str) -> Cow<str> {
     match comparison {
-        personal_best::NAME => personal_best::SHORT_NAME,
-        average_segments::NAME => average_segments::SHORT_NAME,
-        balanced_pb::NAME => balanced_pb::SHORT_NAME,
-        best_segments::NAME => best_segments::SHORT_NAME,
-        best_split_times::NAME => best_split_times::SHORT_NAME,
-        latest_run::NAME => latest_run::SHORT_NAME,
-        none::NAME => none::SHORT_NAME,
-        worst_segments::NAME => worst_segments::SHORT_NAME,
-        c => c,
+        personal_best::NAME => personal_best::SHORT_NAME.into(),
+        average_segments::NAME => average_segments::SHORT_NAME.into(),
+        balanced_pb::NAME => balanced_pb::SHORT_NAME.into(),
+        best_segments::NAME => best_segments::SHORT_NAME.into(),
+        best_split_times::NAME => best_split_times::SHORT_NAME.into(),
+        latest_run::NAME => latest_run::SHORT_NAME.into(),
+        median_segments::NAME => median_segments::SHORT_NAME.into(),
+        none::NAME => none::SHORT_NAME.into(),
+        worst_segments::NAME => worst_segments::SHORT_NAME.into(),
+        c => percentile_segments::short_name(c)
+            .map(Cow::Borrowed)
+            .or_else(|| goal_time::short_name(c).map(Cow::Owned))
+            .or_else(|| recent_attempts::short_name(c).map(Cow::Owned))
+            .unwrap_or(Cow::Borrowed(c)),
     }
 }
 
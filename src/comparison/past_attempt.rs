@@ -0,0 +1,67 @@
+//! Defines the Comparison Generator for calculating a comparison out of any
+//! specific past attempt. Using the Segment History, this comparison
+//! reconstructs the splits of the attempt, regardless of whether it was
+//! finished or not.
+
+use super::ComparisonGenerator;
+use super::latest_run::generate_attempt;
+use {Attempt, Segment, TimingMethod};
+
+/// The Comparison Generator for calculating a comparison out of any specific
+/// past attempt, like the second best run or the best run of yesterday. Using
+/// the Segment History, this comparison reconstructs the splits of the
+/// attempt, regardless of whether it was finished or not. The split times
+/// after the last segment the attempt reached are empty.
+///
+/// Past Attempt generators are not stored in the splits file. A Timer can
+/// compare against a past attempt through `Timer::compare_against_attempt`.
+#[derive(Clone, Debug)]
+pub struct PastAttempt {
+    index: i32,
+    name: String,
+}
+
+const NAME_PREFIX: &str = "Attempt #";
+
+impl PastAttempt {
+    /// Creates a new Past Attempt generator for the attempt with the index
+    /// provided.
+    pub fn new(index: i32) -> Self {
+        Self {
+            index: index,
+            name: format!("{}{}", NAME_PREFIX, index),
+        }
+    }
+
+    /// Recreates the Past Attempt generator that generates the comparison
+    /// with the name provided. If the name doesn't belong to a Past Attempt
+    /// generator, `None` is returned.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if !name.starts_with(NAME_PREFIX) {
+            return None;
+        }
+        let index = name[NAME_PREFIX.len()..].parse().ok()?;
+        let generator = PastAttempt::new(index);
+        if generator.name == name {
+            Some(generator)
+        } else {
+            None
+        }
+    }
+
+    /// Accesses the index of the attempt the comparison is generated from.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+}
+
+impl ComparisonGenerator for PastAttempt {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, segments: &mut [Segment], _: &[Attempt]) {
+        generate_attempt(segments, TimingMethod::RealTime, &self.name, self.index);
+        generate_attempt(segments, TimingMethod::GameTime, &self.name, self.index);
+    }
+}
@@ -0,0 +1,173 @@
+//! Defines the Comparison Generator for calculating a percentile of the segment
+//! times of a Run, like the top 10% or the bottom 25% of the segments. The
+//! percentile is calculated through a weighted percentile that gives more
+//! recent segments a larger weight so that the comparison is more suited to
+//! represent the current performance of a runner.
+
+use super::{ComparisonGenerator, Parameters};
+use {Attempt, Segment, TimeSpan, TimingMethod};
+
+/// The Comparison Generator for calculating a percentile of the segment times
+/// of a Run, like the top 10% or the bottom 25% of the segments. A low
+/// percentile describes fast segments, while a high percentile describes slow
+/// segments. The percentile is calculated through a weighted percentile that
+/// gives more recent segments a larger weight so that the comparison is more
+/// suited to represent the current performance of a runner.
+///
+/// Unlike the other Comparison Generators, any amount of Percentile Segments
+/// generators can be added to a Run. They are stored in the splits file, so
+/// they don't need to be added again when loading the Run.
+#[derive(Clone, Debug)]
+pub struct PercentileSegments {
+    percentile: u8,
+    name: String,
+}
+
+const NAME_SUFFIX: &str = " Percentile Segments";
+const SHORT_NAME_SUFFIX: &str = " Percentile";
+
+const WEIGHT: f64 = 0.75;
+
+impl PercentileSegments {
+    /// Creates a new Percentile Segments generator for the percentile
+    /// provided. Percentiles above 100 are treated as 100.
+    pub fn new(percentile: u8) -> Self {
+        let percentile = percentile.min(100);
+        let ordinal_suffix = match (percentile % 10, percentile % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        };
+
+        Self {
+            percentile: percentile,
+            name: format!("{}{}{}", percentile, ordinal_suffix, NAME_SUFFIX),
+        }
+    }
+
+    /// Recreates the Percentile Segments generator that generates the
+    /// comparison with the name provided. If the name doesn't belong to a
+    /// Percentile Segments generator, `None` is returned.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if !name.ends_with(NAME_SUFFIX) {
+            return None;
+        }
+        let ordinal = &name[..name.len() - NAME_SUFFIX.len()];
+        let percentile = ordinal
+            .trim_end_matches(|c: char| c.is_alphabetic())
+            .parse()
+            .ok()?;
+        let generator = PercentileSegments::new(percentile);
+        if generator.name == name {
+            Some(generator)
+        } else {
+            None
+        }
+    }
+
+    /// Accesses the percentile of the segment times this generator calculates.
+    pub fn percentile(&self) -> u8 {
+        self.percentile
+    }
+}
+
+/// Shortens the name of a comparison generated by a Percentile Segments
+/// generator. If the name doesn't belong to a Percentile Segments generator,
+/// `None` is returned.
+pub fn short_name(name: &str) -> Option<&str> {
+    PercentileSegments::from_name(name)?;
+    let short_len = name.len() - NAME_SUFFIX.len() + SHORT_NAME_SUFFIX.len();
+    Some(&name[..short_len])
+}
+
+/// Calculates the weighted percentile of the segment times provided. Each
+/// segment time is placed at the midpoint of its weight and the percentile is
+/// linearly interpolated between the two segment times surrounding it.
+fn weighted_percentile(times: &mut [(f64, TimeSpan)], percentile: f64) -> TimeSpan {
+    times.sort_by_key(|&(_, time)| time);
+
+    let total_weights = times.iter().map(|&(weight, _)| weight).sum::<f64>();
+    let mut accumulated_weights = 0.0;
+    let mut previous: Option<(f64, TimeSpan)> = None;
+
+    for &(weight, time) in times.iter() {
+        let position = (accumulated_weights + 0.5 * weight) / total_weights;
+        accumulated_weights += weight;
+
+        if position >= percentile {
+            return match previous {
+                Some((previous_position, previous_time)) => {
+                    let factor = (percentile - previous_position) / (position - previous_position);
+                    let difference = (time - previous_time).total_seconds();
+                    previous_time + TimeSpan::from_seconds(factor * difference)
+                }
+                None => time,
+            };
+        }
+
+        previous = Some((position, time));
+    }
+
+    times[times.len() - 1].1
+}
+
+/// Generates the comparison with the name provided by accumulating the
+/// weighted percentiles of the segment times. The percentile needs to be
+/// between 0 and 1.
+pub(super) fn generate(
+    segments: &mut [Segment],
+    method: TimingMethod,
+    comparison: &str,
+    percentile: f64,
+) {
+    let mut accumulated = Some(TimeSpan::zero());
+    let mut times = Vec::new();
+
+    for i in 0..segments.len() {
+        if let Some(accumulated_time) = accumulated {
+            times.clear();
+            let mut current_weight = 1.0;
+
+            for &(id, time) in segments[i].segment_history().iter_actual_runs().rev() {
+                if let Some(time) = time[method] {
+                    // Skip all the combined segments
+                    let skip = catch! {
+                        segments[i.checked_sub(1)?].segment_history().get(id)?[method].is_none()
+                    }.unwrap_or(false);
+
+                    if !skip {
+                        times.push((current_weight, time));
+                        current_weight *= WEIGHT;
+                    }
+                }
+            }
+
+            accumulated = if times.is_empty() {
+                None
+            } else {
+                Some(accumulated_time + weighted_percentile(&mut times, percentile))
+            };
+        }
+        segments[i].comparison_mut(comparison)[method] = accumulated;
+    }
+}
+
+impl ComparisonGenerator for PercentileSegments {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, segments: &mut [Segment], _: &[Attempt]) {
+        let percentile = f64::from(self.percentile) / 100.0;
+        generate(segments, TimingMethod::RealTime, &self.name, percentile);
+        generate(segments, TimingMethod::GameTime, &self.name, percentile);
+    }
+
+    fn parameters(&self) -> Option<Parameters> {
+        Some(Parameters::PercentileSegments {
+            percentile: self.percentile,
+        })
+    }
+}
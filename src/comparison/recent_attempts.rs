@@ -0,0 +1,277 @@
+//! Defines the Comparison Generator for calculating a comparison out of only
+//! the most recent attempts of a Run, like the last 20 attempts or the
+//! attempts of the last 7 days. The all-time Best Segments and Personal Best
+//! may be out of reach for a runner coming back after a break, so these
+//! comparisons describe the runner's current performance instead.
+
+use super::{average_segments, balanced_pb, best_segments, personal_best, AverageSegments,
+            BalancedPB, BestSegments, ComparisonGenerator, Parameters};
+use super::latest_run::generate_attempt;
+use {Attempt, AtomicDateTime, Segment, TimeSpan, TimingMethod};
+
+/// Describes which comparison is calculated out of the recent attempts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// The Best Segments of the recent attempts.
+    BestSegments,
+    /// The Average Segments of the recent attempts.
+    AverageSegments,
+    /// The Balanced PB of the recent attempts. The best finished attempt out
+    /// of the recent attempts is used as the Personal Best to balance.
+    BalancedPB,
+}
+
+impl Kind {
+    /// Returns the name of the comparison that is calculated out of the
+    /// recent attempts.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::BestSegments => best_segments::NAME,
+            Kind::AverageSegments => average_segments::NAME,
+            Kind::BalancedPB => balanced_pb::NAME,
+        }
+    }
+
+    /// Returns the short name of the comparison that is calculated out of the
+    /// recent attempts.
+    pub fn short_name(&self) -> &'static str {
+        match *self {
+            Kind::BestSegments => best_segments::SHORT_NAME,
+            Kind::AverageSegments => average_segments::SHORT_NAME,
+            Kind::BalancedPB => balanced_pb::SHORT_NAME,
+        }
+    }
+
+    /// Parses the kind from the name of the comparison that is calculated out
+    /// of the recent attempts. If the name is not known, `None` is returned.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            best_segments::NAME => Some(Kind::BestSegments),
+            average_segments::NAME => Some(Kind::AverageSegments),
+            balanced_pb::NAME => Some(Kind::BalancedPB),
+            _ => None,
+        }
+    }
+}
+
+/// Describes which attempts are considered to be recent. Attempts that are
+/// invalid or that don't have any segment times are never considered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Window {
+    /// The given amount of the most recent attempts are considered.
+    Attempts(u32),
+    /// The attempts that ended within the given amount of days are
+    /// considered. If an attempt has no end date, its start date is used
+    /// instead. Attempts without any date are never considered.
+    Days(u32),
+}
+
+/// The Comparison Generator for calculating a comparison out of only the most
+/// recent attempts of a Run, like the Best Segments of the last 20 attempts or
+/// the Average Segments of the last 7 days. The all-time Best Segments and
+/// Personal Best may be out of reach for a runner coming back after a break,
+/// so these comparisons describe the runner's current performance instead.
+///
+/// Any amount of Recent Attempts generators can be added to a Run. They are
+/// stored in the splits file, so they don't need to be added again when
+/// loading the Run.
+#[derive(Clone, Debug)]
+pub struct RecentAttempts {
+    kind: Kind,
+    window: Window,
+    name: String,
+}
+
+const NAME_INFIX: &str = " (Last ";
+const NAME_SUFFIX: &str = ")";
+
+impl RecentAttempts {
+    /// Creates a new Recent Attempts generator that calculates the kind of
+    /// comparison provided out of the attempts within the window provided.
+    pub fn new(kind: Kind, window: Window) -> Self {
+        let (count, unit) = match window {
+            Window::Attempts(count) => (count, "Attempt"),
+            Window::Days(count) => (count, "Day"),
+        };
+        let plural = if count == 1 { "" } else { "s" };
+
+        Self {
+            kind: kind,
+            window: window,
+            name: format!(
+                "{}{}{} {}{}{}",
+                kind.name(),
+                NAME_INFIX,
+                count,
+                unit,
+                plural,
+                NAME_SUFFIX
+            ),
+        }
+    }
+
+    /// Recreates the Recent Attempts generator that generates the comparison
+    /// with the name provided. If the name doesn't belong to a Recent Attempts
+    /// generator, `None` is returned.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if !name.ends_with(NAME_SUFFIX) {
+            return None;
+        }
+        let infix_index = name.find(NAME_INFIX)?;
+        let kind = Kind::from_name(&name[..infix_index])?;
+
+        let window = &name[infix_index + NAME_INFIX.len()..name.len() - NAME_SUFFIX.len()];
+        let space_index = window.find(' ')?;
+        let count = window[..space_index].parse().ok()?;
+        let window = match window[space_index + 1..].trim_end_matches('s') {
+            "Attempt" => Window::Attempts(count),
+            "Day" => Window::Days(count),
+            _ => return None,
+        };
+
+        let generator = RecentAttempts::new(kind, window);
+        if generator.name == name {
+            Some(generator)
+        } else {
+            None
+        }
+    }
+
+    /// Accesses the kind of comparison that is calculated out of the recent
+    /// attempts.
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Accesses the window that determines which attempts are considered to
+    /// be recent.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// Collects the sorted indices of the attempts within the window.
+    fn recent_attempts(&self, segments: &[Segment], attempts: &[Attempt]) -> Vec<i32> {
+        let attempts = attempts.iter().filter(|a| {
+            !a.is_invalid()
+                && segments
+                    .iter()
+                    .any(|s| s.segment_history().get(a.index()).is_some())
+        });
+        let mut indices = match self.window {
+            Window::Attempts(count) => {
+                let mut indices = attempts.map(Attempt::index).collect::<Vec<_>>();
+                indices.sort_unstable();
+                let skip = indices.len().saturating_sub(count as usize);
+                indices.split_off(skip)
+            }
+            Window::Days(days) => {
+                let now = AtomicDateTime::now();
+                let max_age = TimeSpan::from_days(f64::from(days));
+                attempts
+                    .filter(|a| {
+                        a.ended()
+                            .or_else(|| a.started())
+                            .map_or(false, |date| now - date <= max_age)
+                    })
+                    .map(Attempt::index)
+                    .collect()
+            }
+        };
+        indices.sort_unstable();
+        indices
+    }
+}
+
+/// Shortens the name of a comparison generated by a Recent Attempts generator
+/// by using the short name of the kind of comparison. If the name doesn't
+/// belong to a Recent Attempts generator, `None` is returned.
+pub fn short_name(name: &str) -> Option<String> {
+    let generator = RecentAttempts::from_name(name)?;
+    let kind_name = generator.kind.name();
+    Some(format!(
+        "{}{}",
+        generator.kind.short_name(),
+        &name[kind_name.len()..]
+    ))
+}
+
+/// Updates the Best Segment Times and the Personal Best split times of the
+/// segments to the ones of the recent attempts, which are the only attempts
+/// left in the Segment Histories.
+fn fix_segments(segments: &mut [Segment], attempts: &[Attempt], method: TimingMethod) {
+    for i in 0..segments.len() {
+        let best_segment_time = segments[i]
+            .segment_history()
+            .iter()
+            .filter(|&&(id, _)| {
+                // Skip all the combined segments
+                !catch! {
+                    segments[i.checked_sub(1)?].segment_history().get(id)?[method].is_none()
+                }.unwrap_or(false)
+            })
+            .filter_map(|&(_, time)| time[method])
+            .min();
+        segments[i].best_segment_time_mut()[method] = best_segment_time;
+    }
+
+    let best_attempt = attempts
+        .iter()
+        .filter(|a| segments.iter().all(|s| s.segment_history().get(a.index()).is_some()))
+        .filter_map(|a| a.time()[method].map(|t| (t, a.index())))
+        .min_by_key(|&(time, _)| time);
+
+    if let Some((_, index)) = best_attempt {
+        generate_attempt(segments, method, personal_best::NAME, index);
+    }
+}
+
+impl ComparisonGenerator for RecentAttempts {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, segments: &mut [Segment], attempts: &[Attempt]) {
+        let indices = self.recent_attempts(segments, attempts);
+        let recent_attempts = attempts
+            .iter()
+            .filter(|a| indices.binary_search(&a.index()).is_ok())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut recent_segments = segments
+            .iter()
+            .map(|segment| {
+                let mut recent_segment = Segment::new("");
+                for &(id, time) in segment.segment_history().iter() {
+                    if indices.binary_search(&id).is_ok() {
+                        recent_segment.segment_history_mut().insert(id, time);
+                    }
+                }
+                recent_segment
+            })
+            .collect::<Vec<_>>();
+
+        for &method in &TimingMethod::all() {
+            fix_segments(&mut recent_segments, &recent_attempts, method);
+        }
+
+        match self.kind {
+            Kind::BestSegments => BestSegments.generate(&mut recent_segments, &recent_attempts),
+            Kind::AverageSegments => {
+                AverageSegments.generate(&mut recent_segments, &recent_attempts)
+            }
+            Kind::BalancedPB => BalancedPB.generate(&mut recent_segments, &recent_attempts),
+        }
+
+        for (segment, recent_segment) in segments.iter_mut().zip(&recent_segments) {
+            *segment.comparison_mut(&self.name) = recent_segment.comparison(self.kind.name());
+        }
+    }
+
+    fn parameters(&self) -> Option<Parameters> {
+        Some(Parameters::RecentAttempts {
+            kind: self.kind,
+            window: self.window,
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use TimeSpan;
 use comparison::{self, ComparisonGenerator};
 
 fn test<T: ComparisonGenerator>(mut generator: T) {
@@ -24,11 +25,39 @@ fn best_split_times() {
     test(comparison::BestSplitTimes);
 }
 
+#[test]
+fn goal_time() {
+    test(comparison::GoalTime::new(TimeSpan::from_seconds(60.0)));
+}
+
 #[test]
 fn latest_run() {
     test(comparison::LatestRun);
 }
 
+#[test]
+fn median_segments() {
+    test(comparison::MedianSegments);
+}
+
+#[test]
+fn past_attempt() {
+    test(comparison::PastAttempt::new(1));
+}
+
+#[test]
+fn percentile_segments() {
+    test(comparison::PercentileSegments::new(50));
+}
+
+#[test]
+fn recent_attempts() {
+    test(comparison::RecentAttempts::new(
+        comparison::recent_attempts::Kind::BalancedPB,
+        comparison::recent_attempts::Window::Attempts(10),
+    ));
+}
+
 #[test]
 fn none() {
     test(comparison::None);
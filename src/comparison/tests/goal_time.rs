@@ -0,0 +1,66 @@
+use {Run, Segment, TimeSpan, Timer};
+use comparison::{self, ComparisonGenerator, GoalTime};
+use super::run_with_splits;
+
+#[test]
+fn names() {
+    let s = TimeSpan::from_seconds;
+
+    for &(goal, name) in &[
+        (5400.0, "Goal (1:30:00)"),
+        (59.0, "Goal (0:59)"),
+        (3599.5, "Goal (59:59.50)"),
+    ] {
+        assert_eq!(GoalTime::new(s(goal)).name(), name);
+        assert_eq!(GoalTime::from_name(name).map(|g| g.goal()), Some(s(goal)));
+    }
+
+    assert!(GoalTime::from_name("Goal (1:30:00.00)").is_none());
+    assert!(GoalTime::from_name("Goal (abc)").is_none());
+    assert!(GoalTime::from_name("Balanced PB").is_none());
+    assert_eq!(comparison::shorten("Goal (1:30:00)"), "Goal 1:30:00");
+}
+
+#[test]
+fn ends_at_goal() {
+    let s = TimeSpan::from_seconds;
+
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+
+    run.comparison_generators_mut().clear();
+    run.comparison_generators_mut()
+        .push(Box::new(GoalTime::new(s(20.0))));
+
+    let mut timer = Timer::new(run).unwrap();
+
+    run_with_splits(&mut timer, &[10.0, 30.0]);
+    run_with_splits(&mut timer, &[12.0, 34.0]);
+
+    let run = timer.run();
+    let name = "Goal (0:20)";
+    let first = run.segment(0).comparison(name).game_time.unwrap();
+    assert!(first > s(5.0) && first < s(10.0));
+    assert_eq!(run.segment(1).comparison(name).game_time, Some(s(20.0)));
+}
+
+#[test]
+fn needs_all_segments() {
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+
+    run.comparison_generators_mut().clear();
+    run.comparison_generators_mut()
+        .push(Box::new(GoalTime::new(TimeSpan::from_seconds(20.0))));
+
+    let mut timer = Timer::new(run).unwrap();
+
+    run_with_splits(&mut timer, &[10.0]);
+
+    let run = timer.run();
+    assert_eq!(run.segment(1).comparison("Goal (0:20)").game_time, None);
+}
@@ -0,0 +1,53 @@
+use {Run, Segment, TimeSpan, Timer};
+use comparison::median_segments::{MedianSegments, NAME};
+use super::run_with_splits;
+
+#[test]
+fn ignores_disastrous_segments() {
+    let s = TimeSpan::from_seconds;
+
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+    run.push_segment(Segment::new("Third"));
+
+    run.comparison_generators_mut().clear();
+    run.comparison_generators_mut().push(Box::new(MedianSegments));
+
+    let mut timer = Timer::new(run).unwrap();
+
+    run_with_splits(&mut timer, &[1.0, 2.0, 3.0]);
+
+    {
+        let run = timer.run();
+        assert_eq!(run.segment(0).comparison(NAME).game_time, Some(s(1.0)));
+        assert_eq!(run.segment(1).comparison(NAME).game_time, Some(s(2.0)));
+        assert_eq!(run.segment(2).comparison(NAME).game_time, Some(s(3.0)));
+    }
+
+    run_with_splits(&mut timer, &[1.0, 2.0, 3.0]);
+    run_with_splits(&mut timer, &[1.0, 2.0, 3.0]);
+    run_with_splits(&mut timer, &[1.0, 2.0, 30.0]);
+
+    {
+        let run = timer.run();
+        assert_eq!(run.segment(0).comparison(NAME).game_time, Some(s(1.0)));
+        assert_eq!(run.segment(1).comparison(NAME).game_time, Some(s(2.0)));
+        assert_eq!(run.segment(2).comparison(NAME).game_time, Some(s(3.0)));
+    }
+
+    run_with_splits(&mut timer, &[1.0, 2.0, 30.0]);
+    run_with_splits(&mut timer, &[1.0, 2.0, 30.0]);
+
+    {
+        let run = timer.run();
+        assert_eq!(run.segment(2).comparison(NAME).game_time, Some(s(30.0)));
+    }
+}
+
+#[test]
+fn is_a_default_generator() {
+    let run = Run::new();
+    assert!(run.comparisons().any(|c| c == NAME));
+}
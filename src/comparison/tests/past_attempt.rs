@@ -0,0 +1,42 @@
+use {Run, Segment, TimeSpan, Timer};
+use comparison::{ComparisonGenerator, PastAttempt};
+
+#[test]
+fn names() {
+    assert_eq!(PastAttempt::new(12).name(), "Attempt #12");
+    assert_eq!(PastAttempt::from_name("Attempt #12").map(|g| g.index()), Some(12));
+    assert!(PastAttempt::from_name("Attempt #012").is_none());
+    assert!(PastAttempt::from_name("Attempt #").is_none());
+    assert!(PastAttempt::from_name("Latest Run").is_none());
+}
+
+#[test]
+fn reconstructs_unfinished_attempt() {
+    let s = TimeSpan::from_seconds;
+
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+    run.push_segment(Segment::new("Third"));
+
+    run.comparison_generators_mut().clear();
+    run.comparison_generators_mut()
+        .push(Box::new(PastAttempt::new(1)));
+
+    let mut timer = Timer::new(run).unwrap();
+
+    timer.start();
+    timer.initialize_game_time();
+    timer.pause_game_time();
+    timer.set_game_time(s(1.0));
+    timer.split();
+    timer.set_game_time(s(3.0));
+    timer.split();
+    timer.reset(true);
+
+    let run = timer.run();
+    assert_eq!(run.segment(0).comparison("Attempt #1").game_time, Some(s(1.0)));
+    assert_eq!(run.segment(1).comparison("Attempt #1").game_time, Some(s(3.0)));
+    assert_eq!(run.segment(2).comparison("Attempt #1").game_time, None);
+}
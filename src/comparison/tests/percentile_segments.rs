@@ -0,0 +1,55 @@
+use {Run, Segment, TimeSpan, Timer};
+use comparison::{self, ComparisonGenerator, PercentileSegments};
+use super::run_with_splits;
+
+#[test]
+fn names() {
+    for &(percentile, name) in &[
+        (1, "1st Percentile Segments"),
+        (2, "2nd Percentile Segments"),
+        (3, "3rd Percentile Segments"),
+        (10, "10th Percentile Segments"),
+        (12, "12th Percentile Segments"),
+        (22, "22nd Percentile Segments"),
+        (100, "100th Percentile Segments"),
+    ] {
+        assert_eq!(PercentileSegments::new(percentile).name(), name);
+        assert_eq!(
+            PercentileSegments::from_name(name).map(|g| g.percentile()),
+            Some(percentile)
+        );
+    }
+
+    assert!(PercentileSegments::from_name("22th Percentile Segments").is_none());
+    assert!(PercentileSegments::from_name("Median Segments").is_none());
+    assert_eq!(comparison::shorten("10th Percentile Segments"), "10th Percentile");
+}
+
+#[test]
+fn generates_percentiles() {
+    let s = TimeSpan::from_seconds;
+
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+
+    run.comparison_generators_mut().clear();
+    run.comparison_generators_mut()
+        .push(Box::new(PercentileSegments::new(0)));
+    run.comparison_generators_mut()
+        .push(Box::new(PercentileSegments::new(100)));
+
+    let mut timer = Timer::new(run).unwrap();
+
+    run_with_splits(&mut timer, &[1.0, 3.0]);
+    run_with_splits(&mut timer, &[2.0, 3.0]);
+
+    let run = timer.run();
+    let fastest = "0th Percentile Segments";
+    let slowest = "100th Percentile Segments";
+    assert_eq!(run.segment(0).comparison(fastest).game_time, Some(s(1.0)));
+    assert_eq!(run.segment(1).comparison(fastest).game_time, Some(s(2.0)));
+    assert_eq!(run.segment(0).comparison(slowest).game_time, Some(s(2.0)));
+    assert_eq!(run.segment(1).comparison(slowest).game_time, Some(s(4.0)));
+}
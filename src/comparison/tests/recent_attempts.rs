@@ -0,0 +1,146 @@
+use chrono::{Duration, Utc};
+use {AtomicDateTime, Run, Segment, Time, TimeSpan, Timer};
+use comparison::{self, ComparisonGenerator, RecentAttempts};
+use comparison::recent_attempts::{Kind, Window};
+use super::run_with_splits;
+
+fn timer_with(generators: &[RecentAttempts]) -> Timer {
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+
+    run.comparison_generators_mut().clear();
+    for generator in generators {
+        run.comparison_generators_mut()
+            .push(Box::new(generator.clone()));
+    }
+
+    let mut timer = Timer::new(run).unwrap();
+
+    run_with_splits(&mut timer, &[1.0, 3.0]);
+    run_with_splits(&mut timer, &[2.0, 3.0]);
+    run_with_splits(&mut timer, &[5.0, 8.0]);
+
+    timer
+}
+
+#[test]
+fn names() {
+    for &(kind, window, name) in &[
+        (
+            Kind::BestSegments,
+            Window::Attempts(20),
+            "Best Segments (Last 20 Attempts)",
+        ),
+        (
+            Kind::AverageSegments,
+            Window::Days(7),
+            "Average Segments (Last 7 Days)",
+        ),
+        (Kind::BalancedPB, Window::Days(1), "Balanced PB (Last 1 Day)"),
+    ] {
+        let generator = RecentAttempts::new(kind, window);
+        assert_eq!(generator.name(), name);
+
+        let generator = RecentAttempts::from_name(name).unwrap();
+        assert_eq!(generator.kind(), kind);
+        assert_eq!(generator.window(), window);
+    }
+
+    assert_eq!(
+        comparison::shorten("Best Segments (Last 20 Attempts)"),
+        "Best (Last 20 Attempts)"
+    );
+
+    assert!(RecentAttempts::from_name("Best Segments (Last 1 Days)").is_none());
+    assert!(RecentAttempts::from_name("Best Segments (Last 20 Weeks)").is_none());
+    assert!(RecentAttempts::from_name("Latest Run (Last 20 Attempts)").is_none());
+    assert!(RecentAttempts::from_name("Best Segments").is_none());
+}
+
+#[test]
+fn last_attempts() {
+    let s = TimeSpan::from_seconds;
+
+    let best = RecentAttempts::new(Kind::BestSegments, Window::Attempts(2));
+    let average = RecentAttempts::new(Kind::AverageSegments, Window::Attempts(1));
+    let balanced = RecentAttempts::new(Kind::BalancedPB, Window::Attempts(2));
+    let timer = timer_with(&[best.clone(), average.clone(), balanced.clone()]);
+    let run = timer.run();
+
+    // The gold of the first attempt is not recent anymore.
+    assert_eq!(run.segment(0).comparison(best.name()).game_time, Some(s(2.0)));
+    assert_eq!(run.segment(1).comparison(best.name()).game_time, Some(s(3.0)));
+
+    assert_eq!(run.segment(0).comparison(average.name()).game_time, Some(s(5.0)));
+    assert_eq!(run.segment(1).comparison(average.name()).game_time, Some(s(8.0)));
+
+    // The second attempt is the best recent attempt.
+    let balanced_time = run.segment(1).comparison(balanced.name()).game_time.unwrap();
+    assert!((balanced_time - s(3.0)).total_seconds().abs() < 0.001);
+}
+
+#[test]
+fn last_attempts_skip_invalid_and_empty_attempts() {
+    let s = TimeSpan::from_seconds;
+
+    let average = RecentAttempts::new(Kind::AverageSegments, Window::Attempts(1));
+    let mut timer = timer_with(&[average.clone()]);
+
+    // Resetting before the first split leaves no segment times behind.
+    timer.start();
+    timer.reset(true);
+
+    let mut run = timer.into_run(true);
+    assert_eq!(run.attempt_history().len(), 4);
+    assert!(run.invalidate_attempt(3));
+    run.regenerate_comparisons();
+
+    assert_eq!(run.segment(0).comparison(average.name()).game_time, Some(s(2.0)));
+    assert_eq!(run.segment(1).comparison(average.name()).game_time, Some(s(3.0)));
+}
+
+#[test]
+fn last_days() {
+    let s = TimeSpan::from_seconds;
+    let t = |seconds| Time::new().with_game_time(Some(s(seconds)));
+
+    let mut run = Run::new();
+
+    run.push_segment(Segment::new("First"));
+    run.push_segment(Segment::new("Second"));
+
+    let old = AtomicDateTime {
+        time: Utc::now() - Duration::days(30),
+        synced_with_atomic_clock: false,
+    };
+    let new = AtomicDateTime::now();
+    run.add_attempt_with_index(t(2.0), 1, Some(old), Some(old), None);
+    run.add_attempt_with_index(t(4.0), 2, Some(new), Some(new), None);
+    run.add_attempt_with_index(t(6.0), 3, None, None, None);
+    for &(index, seconds) in &[(1, 1.0), (2, 2.0), (3, 0.5)] {
+        for segment in run.segments_mut() {
+            segment.segment_history_mut().insert(index, t(seconds));
+        }
+    }
+
+    let mut generator = RecentAttempts::new(Kind::BestSegments, Window::Days(7));
+    let mut segments = run.segments().to_vec();
+    generator.generate(&mut segments, run.attempt_history());
+
+    assert_eq!(segments[0].comparison(generator.name()).game_time, Some(s(2.0)));
+    assert_eq!(segments[1].comparison(generator.name()).game_time, Some(s(4.0)));
+}
+
+#[test]
+fn no_recent_attempts() {
+    let mut run = Run::new();
+    run.push_segment(Segment::new("First"));
+
+    let mut generator = RecentAttempts::new(Kind::BalancedPB, Window::Days(7));
+    let mut segments = run.segments().to_vec();
+    generator.generate(&mut segments, run.attempt_history());
+
+    assert_eq!(segments[0].comparison(generator.name()).game_time, None);
+}
@@ -265,7 +265,7 @@ impl Component {
         let icon_change = if self.settings.display_icon {
             timer
                 .current_split()
-                .and_then(|s| s.icon().check_for_change(icon_id).map(str::to_owned))
+                .and_then(|s| s.icon().check_for_change(icon_id))
         } else if *icon_id != 0 {
             *icon_id = 0;
             Some(String::new())
@@ -6,6 +6,7 @@
 //! shown all the time.
 
 use std::cmp::{max, min};
+use std::collections::HashSet;
 use std::io::Write;
 use serde_json::{to_writer, Result};
 use {analysis, GeneralLayoutSettings, Timer};
@@ -23,6 +24,8 @@ use settings::{Color, Field, Gradient, SemanticColor, SettingsDescription, Value
 #[derive(Default, Clone)]
 pub struct Component {
     icon_ids: Vec<usize>,
+    sent_icon_ids: HashSet<usize>,
+    deduplicate_icons: bool,
     settings: Settings,
     current_split_index: Option<usize>,
     scroll_offset: isize,
@@ -92,8 +95,13 @@ pub struct IconChange {
     /// object.
     pub segment_index: usize,
     /// The segment's icon encoded as a Data URL. The String itself may be
-    /// empty. This indicates that there is no icon.
+    /// empty. This indicates that there is no icon. If the component
+    /// deduplicates icons, it is also empty if the icon has already been
+    /// provided for another segment.
     pub icon: String,
+    /// The ID of the segment's icon. Segments sharing the same icon have the
+    /// same ID. This is 0 if there is no icon.
+    pub icon_id: usize,
 }
 
 /// The state object describes the information to visualize for this component.
@@ -182,6 +190,16 @@ impl Component {
     /// is queried the next time.
     pub fn remount(&mut self) {
         self.icon_ids.clear();
+        self.sent_icon_ids.clear();
+    }
+
+    /// Sets whether each distinct icon is only provided once in the state
+    /// objects. Segments sharing an icon that has already been provided only
+    /// report the icon's ID then, so the icon needs to be looked up by its ID.
+    /// Remounting the component provides every icon again.
+    pub fn set_deduplicate_icons(&mut self, deduplicate_icons: bool) {
+        self.deduplicate_icons = deduplicate_icons;
+        self.remount();
     }
 
     /// Accesses the name of the component.
@@ -233,6 +251,8 @@ impl Component {
             && skip_count + take_count + 1 < timer.run().len();
 
         let mut icon_changes = Vec::new();
+        let sent_icon_ids = &mut self.sent_icon_ids;
+        let deduplicate_icons = self.deduplicate_icons;
 
         State {
             splits: timer
@@ -279,10 +299,20 @@ impl Component {
 
                     let visual_color = semantic_color.visualize(layout_settings);
 
-                    if let Some(icon_change) = segment.icon().check_for_change(icon_id) {
+                    let icon = segment.icon();
+                    if *icon_id != icon.id() {
+                        *icon_id = icon.id();
+                        let shared_id = if icon.is_empty() { 0 } else { icon.id() };
+                        let already_sent = deduplicate_icons && shared_id != 0
+                            && !sent_icon_ids.insert(shared_id);
                         icon_changes.push(IconChange {
                             segment_index: i,
-                            icon: icon_change.to_owned(),
+                            icon: if already_sent {
+                                String::new()
+                            } else {
+                                icon.url()
+                            },
+                            icon_id: shared_id,
                         });
                     }
 
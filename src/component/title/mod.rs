@@ -185,7 +185,6 @@ impl Component {
         let icon_change = if self.settings.display_game_icon {
             run.game_icon()
                 .check_for_change(&mut self.icon_id)
-                .map(str::to_owned)
         } else if self.icon_id != 0 {
             self.icon_id = 0;
             Some(String::new())
@@ -1,25 +1,85 @@
+use std::cmp::max;
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::Path;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use base64::{self, STANDARD};
+use byteorder::{ByteOrder, BE, LE};
+use imagelib::{self, png, ColorType, FilterType, GenericImage};
 
 static LAST_IMAGE_ID: AtomicUsize = ATOMIC_USIZE_INIT;
 
+/// The format of the data stored in an image. It is detected from the
+/// signature at the beginning of the data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// Portable Network Graphics
+    Png,
+    /// JPEG
+    Jpeg,
+    /// Graphics Interchange Format
+    Gif,
+    /// Windows Bitmap
+    Bmp,
+    /// Windows Icon
+    Ico,
+    /// The format couldn't be detected. This is also the format of empty
+    /// images.
+    Unknown,
+}
+
+impl ImageFormat {
+    /// Detects the format of the image data provided.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            ImageFormat::Png
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            ImageFormat::Jpeg
+        } else if data.starts_with(b"GIF8") {
+            ImageFormat::Gif
+        } else if data.starts_with(b"BM") {
+            ImageFormat::Bmp
+        } else if data.starts_with(b"\0\0\x01\0") {
+            ImageFormat::Ico
+        } else {
+            ImageFormat::Unknown
+        }
+    }
+
+    /// Returns the MIME type of the format. If the format is unknown, this
+    /// returns an empty string.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Ico => "image/x-icon",
+            ImageFormat::Unknown => "",
+        }
+    }
+}
+
 /// Images can be used to store segment and game icons. Each image object comes
 /// with an ID that changes whenever the image is modified. IDs are unique
 /// across different images. ID 0 is never used so it can be used as an initial
-/// state to refresh state at the beginning. You can query the image's data as a
-/// Data URL. There's no specific image format you need to use for the images.
+/// state to refresh state at the beginning. The image's data is stored as is,
+/// along with its format and dimensions, if they can be detected. You can
+/// query the image's data as a Data URL as well. There's no specific image
+/// format you need to use for the images. Cloning an image is cheap, as the
+/// clones share the image's data and keep its ID.
 #[derive(Debug, Clone)]
 pub struct Image {
-    url: String,
+    data: Arc<Vec<u8>>,
+    format: ImageFormat,
+    dimensions: Option<(u32, u32)>,
     id: usize,
 }
 
 impl PartialEq for Image {
     fn eq(&self, other: &Image) -> bool {
-        self.url == other.url
+        Arc::ptr_eq(&self.data, &other.data) || self.data == other.data
     }
 }
 
@@ -39,7 +99,9 @@ impl Image {
     /// Creates a new image with a unique ID with the image data provided.
     pub fn new(data: &[u8]) -> Self {
         let mut image = Image {
-            url: String::new(),
+            data: Arc::new(Vec::new()),
+            format: ImageFormat::Unknown,
+            dimensions: None,
             id: 0,
         };
         image.modify(data);
@@ -79,11 +141,38 @@ impl Image {
         self.id
     }
 
-    /// Accesses the Data URL storing the image's data. If the image's data is
-    /// empty, this returns an empty string instead of a URL.
+    /// Accesses the image's data.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Accesses the format of the image's data.
+    #[inline]
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Accesses the width and height of the image in pixels. This returns
+    /// `None` if they can't be determined from the image's data.
     #[inline]
-    pub fn url(&self) -> &str {
-        &self.url
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.dimensions
+    }
+
+    /// Encodes the image's data as a Data URL. The URL is only built when
+    /// it's requested, so you may want to use `check_for_change` to avoid
+    /// building it more often than necessary. If the image's data is empty,
+    /// this returns an empty string instead of a URL.
+    pub fn url(&self) -> String {
+        let mut url = String::new();
+        if !self.data.is_empty() {
+            url.push_str("data:");
+            url.push_str(self.format.mime_type());
+            url.push_str(";base64,");
+            base64::encode_config_buf(&self.data[..], STANDARD, &mut url);
+        }
+        url
     }
 
     /// Checks whether the image has changed by providing the last known image
@@ -91,7 +180,7 @@ impl Image {
     /// changed, the new ID is stored in the ID you provided and the image's URL
     /// is returned.
     #[inline]
-    pub fn check_for_change(&self, old_id: &mut usize) -> Option<&str> {
+    pub fn check_for_change(&self, old_id: &mut usize) -> Option<String> {
         if *old_id != self.id {
             *old_id = self.id;
             Some(self.url())
@@ -104,16 +193,158 @@ impl Image {
     /// provided. The image's ID changes to a new unique ID.
     pub fn modify(&mut self, data: &[u8]) {
         self.id = LAST_IMAGE_ID.fetch_add(1, Ordering::SeqCst) + 1;
-        self.url.clear();
+        self.data = Arc::new(data.to_owned());
+        self.format = ImageFormat::detect(data);
+        self.dimensions = dimensions(self.format, data);
+    }
 
-        if !data.is_empty() {
-            self.url.push_str("data:;base64,");
-            base64::encode_config_buf(data, STANDARD, &mut self.url);
+    /// Downscales the image, so that neither its width nor its height exceed
+    /// the maximum size provided. The aspect ratio is preserved and the
+    /// downscaled image is stored as a PNG. Images that are small enough
+    /// already, as well as images that can't be decoded, are kept as they are.
+    /// Images are only decoded if their header states dimensions that can be
+    /// decoded without allocating excessive amounts of memory. This is used
+    /// when icons get set through the Run Editor, so that huge icons don't
+    /// need to be kept around. Returns whether the image got downscaled.
+    pub fn downscale(&mut self, max_size: u32) -> bool {
+        let max_size = max(max_size, 1);
+        match self.dimensions {
+            Some((width, height)) => {
+                if width <= max_size && height <= max_size {
+                    return false;
+                }
+                if width as u64 * height as u64 > MAX_DECODED_PIXELS {
+                    return false;
+                }
+            }
+            None => return false,
         }
+
+        let image = match imagelib::load_from_memory(&self.data) {
+            Ok(image) => image,
+            Err(_) => return false,
+        };
+        let (width, height) = image.dimensions();
+        if width <= max_size && height <= max_size {
+            return false;
+        }
+
+        let image = image
+            .resize(max_size, max_size, FilterType::Triangle)
+            .to_rgba();
+
+        let mut data = Vec::new();
+        if png::PNGEncoder::new(&mut data)
+            .encode(&image, image.width(), image.height(), ColorType::RGBA(8))
+            .is_err()
+        {
+            return false;
+        }
+
+        self.modify(&data);
+        true
     }
 
     /// Checks if the image data is empty.
     pub fn is_empty(&self) -> bool {
-        self.url.is_empty()
+        self.data.is_empty()
+    }
+}
+
+/// The maximum amount of pixels of images that get decoded in order to
+/// downscale them.
+const MAX_DECODED_PIXELS: u64 = 4096 * 4096;
+
+/// Reads the dimensions of the image from the header of its data.
+fn dimensions(format: ImageFormat, data: &[u8]) -> Option<(u32, u32)> {
+    match format {
+        ImageFormat::Png => {
+            if data.len() >= 24 && &data[12..16] == b"IHDR" {
+                Some((BE::read_u32(&data[16..]), BE::read_u32(&data[20..])))
+            } else {
+                None
+            }
+        }
+        ImageFormat::Gif => {
+            if data.len() >= 10 {
+                Some((
+                    LE::read_u16(&data[6..]) as u32,
+                    LE::read_u16(&data[8..]) as u32,
+                ))
+            } else {
+                None
+            }
+        }
+        ImageFormat::Bmp => {
+            if data.len() < 26 {
+                None
+            } else if LE::read_u32(&data[14..]) == 12 {
+                Some((
+                    LE::read_u16(&data[18..]) as u32,
+                    LE::read_u16(&data[20..]) as u32,
+                ))
+            } else {
+                // Bitmaps stored from top to bottom have a negative height.
+                // Going through i64 avoids overflowing on i32::MIN.
+                Some((
+                    (LE::read_i32(&data[18..]) as i64).abs() as u32,
+                    (LE::read_i32(&data[22..]) as i64).abs() as u32,
+                ))
+            }
+        }
+        ImageFormat::Ico => {
+            // The dimensions of the first icon in the file are used. A size
+            // of 0 means 256 pixels.
+            if data.len() >= 8 {
+                let size = |s: u8| if s == 0 { 256 } else { s as u32 };
+                Some((size(data[6]), size(data[7])))
+            } else {
+                None
+            }
+        }
+        ImageFormat::Jpeg => jpeg_dimensions(data),
+        ImageFormat::Unknown => None,
+    }
+}
+
+/// Looks for the Start Of Frame segment of the JPEG, which stores its
+/// dimensions.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            // Fill bytes
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // Markers without a segment
+            0x01 | 0xD0..=0xD8 => {
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        let len = BE::read_u16(&data[pos + 2..]) as usize;
+        let is_start_of_frame = match marker {
+            0xC4 | 0xC8 | 0xCC => false,
+            0xC0..=0xCF => true,
+            _ => false,
+        };
+        if is_start_of_frame {
+            if pos + 9 > data.len() {
+                return None;
+            }
+            let height = BE::read_u16(&data[pos + 5..]) as u32;
+            let width = BE::read_u16(&data[pos + 7..]) as u32;
+            return Some((width, height));
+        }
+        pos += 2 + len;
     }
+    None
 }
@@ -56,6 +56,8 @@ pub extern crate indexmap;
 pub extern crate livesplit_hotkey as hotkey;
 pub extern crate palette;
 pub extern crate parking_lot;
+#[cfg(feature = "sqlite")]
+pub extern crate rusqlite;
 
 mod platform;
 
@@ -83,7 +85,7 @@ pub mod time;
 pub use chrono::{DateTime, Utc};
 pub use self::hotkey_config::HotkeyConfig;
 pub use self::hotkey_system::HotkeySystem;
-pub use self::image::Image;
+pub use self::image::{Image, ImageFormat};
 pub use self::layout::{Component, Editor as LayoutEditor,
                        GeneralSettings as GeneralLayoutSettings, Layout};
 pub use self::run::{Attempt, Editor as RunEditor, Run, RunMetadata, Segment, SegmentHistory};
@@ -10,6 +10,10 @@ pub struct Attempt {
     started: Option<AtomicDateTime>,
     ended: Option<AtomicDateTime>,
     pause_time: Option<TimeSpan>,
+    is_invalid: bool,
+    note: String,
+    tags: Vec<String>,
+    unknown_elements: Vec<u8>,
 }
 
 impl Attempt {
@@ -35,6 +39,10 @@ impl Attempt {
             started,
             ended,
             pause_time,
+            is_invalid: false,
+            note: String::new(),
+            tags: Vec::new(),
+            unknown_elements: Vec::new(),
         }
     }
 
@@ -65,6 +73,12 @@ impl Attempt {
         self.time
     }
 
+    /// Sets the split time of the last segment.
+    #[inline]
+    pub fn set_time(&mut self, time: Time) {
+        self.time = time;
+    }
+
     /// Accesses the amount of time the attempt has been paused for. If it is
     /// not known, this returns `None`. This means that it may not necessarily
     /// be possible to differentiate whether a Run has not been paused or it
@@ -87,4 +101,84 @@ impl Attempt {
     pub fn ended(&self) -> Option<AtomicDateTime> {
         self.ended
     }
+
+    /// Returns `true` if the attempt has been marked as invalid, for example
+    /// because it was cheated, glitched or done in the wrong category. The
+    /// segment times of invalid attempts are not kept in the Segment History.
+    #[inline]
+    pub fn is_invalid(&self) -> bool {
+        self.is_invalid
+    }
+
+    /// Marks the attempt as invalid or valid. This only changes the marker.
+    /// Use `Run::invalidate_attempt` to also remove the attempt's segment times
+    /// from the Run.
+    #[inline]
+    pub fn set_invalid(&mut self, is_invalid: bool) {
+        self.is_invalid = is_invalid;
+    }
+
+    /// Accesses the note the runner attached to the attempt. This may be empty
+    /// if there is no note.
+    #[inline]
+    pub fn note(&self) -> &str {
+        &self.note
+    }
+
+    /// Sets the note attached to the attempt. This may be empty to remove the
+    /// note.
+    #[inline]
+    pub fn set_note<S>(&mut self, note: S)
+    where
+        S: AsRef<str>,
+    {
+        self.note.clear();
+        self.note.push_str(note.as_ref());
+    }
+
+    /// Accesses the tags attached to the attempt.
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Attaches a tag to the attempt. If the attempt already has the tag,
+    /// nothing happens.
+    pub fn add_tag<S>(&mut self, tag: S)
+    where
+        S: Into<String>,
+    {
+        let tag = tag.into();
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    /// Removes a tag from the attempt. If the attempt doesn't have the tag,
+    /// nothing happens.
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+    }
+
+    /// Accesses the XML elements of the attempt that weren't understood when
+    /// parsing it. They are kept as they were, so they can be written back
+    /// when saving the attempt again. Just like the unknown elements of the
+    /// Run, they are written back after all the elements of the attempt that
+    /// are understood.
+    #[inline]
+    pub fn unknown_elements(&self) -> &[u8] {
+        &self.unknown_elements
+    }
+
+    /// Grants mutable access to the XML elements of the attempt that weren't
+    /// understood when parsing it.
+    ///
+    /// # Warning
+    ///
+    /// You need to ensure that the elements are encoded as data that would be
+    /// valid as an interior of an XML element.
+    #[inline]
+    pub fn unknown_elements_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.unknown_elements
+    }
 }
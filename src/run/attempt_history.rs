@@ -0,0 +1,149 @@
+use {Run, Segment, Time, TimeSpan, TimingMethod};
+
+impl Run {
+    /// Removes the attempt with the index provided from the Attempt History,
+    /// along with all of its segment times in the Segment Histories. Best
+    /// Segment Times achieved in the attempt are recalculated from the
+    /// remaining history. If the attempt was the Personal Best, the fastest
+    /// remaining valid attempt becomes the new Personal Best. Returns `false`
+    /// if there is no attempt with that index.
+    pub fn remove_attempt(&mut self, index: i32) -> bool {
+        let position = match self.attempt_history()
+            .iter()
+            .position(|a| a.index() == index)
+        {
+            Some(position) => position,
+            None => return false,
+        };
+
+        self.remove_attempt_times(index);
+        self.attempt_history_mut().remove(position);
+        true
+    }
+
+    /// Marks the attempt with the index provided as invalid, for example
+    /// because it was cheated, glitched or done in the wrong category. The
+    /// attempt stays in the Attempt History, but its segment times are removed
+    /// from the Segment Histories, just like when removing the attempt. Returns
+    /// `false` if there is no attempt with that index.
+    pub fn invalidate_attempt(&mut self, index: i32) -> bool {
+        if !self.attempt_history().iter().any(|a| a.index() == index) {
+            return false;
+        }
+
+        self.remove_attempt_times(index);
+        if let Some(attempt) = self.attempt_history_mut()
+            .iter_mut()
+            .find(|a| a.index() == index)
+        {
+            attempt.set_invalid(true);
+        }
+        true
+    }
+
+    fn remove_attempt_times(&mut self, index: i32) {
+        let is_personal_best = self.attempt_history()
+            .iter()
+            .find(|a| a.index() == index)
+            .map_or(false, |a| self.is_personal_best(a.time()));
+
+        for segment in self.segments_mut() {
+            remove_segment_time(segment, index);
+        }
+
+        if is_personal_best {
+            self.replace_personal_best(index);
+        }
+
+        if !self.is_empty() {
+            self.fix_splits();
+            self.regenerate_comparisons();
+        }
+        self.mark_as_changed();
+    }
+
+    fn is_personal_best(&self, time: Time) -> bool {
+        let personal_best = match self.segments().last() {
+            Some(segment) => segment.personal_best_split_time(),
+            None => return false,
+        };
+        TimingMethod::all()
+            .iter()
+            .any(|&m| time[m].is_some() && time[m] == personal_best[m])
+    }
+
+    /// Sets the Personal Best to the fastest valid attempt other than the one
+    /// provided that has segment times for all the segments.
+    fn replace_personal_best(&mut self, removed_index: i32) {
+        let method = if self.attempt_history()
+            .iter()
+            .any(|a| a.time().real_time.is_some())
+        {
+            TimingMethod::RealTime
+        } else {
+            TimingMethod::GameTime
+        };
+
+        let personal_best = self.attempt_history()
+            .iter()
+            .filter(|a| a.index() != removed_index && !a.is_invalid())
+            .filter_map(|a| Some((a.time()[method]?, a.index())))
+            .filter(|&(_, index)| {
+                self.segments()
+                    .iter()
+                    .all(|s| s.segment_history().get(index).is_some())
+            })
+            .min_by_key(|&(time, _)| time)
+            .map(|(_, index)| index);
+
+        let mut split_time = [TimeSpan::zero(); 2];
+        for segment in self.segments_mut() {
+            let mut time = Time::default();
+            if let Some(index) = personal_best {
+                let segment_time = segment
+                    .segment_history()
+                    .get(index)
+                    .unwrap_or_default();
+                for (i, &method) in TimingMethod::all().iter().enumerate() {
+                    if let Some(segment_time) = segment_time[method] {
+                        split_time[i] += segment_time;
+                        time[method] = Some(split_time[i]);
+                    }
+                }
+            }
+            segment.set_personal_best_split_time(time);
+        }
+
+        self.clear_run_id();
+    }
+}
+
+/// Removes the segment time of the attempt with the index provided from the
+/// segment's Segment History. The Best Segment Time is recalculated from the
+/// remaining history if necessary.
+pub(super) fn remove_segment_time(segment: &mut Segment, index: i32) {
+    if let Some(removed) = segment.segment_history().get(index) {
+        segment.segment_history_mut().remove(index);
+        for &method in &TimingMethod::all() {
+            forget_segment_time(segment, method, removed[method]);
+        }
+    }
+}
+
+/// Recalculates the Best Segment Time of the segment for the timing method
+/// provided from its Segment History, if the segment time that just got removed
+/// from the Segment History was the Best Segment Time.
+pub(super) fn forget_segment_time(
+    segment: &mut Segment,
+    method: TimingMethod,
+    removed: Option<TimeSpan>,
+) {
+    if removed.is_some() && removed == segment.best_segment_time()[method] {
+        let best_segment_time = segment
+            .segment_history()
+            .iter()
+            .filter_map(|&(_, t)| t[method])
+            .min();
+        segment.best_segment_time_mut()[method] = best_segment_time;
+    }
+}
@@ -0,0 +1,75 @@
+//! Provides reading and writing of tables as CSV or TSV. Fields are quoted
+//! if necessary, as described by RFC 4180.
+
+use std::io::{self, BufRead, Write};
+
+/// Writes a single row of the table, separating the fields by the delimiter
+/// provided.
+pub fn write_row<W, S>(writer: &mut W, delimiter: char, row: &[S]) -> io::Result<()>
+where
+    W: Write,
+    S: AsRef<str>,
+{
+    for (i, field) in row.iter().enumerate() {
+        if i != 0 {
+            write!(writer, "{}", delimiter)?;
+        }
+        let field = field.as_ref();
+        if field.contains(|c: char| c == delimiter || c == '"' || c == '\n' || c == '\r') {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+/// Reads all the rows of the table. Empty lines are skipped.
+pub fn read_rows<R: BufRead>(mut source: R, delimiter: char) -> io::Result<Vec<Vec<String>>> {
+    let mut text = String::new();
+    source.read_to_string(&mut text)?;
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            row.push(field.split_off(0));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(field.split_off(0));
+            if row.len() > 1 || !row[0].is_empty() {
+                rows.push(row.split_off(0));
+            } else {
+                row.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
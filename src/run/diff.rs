@@ -0,0 +1,385 @@
+//! The diff module provides a structural comparison of two Runs. It reports
+//! the segments that got added, removed, renamed or moved, the times that
+//! changed, as well as the attempts and the metadata that changed. This can be
+//! used to show what changed since the Run got saved the last time, or to
+//! review the changes before overwriting a splits file.
+//!
+//! # Examples
+//!
+//! ```
+//! use livesplit_core::run::diff::{self, Change};
+//! use livesplit_core::{Run, Segment};
+//!
+//! let mut old = Run::new();
+//! old.push_segment(Segment::new("Cap Kingdom"));
+//!
+//! let mut new = old.clone();
+//! new.push_segment(Segment::new("Cascade Kingdom"));
+//!
+//! let changes = diff::diff(&old, &new);
+//! assert_eq!(
+//!     changes,
+//!     vec![Change::SegmentAdded { index: 1, name: "Cascade Kingdom".into() }]
+//! );
+//! ```
+
+use {Run, RunMetadata, Segment, Time, TimeSpan};
+use comparison::personal_best;
+
+/// A single difference between two Runs. Segments are referred to by their
+/// index in the old Run, the new Run, or both.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The name of the game changed.
+    GameName {
+        /// The name in the old Run.
+        old: String,
+        /// The name in the new Run.
+        new: String,
+    },
+    /// The name of the category changed.
+    CategoryName {
+        /// The name in the old Run.
+        old: String,
+        /// The name in the new Run.
+        new: String,
+    },
+    /// The game icon changed.
+    GameIcon,
+    /// The timer offset changed.
+    Offset {
+        /// The offset of the old Run.
+        old: TimeSpan,
+        /// The offset of the new Run.
+        new: TimeSpan,
+    },
+    /// The attempt count changed.
+    AttemptCount {
+        /// The attempt count of the old Run.
+        old: u32,
+        /// The attempt count of the new Run.
+        new: u32,
+    },
+    /// The metadata changed.
+    Metadata {
+        /// The metadata of the old Run.
+        old: RunMetadata,
+        /// The metadata of the new Run.
+        new: RunMetadata,
+    },
+    /// A segment only exists in the new Run.
+    SegmentAdded {
+        /// The index of the segment in the new Run.
+        index: usize,
+        /// The name of the segment.
+        name: String,
+    },
+    /// A segment only exists in the old Run.
+    SegmentRemoved {
+        /// The index of the segment in the old Run.
+        index: usize,
+        /// The name of the segment.
+        name: String,
+    },
+    /// A segment got renamed.
+    SegmentRenamed {
+        /// The index of the segment in the old Run.
+        old_index: usize,
+        /// The index of the segment in the new Run.
+        new_index: usize,
+        /// The name of the segment in the old Run.
+        old: String,
+        /// The name of the segment in the new Run.
+        new: String,
+    },
+    /// A segment got moved relative to the other segments.
+    SegmentMoved {
+        /// The index of the segment in the old Run.
+        old_index: usize,
+        /// The index of the segment in the new Run.
+        new_index: usize,
+        /// The name of the segment.
+        name: String,
+    },
+    /// The icon of a segment changed.
+    SegmentIcon {
+        /// The index of the segment in the new Run.
+        index: usize,
+    },
+    /// The Personal Best split time of a segment changed.
+    PersonalBestSplitTime {
+        /// The index of the segment in the new Run.
+        index: usize,
+        /// The split time in the old Run.
+        old: Time,
+        /// The split time in the new Run.
+        new: Time,
+    },
+    /// The Best Segment Time of a segment changed.
+    BestSegmentTime {
+        /// The index of the segment in the new Run.
+        index: usize,
+        /// The segment time in the old Run.
+        old: Time,
+        /// The segment time in the new Run.
+        new: Time,
+    },
+    /// A custom comparison only exists in the new Run.
+    ComparisonAdded(String),
+    /// A custom comparison only exists in the old Run.
+    ComparisonRemoved(String),
+    /// The time of a custom comparison changed for a segment. The Personal
+    /// Best is reported as `PersonalBestSplitTime` instead.
+    ComparisonTime {
+        /// The index of the segment in the new Run.
+        index: usize,
+        /// The name of the comparison.
+        comparison: String,
+        /// The comparison time in the old Run.
+        old: Time,
+        /// The comparison time in the new Run.
+        new: Time,
+    },
+    /// The attempt with this index only exists in the new Run.
+    AttemptAdded(i32),
+    /// The attempt with this index only exists in the old Run.
+    AttemptRemoved(i32),
+    /// The attempt with this index differs between the Runs.
+    AttemptChanged(i32),
+}
+
+/// Matches up the segments of both Runs. Segments with the same name are
+/// matched up in order. A segment that isn't matched that way is considered
+/// renamed if the segment before it, or the beginning of the Run, is matched
+/// up with the segment in front of an unmatched segment of the new Run.
+fn match_segments(old: &Run, new: &Run) -> (Vec<Option<usize>>, Vec<bool>) {
+    let mut used = vec![false; new.len()];
+    let mut mapping = old.segments()
+        .iter()
+        .map(|segment| {
+            let index = (0..new.len())
+                .find(|&i| !used[i] && new.segment(i).name() == segment.name());
+            if let Some(index) = index {
+                used[index] = true;
+            }
+            index
+        })
+        .collect::<Vec<_>>();
+
+    let mut renamed = vec![false; old.len()];
+    for old_index in 0..old.len() {
+        if mapping[old_index].is_some() {
+            continue;
+        }
+        let new_index = if old_index == 0 {
+            Some(0)
+        } else {
+            mapping[old_index - 1].map(|i| i + 1)
+        };
+        if let Some(new_index) = new_index {
+            if new_index < new.len() && !used[new_index] {
+                used[new_index] = true;
+                mapping[old_index] = Some(new_index);
+                renamed[old_index] = true;
+            }
+        }
+    }
+
+    (mapping, renamed)
+}
+
+/// Determines which of the matched up segments stay in the same order, by
+/// finding the longest increasing subsequence of their new indices. All the
+/// other segments are considered moved.
+fn stationary_segments(mapping: &[Option<usize>]) -> Vec<bool> {
+    let matched = mapping
+        .iter()
+        .enumerate()
+        .filter_map(|(old, &new)| Some((old, new?)))
+        .collect::<Vec<_>>();
+
+    let mut lengths = vec![1; matched.len()];
+    let mut previous = vec![None; matched.len()];
+    for i in 0..matched.len() {
+        for j in 0..i {
+            if matched[j].1 < matched[i].1 && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut stationary = vec![false; mapping.len()];
+    let mut current = (0..matched.len()).max_by_key(|&i| lengths[i]);
+    while let Some(i) = current {
+        stationary[matched[i].0] = true;
+        current = previous[i];
+    }
+    stationary
+}
+
+fn diff_segment(
+    changes: &mut Vec<Change>,
+    comparisons: &[&String],
+    index: usize,
+    old: &Segment,
+    new: &Segment,
+) {
+    if old.icon() != new.icon() {
+        changes.push(Change::SegmentIcon { index });
+    }
+    if old.personal_best_split_time() != new.personal_best_split_time() {
+        changes.push(Change::PersonalBestSplitTime {
+            index,
+            old: old.personal_best_split_time(),
+            new: new.personal_best_split_time(),
+        });
+    }
+    if old.best_segment_time() != new.best_segment_time() {
+        changes.push(Change::BestSegmentTime {
+            index,
+            old: old.best_segment_time(),
+            new: new.best_segment_time(),
+        });
+    }
+    for &comparison in comparisons {
+        let (old, new) = (old.comparison(comparison), new.comparison(comparison));
+        if old != new {
+            changes.push(Change::ComparisonTime {
+                index,
+                comparison: comparison.clone(),
+                old,
+                new,
+            });
+        }
+    }
+}
+
+/// Compares the old Run to the new Run and returns all the changes that
+/// happened in between. An empty list means that the Runs are equivalent.
+pub fn diff(old: &Run, new: &Run) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    if old.game_name() != new.game_name() {
+        changes.push(Change::GameName {
+            old: old.game_name().to_owned(),
+            new: new.game_name().to_owned(),
+        });
+    }
+    if old.category_name() != new.category_name() {
+        changes.push(Change::CategoryName {
+            old: old.category_name().to_owned(),
+            new: new.category_name().to_owned(),
+        });
+    }
+    if old.game_icon() != new.game_icon() {
+        changes.push(Change::GameIcon);
+    }
+    if old.offset() != new.offset() {
+        changes.push(Change::Offset {
+            old: old.offset(),
+            new: new.offset(),
+        });
+    }
+    if old.attempt_count() != new.attempt_count() {
+        changes.push(Change::AttemptCount {
+            old: old.attempt_count(),
+            new: new.attempt_count(),
+        });
+    }
+    if old.metadata() != new.metadata() {
+        changes.push(Change::Metadata {
+            old: old.metadata().clone(),
+            new: new.metadata().clone(),
+        });
+    }
+
+    let (mapping, renamed) = match_segments(old, new);
+    let stationary = stationary_segments(&mapping);
+    let mut added = vec![true; new.len()];
+
+    for (old_index, segment) in old.segments().iter().enumerate() {
+        match mapping[old_index] {
+            Some(new_index) => {
+                added[new_index] = false;
+                if renamed[old_index] {
+                    changes.push(Change::SegmentRenamed {
+                        old_index,
+                        new_index,
+                        old: segment.name().to_owned(),
+                        new: new.segment(new_index).name().to_owned(),
+                    });
+                }
+                if !stationary[old_index] {
+                    changes.push(Change::SegmentMoved {
+                        old_index,
+                        new_index,
+                        name: new.segment(new_index).name().to_owned(),
+                    });
+                }
+            }
+            None => changes.push(Change::SegmentRemoved {
+                index: old_index,
+                name: segment.name().to_owned(),
+            }),
+        }
+    }
+    for (index, segment) in new.segments().iter().enumerate() {
+        if added[index] {
+            changes.push(Change::SegmentAdded {
+                index,
+                name: segment.name().to_owned(),
+            });
+        }
+    }
+
+    for comparison in old.custom_comparisons() {
+        if !new.custom_comparisons().contains(comparison) {
+            changes.push(Change::ComparisonRemoved(comparison.clone()));
+        }
+    }
+    for comparison in new.custom_comparisons() {
+        if !old.custom_comparisons().contains(comparison) {
+            changes.push(Change::ComparisonAdded(comparison.clone()));
+        }
+    }
+
+    let comparisons = new.custom_comparisons()
+        .iter()
+        .filter(|&c| c != personal_best::NAME && old.custom_comparisons().contains(c))
+        .collect::<Vec<_>>();
+    for (old_index, &new_index) in mapping.iter().enumerate() {
+        if let Some(new_index) = new_index {
+            diff_segment(
+                &mut changes,
+                &comparisons,
+                new_index,
+                old.segment(old_index),
+                new.segment(new_index),
+            );
+        }
+    }
+
+    for attempt in old.attempt_history() {
+        match new.attempt_history()
+            .iter()
+            .find(|a| a.index() == attempt.index())
+        {
+            Some(new_attempt) if new_attempt != attempt => {
+                changes.push(Change::AttemptChanged(attempt.index()))
+            }
+            Some(_) => {}
+            None => changes.push(Change::AttemptRemoved(attempt.index())),
+        }
+    }
+    for attempt in new.attempt_history() {
+        if !old.attempt_history()
+            .iter()
+            .any(|a| a.index() == attempt.index())
+        {
+            changes.push(Change::AttemptAdded(attempt.index()));
+        }
+    }
+
+    changes
+}
@@ -21,6 +21,7 @@ use chrono::Local;
 /// individually if any of them seem wrong.
 pub struct SumOfBestCleaner<'r> {
     run: &'r mut Run,
+    changed: Option<&'r mut bool>,
     predictions: Vec<Option<TimeSpan>>,
     state: State,
 }
@@ -123,11 +124,20 @@ impl<'r> SumOfBestCleaner<'r> {
         let predictions = Vec::with_capacity(run.len() + 1);
         Self {
             run,
+            changed: None,
             predictions,
             state: State::WithTimingMethod(TimingMethod::RealTime),
         }
     }
 
+    /// Creates a new Sum of Best Cleaner for the provided Run object that
+    /// additionally sets the flag provided whenever a clean up is applied.
+    pub(super) fn with_change_tracking(run: &'r mut Run, changed: &'r mut bool) -> Self {
+        let mut cleaner = Self::new(run);
+        cleaner.changed = Some(changed);
+        cleaner
+    }
+
     /// Applies a clean up to the Run.
     pub fn apply(&mut self, clean_up: CleanUp) {
         self.run
@@ -136,6 +146,9 @@ impl<'r> SumOfBestCleaner<'r> {
             .remove(clean_up.run_index);
 
         self.run.mark_as_changed();
+        if let Some(ref mut changed) = self.changed {
+            **changed = true;
+        }
     }
 
     /// Returns the next potential clean up. If there are no more potential
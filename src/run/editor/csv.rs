@@ -0,0 +1,368 @@
+//! The csv module provides the import and export of the segment times of the
+//! Run Editor as CSV or TSV tables, so they can be edited in bulk with a
+//! spreadsheet application.
+//!
+//! The table has a header row and a row for each segment. The columns are the
+//! segment name, followed by the split time, the segment time and the best
+//! segment time for both timing methods and then the times of all the custom
+//! comparisons for both timing methods.
+
+use std::io::{self, BufRead, Write};
+use std::result::Result as StdResult;
+use {Run, TimeSpan, TimingMethod};
+use comparison::personal_best;
+use run::ComparisonError;
+use run::csv_util::{read_rows, write_row};
+use time::formatter::{Complete, TimeFormatter};
+use super::{Editor, ParseError};
+use super::segment_row::parse_positive;
+
+const NAME: &str = "Segment Name";
+const SPLIT_TIME: &str = "Split Time";
+const SEGMENT_TIME: &str = "Segment Time";
+const BEST_SEGMENT: &str = "Best Segment";
+
+quick_error! {
+    /// The Error type for tables that couldn't be imported.
+    #[derive(Debug)]
+    pub enum Error {
+        /// Failed to read from the source.
+        Io(err: io::Error) {
+            from()
+        }
+        /// The table doesn't have a header row.
+        Empty {}
+        /// The header row doesn't have a column for the segment names.
+        MissingNameColumn {}
+        /// The column in the header row is not known.
+        UnknownColumn(column: String) {}
+        /// The column appears more than once in the header row.
+        DuplicateColumn(column: String) {}
+        /// The row with the given line number has a different amount of fields
+        /// than the header row. The expected and the actual amount are stored
+        /// as well.
+        FieldCount(row: usize, expected: usize, actual: usize) {}
+        /// The amount of rows doesn't match the amount of segments, so they
+        /// can't be matched up by position. The expected and the actual amount
+        /// are stored as well.
+        RowCount(expected: usize, actual: usize) {}
+        /// There's no segment with the name of the row with the given line
+        /// number. If multiple rows have the same name, there need to be as
+        /// many segments with that name.
+        UnknownSegment(row: usize, name: String) {}
+        /// A time in the given line number and column couldn't be parsed.
+        Time(row: usize, column: String, err: ParseError) {}
+        /// A custom comparison in the table couldn't be added to the Run.
+        Comparison(err: ComparisonError) {
+            from()
+        }
+    }
+}
+
+/// The Result type for importing tables.
+pub type Result<T> = StdResult<T, Error>;
+
+/// Describes the character that separates the fields of a row.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    /// The fields are separated by commas, as in CSV files.
+    Comma,
+    /// The fields are separated by tabs, as in TSV files.
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(&self) -> char {
+        match *self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+/// Describes how the rows of an imported table are matched up with the
+/// segments of the Run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Matching {
+    /// Each row is matched with the segment of the same name. If there are
+    /// multiple segments with the same name, the rows with that name are
+    /// matched up with them in order. Segments without a row are left as is.
+    ByName,
+    /// The rows are matched with the segments in order. There needs to be a
+    /// row for every segment.
+    ByPosition,
+}
+
+#[derive(Clone, PartialEq)]
+enum Column {
+    Name,
+    SplitTime(TimingMethod),
+    SegmentTime(TimingMethod),
+    BestSegment(TimingMethod),
+    Comparison(String, TimingMethod),
+}
+
+impl Column {
+    fn parse(header: &str) -> Result<Self> {
+        if header == NAME {
+            return Ok(Column::Name);
+        }
+
+        let (base, method) = if header.ends_with(suffix(TimingMethod::RealTime)) {
+            let suffix = suffix(TimingMethod::RealTime);
+            (&header[..header.len() - suffix.len()], TimingMethod::RealTime)
+        } else if header.ends_with(suffix(TimingMethod::GameTime)) {
+            let suffix = suffix(TimingMethod::GameTime);
+            (&header[..header.len() - suffix.len()], TimingMethod::GameTime)
+        } else {
+            return Err(Error::UnknownColumn(header.to_owned()));
+        };
+
+        Ok(match base {
+            SPLIT_TIME => Column::SplitTime(method),
+            SEGMENT_TIME => Column::SegmentTime(method),
+            BEST_SEGMENT => Column::BestSegment(method),
+            comparison => Column::Comparison(comparison.to_owned(), method),
+        })
+    }
+}
+
+fn suffix(method: TimingMethod) -> &'static str {
+    match method {
+        TimingMethod::RealTime => " (Real Time)",
+        TimingMethod::GameTime => " (Game Time)",
+    }
+}
+
+impl Editor {
+    /// Writes the segment times of the Run as a table. The times are written
+    /// in a lossless format that can be imported again.
+    pub fn export_csv<W>(&self, mut writer: W, delimiter: Delimiter) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let comparisons = self.run
+            .custom_comparisons()
+            .iter()
+            .filter(|&c| c != personal_best::NAME)
+            .collect::<Vec<_>>();
+
+        let mut header = vec![NAME.to_owned()];
+        for &method in &TimingMethod::all() {
+            for name in &[SPLIT_TIME, SEGMENT_TIME, BEST_SEGMENT] {
+                header.push(format!("{}{}", name, suffix(method)));
+            }
+            for comparison in &comparisons {
+                header.push(format!("{}{}", comparison, suffix(method)));
+            }
+        }
+        write_row(&mut writer, delimiter.as_char(), &header)?;
+
+        let mut row = Vec::with_capacity(header.len());
+        let mut previous_split_times = [Some(TimeSpan::zero()); 2];
+        for segment in self.run.segments() {
+            row.clear();
+            row.push(segment.name().to_owned());
+            for (&method, previous_split_time) in TimingMethod::all()
+                .iter()
+                .zip(previous_split_times.iter_mut())
+            {
+                let split_time = segment.personal_best_split_time()[method];
+                let segment_time = catch! { split_time? - (*previous_split_time)? };
+                if split_time.is_some() {
+                    *previous_split_time = split_time;
+                }
+
+                row.push(format_time(split_time));
+                row.push(format_time(segment_time));
+                row.push(format_time(segment.best_segment_time()[method]));
+                for comparison in &comparisons {
+                    row.push(format_time(segment.comparison(comparison)[method]));
+                }
+            }
+            write_row(&mut writer, delimiter.as_char(), &row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Imports the segment times from a table, like the ones written by
+    /// `export_csv`. Only the segment name column is required, all the other
+    /// columns are optional and only the times in the columns provided are
+    /// changed. If a split time column is provided for a timing method, the
+    /// segment time column of that timing method is ignored. Custom
+    /// comparisons that don't exist yet are added. The whole table is
+    /// validated before any of the changes are applied, so either all of them
+    /// are applied or none of them.
+    pub fn import_csv<R>(
+        &mut self,
+        source: R,
+        delimiter: Delimiter,
+        matching: Matching,
+    ) -> Result<()>
+    where
+        R: BufRead,
+    {
+        let mut rows = read_rows(source, delimiter.as_char())?.into_iter();
+
+        let columns = rows.next()
+            .ok_or(Error::Empty)?
+            .iter()
+            .map(|c| Column::parse(c))
+            .collect::<Result<Vec<_>>>()?;
+        for (i, column) in columns.iter().enumerate() {
+            if columns[..i].contains(column) {
+                return Err(Error::DuplicateColumn(header_name(column)));
+            }
+        }
+        let name_column = columns
+            .iter()
+            .position(|c| *c == Column::Name)
+            .ok_or(Error::MissingNameColumn)?;
+
+        let rows = rows.collect::<Vec<_>>();
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != columns.len() {
+                return Err(Error::FieldCount(i + 2, columns.len(), row.len()));
+            }
+        }
+
+        let segment_indices = match_rows(&self.run, &rows, name_column, matching)?;
+
+        let mut run = self.run.clone();
+        for column in &columns {
+            if let Column::Comparison(ref name, _) = *column {
+                if !run.custom_comparisons().contains(name) {
+                    run.add_custom_comparison(name.as_str())?;
+                }
+            }
+        }
+
+        let mut segment_times = vec![[None; 2]; run.len()];
+        for (i, (row, &segment_index)) in rows.iter().zip(&segment_indices).enumerate() {
+            for (column, field) in columns.iter().zip(row) {
+                let time = match *column {
+                    Column::Name => continue,
+                    _ => parse_time(field)
+                        .map_err(|e| Error::Time(i + 2, header_name(column), e))?,
+                };
+
+                let segment = run.segment_mut(segment_index);
+                match *column {
+                    Column::Name => {}
+                    Column::SplitTime(method) => {
+                        segment.personal_best_split_time_mut()[method] = time
+                    }
+                    Column::SegmentTime(method) => {
+                        segment_times[segment_index][method as usize] = Some(time)
+                    }
+                    Column::BestSegment(method) => segment.best_segment_time_mut()[method] = time,
+                    Column::Comparison(ref name, method) => {
+                        segment.comparison_mut(name)[method] = time
+                    }
+                }
+            }
+        }
+
+        for &method in &TimingMethod::all() {
+            if !columns.contains(&Column::SplitTime(method))
+                && columns.contains(&Column::SegmentTime(method))
+            {
+                apply_segment_times(&mut run, &segment_times, method);
+            }
+        }
+
+        self.run = run;
+        self.times_modified();
+        self.fix();
+
+        Ok(())
+    }
+}
+
+fn header_name(column: &Column) -> String {
+    match *column {
+        Column::Name => NAME.to_owned(),
+        Column::SplitTime(method) => format!("{}{}", SPLIT_TIME, suffix(method)),
+        Column::SegmentTime(method) => format!("{}{}", SEGMENT_TIME, suffix(method)),
+        Column::BestSegment(method) => format!("{}{}", BEST_SEGMENT, suffix(method)),
+        Column::Comparison(ref name, method) => format!("{}{}", name, suffix(method)),
+    }
+}
+
+fn match_rows(
+    run: &Run,
+    rows: &[Vec<String>],
+    name_column: usize,
+    matching: Matching,
+) -> Result<Vec<usize>> {
+    match matching {
+        Matching::ByPosition => {
+            if rows.len() != run.len() {
+                return Err(Error::RowCount(run.len(), rows.len()));
+            }
+            Ok((0..rows.len()).collect())
+        }
+        Matching::ByName => {
+            let mut matched = vec![false; run.len()];
+            rows.iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let name = &row[name_column];
+                    let index = (0..run.len())
+                        .find(|&index| !matched[index] && run.segment(index).name() == name)
+                        .ok_or_else(|| Error::UnknownSegment(i + 2, name.clone()))?;
+                    matched[index] = true;
+                    Ok(index)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Updates the split times of the segments with segment times provided, while
+/// keeping the segment times of all the other segments the same.
+fn apply_segment_times(
+    run: &mut Run,
+    segment_times: &[[Option<Option<TimeSpan>>; 2]],
+    method: TimingMethod,
+) {
+    let mut previous_split_time = Some(TimeSpan::zero());
+    let mut previous_original = Some(TimeSpan::zero());
+    for (segment, times) in run.segments_mut().iter_mut().zip(segment_times) {
+        let original = segment.personal_best_split_time()[method];
+        let segment_time = match times[method as usize] {
+            Some(segment_time) => segment_time,
+            None => catch! { original? - previous_original? },
+        };
+        if original.is_some() {
+            previous_original = original;
+        }
+
+        let split_time = catch! { previous_split_time? + segment_time? };
+        segment.personal_best_split_time_mut()[method] = split_time;
+        if split_time.is_some() {
+            previous_split_time = split_time;
+        }
+    }
+}
+
+fn format_time(time: Option<TimeSpan>) -> String {
+    time.map(|t| Complete.format(t).to_string())
+        .unwrap_or_default()
+}
+
+fn parse_time(text: &str) -> StdResult<Option<TimeSpan>, ParseError> {
+    let text = text.trim();
+    // The Complete formatter prefixes times of a day or longer with the
+    // amount of days.
+    if let (Some(dot_index), Some(colon_index)) = (text.find('.'), text.find(':')) {
+        if dot_index < colon_index {
+            if let Ok(days) = text[..dot_index].parse() {
+                let time = parse_positive(&text[dot_index + 1..])?;
+                return Ok(time.map(|t| TimeSpan::from_days(days) + t));
+            }
+        }
+    }
+    parse_positive(text)
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use {AtomicDateTime, TimeSpan};
+use run::attempt_history::{forget_segment_time, remove_segment_time};
+use super::{Editor, ParseError};
+use super::segment_row::parse_positive;
+
+/// The amount of median absolute deviations a segment time needs to be away
+/// from the median segment time by default in order to be considered an
+/// outlier.
+pub const DEFAULT_OUTLIER_THRESHOLD: f64 = 3.0;
+
+/// Describes a single element of the active segment's Segment History, as
+/// provided by the Run Editor's Segment History browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryElement {
+    /// The index of the attempt the segment time belongs to. Segment times with
+    /// an index of 0 or lower don't belong to any attempt and were imported
+    /// from a different source, like a comparison.
+    pub attempt_index: i32,
+    /// The point in time the attempt was started at. If this isn't known, the
+    /// point in time it ended at is used instead. This is `None` if neither is
+    /// known.
+    pub date: Option<AtomicDateTime>,
+    /// The segment time for the active timing method. This is `None` if the
+    /// segment was skipped.
+    pub time: Option<TimeSpan>,
+    /// Describes whether the segment time is a statistical outlier, which is
+    /// the case if it's further away from the median segment time than the
+    /// outlier threshold allows. Outliers are often caused by missed or
+    /// accidental splits.
+    pub is_outlier: bool,
+}
+
+impl Editor {
+    /// Lists all the elements of the active segment's Segment History for the
+    /// active timing method and flags the ones that are statistical outliers.
+    /// The Segment Histories of the other segments aren't looked at.
+    pub fn segment_history(&self) -> Vec<HistoryElement> {
+        let method = self.selected_method;
+        let segment = self.run.segment(self.active_segment_index());
+        let outliers = Outliers::new(
+            segment.segment_history().iter().filter_map(|&(_, t)| t[method]),
+            self.outlier_threshold,
+        );
+        let dates = self.run
+            .attempt_history()
+            .iter()
+            .map(|a| (a.index(), a.started().or_else(|| a.ended())))
+            .collect::<HashMap<_, _>>();
+
+        segment
+            .segment_history()
+            .iter()
+            .map(|&(index, time)| {
+                let date = dates.get(&index).and_then(|&date| date);
+                let time = time[method];
+
+                HistoryElement {
+                    attempt_index: index,
+                    date: date,
+                    time: time,
+                    is_outlier: time.map_or(false, |t| outliers.contains(t)),
+                }
+            })
+            .collect()
+    }
+
+    /// Accesses the amount of median absolute deviations a segment time needs
+    /// to be away from the median segment time in order to be considered an
+    /// outlier.
+    pub fn outlier_threshold(&self) -> f64 {
+        self.outlier_threshold
+    }
+
+    /// Sets the amount of median absolute deviations a segment time needs to
+    /// be away from the median segment time in order to be considered an
+    /// outlier.
+    pub fn set_outlier_threshold(&mut self, threshold: f64) {
+        self.outlier_threshold = threshold;
+    }
+
+    /// Removes the segment time of the attempt with the index provided from
+    /// the active segment's Segment History. If it was the Best Segment Time,
+    /// the Best Segment Time is recalculated from the remaining history.
+    pub fn remove_segment_history_element(&mut self, attempt_index: i32) {
+        let index = self.active_segment_index();
+        remove_segment_time(self.run.segment_mut(index), attempt_index);
+        self.times_modified();
+        self.fix();
+    }
+
+    /// Sets the segment time of the attempt with the index provided in the
+    /// active segment's Segment History for the active timing method. The
+    /// Best Segment Time is updated accordingly.
+    pub fn set_segment_history_time(&mut self, attempt_index: i32, time: Option<TimeSpan>) {
+        let method = self.selected_method;
+        let index = self.active_segment_index();
+        {
+            let segment = self.run.segment_mut(index);
+            let previous = match segment.segment_history_mut().get_mut(attempt_index) {
+                Some(element) => {
+                    let previous = element[method];
+                    element[method] = time;
+                    previous
+                }
+                None => return,
+            };
+            forget_segment_time(segment, method, previous);
+
+            let best_segment_time = &mut segment.best_segment_time_mut()[method];
+            if let Some(time) = time {
+                if best_segment_time.map_or(true, |b| time < b) {
+                    *best_segment_time = Some(time);
+                }
+            }
+        }
+        self.times_modified();
+        self.fix();
+    }
+
+    /// Parses a segment time from a string and sets it for the attempt with
+    /// the index provided in the active segment's Segment History for the
+    /// active timing method.
+    pub fn parse_and_set_segment_history_time<S>(
+        &mut self,
+        attempt_index: i32,
+        time: S,
+    ) -> Result<(), ParseError>
+    where
+        S: AsRef<str>,
+    {
+        self.set_segment_history_time(attempt_index, parse_positive(time)?);
+        Ok(())
+    }
+
+    /// Removes all the segment times from the active segment's Segment History
+    /// that are flagged as outliers for the active timing method. This is
+    /// recorded as a single undo step.
+    pub fn remove_outliers(&mut self) {
+        let outliers = self.segment_history()
+            .into_iter()
+            .filter(|e| e.is_outlier)
+            .map(|e| e.attempt_index)
+            .collect::<Vec<_>>();
+        if outliers.is_empty() {
+            return;
+        }
+
+        let index = self.active_segment_index();
+        for attempt_index in outliers {
+            remove_segment_time(self.run.segment_mut(index), attempt_index);
+        }
+        self.times_modified();
+        self.fix();
+    }
+}
+
+/// Detects outliers based on the median absolute deviation of a set of
+/// segment times.
+struct Outliers {
+    median: f64,
+    max_deviation: f64,
+}
+
+impl Outliers {
+    fn new<I>(times: I, threshold: f64) -> Self
+    where
+        I: IntoIterator<Item = TimeSpan>,
+    {
+        let mut times = times
+            .into_iter()
+            .map(|t| t.total_seconds())
+            .collect::<Vec<_>>();
+        let median_time = median(&mut times);
+        let mut deviations = times
+            .iter()
+            .map(|t| (t - median_time).abs())
+            .collect::<Vec<_>>();
+        let median_deviation = median(&mut deviations);
+
+        Outliers {
+            median: median_time,
+            max_deviation: threshold * median_deviation,
+        }
+    }
+
+    fn contains(&self, time: TimeSpan) -> bool {
+        // If more than half of the times are the same, there's no meaningful
+        // deviation to compare against, so nothing is considered an outlier.
+        self.max_deviation > 0.0
+            && (time.total_seconds() - self.median).abs() > self.max_deviation
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        0.5 * (values[middle - 1] + values[middle])
+    } else {
+        values[middle]
+    }
+}
@@ -4,22 +4,33 @@
 //! current state of the editor as state objects that can be visualized by any
 //! kind of User Interface.
 
+use std::collections::VecDeque;
 use std::num::ParseIntError;
-use std::mem::swap;
-use {comparison, unicase, Image, Run, Segment, Time, TimeSpan, TimingMethod};
+use std::mem::{replace, swap};
+use {comparison, unicase, Attempt, Image, Run, RunMetadata, Segment, Time, TimeSpan,
+     TimingMethod};
+use super::{Fix, Issue, MAX_ICON_SIZE};
 use super::run::{ComparisonError, ComparisonResult};
 use time::ParseError as ParseTimeSpanError;
 
 pub mod cleaning;
+pub mod csv;
+mod history;
 mod segment_row;
 mod state;
 #[cfg(test)]
 mod tests;
 
+pub use self::history::{HistoryElement, DEFAULT_OUTLIER_THRESHOLD};
 pub use self::segment_row::SegmentRow;
-pub use self::state::{Buttons as ButtonsState, Segment as SegmentState, State};
+pub use self::state::{Buttons as ButtonsState, Metadata as MetadataState,
+                      Segment as SegmentState, SegmentHistoryElement as SegmentHistoryElementState,
+                      State};
 pub use self::cleaning::SumOfBestCleaner;
 
+/// The maximum amount of changes that can be undone.
+const MAX_UNDO_STEPS: usize = 100;
+
 quick_error! {
     /// Describes an Error that occurred while parsing a time.
     #[derive(Debug)]
@@ -47,8 +58,10 @@ quick_error! {
     /// Error type for a failed Rename
     #[derive(PartialEq, Debug)]
     pub enum RenameError {
-        /// Old Comparison was not found durring rename.
+        /// The old name was not found during the rename.
         OldNameNotFound {}
+        /// The new name is already used by another variable.
+        NameAlreadyInUse {}
         /// Name was invalid
         InvalidName(err: ComparisonError) {
             from()
@@ -68,6 +81,11 @@ pub struct Editor {
     game_icon_id: usize,
     segment_icon_ids: Vec<usize>,
     segment_times: Vec<Option<TimeSpan>>,
+    snapshot: Run,
+    has_unrecorded_changes: bool,
+    undo_stack: VecDeque<Run>,
+    redo_stack: Vec<Run>,
+    outlier_threshold: f64,
 }
 
 impl Editor {
@@ -82,6 +100,7 @@ impl Editor {
         let personal_best_time = run.segments().last().unwrap().personal_best_split_time();
 
         let mut editor = Self {
+            snapshot: run.clone(),
             run: run,
             selected_method: TimingMethod::RealTime,
             selected_segments: vec![0],
@@ -89,6 +108,10 @@ impl Editor {
             game_icon_id: 0,
             segment_icon_ids: Vec::with_capacity(len),
             segment_times: Vec::with_capacity(len),
+            has_unrecorded_changes: false,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            outlier_threshold: DEFAULT_OUTLIER_THRESHOLD,
         };
 
         editor.update_segment_list();
@@ -175,6 +198,77 @@ impl Editor {
 
     fn raise_run_edited(&mut self) {
         self.run.mark_as_changed();
+        self.has_unrecorded_changes = true;
+        self.save_undo_step();
+    }
+
+    /// Records the changes made to the Run since the last undo step as a new
+    /// undo step. Every modification raises this once it's done, but the Sum
+    /// of Best Cleaner modifies the Run directly, so its changes are only
+    /// recorded when this is called the next time.
+    fn save_undo_step(&mut self) {
+        if self.has_unrecorded_changes {
+            self.has_unrecorded_changes = false;
+            let previous = replace(&mut self.snapshot, self.run.clone());
+            self.undo_stack.push_back(previous);
+            if self.undo_stack.len() > MAX_UNDO_STEPS {
+                self.undo_stack.pop_front();
+            }
+            self.redo_stack.clear();
+        }
+    }
+
+    /// Restores the editor's state after the Run got replaced by undoing or
+    /// redoing a change.
+    fn restore(&mut self) {
+        self.run.mark_as_changed();
+        self.snapshot = self.run.clone();
+
+        let len = self.run.len();
+        self.selected_segments.retain(|&i| i < len);
+        if self.selected_segments.is_empty() {
+            self.selected_segments.push(0);
+        }
+        self.previous_personal_best_time = self.run
+            .segments()
+            .last()
+            .unwrap()
+            .personal_best_split_time();
+        self.update_segment_list();
+    }
+
+    /// Checks if there is a change that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.has_unrecorded_changes
+    }
+
+    /// Checks if there is an undone change that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty() && !self.has_unrecorded_changes
+    }
+
+    /// Undoes the most recent change made to the Run. This includes all the
+    /// changes made through Segment Rows, as well as the ones made with the Sum
+    /// of Best Cleaner. If there's nothing to undo, nothing happens.
+    pub fn undo(&mut self) {
+        self.save_undo_step();
+        if let Some(previous) = self.undo_stack.pop_back() {
+            let current = replace(&mut self.run, previous);
+            self.redo_stack.push(current);
+            self.restore();
+        }
+    }
+
+    /// Redoes the most recently undone change. Any change made to the Run after
+    /// undoing discards all the changes that could've been redone. If there's
+    /// nothing to redo, nothing happens.
+    pub fn redo(&mut self) {
+        self.save_undo_step();
+        if let Some(next) = self.redo_stack.pop() {
+            let current = replace(&mut self.run, next);
+            self.undo_stack.push_back(current);
+            self.restore();
+        }
     }
 
     /// Accesses the name of the game.
@@ -188,8 +282,8 @@ impl Editor {
         S: AsRef<str>,
     {
         self.run.set_game_name(name);
-        self.raise_run_edited();
         self.run.clear_run_id();
+        self.raise_run_edited();
     }
 
     /// Accesses the name of the category.
@@ -203,8 +297,8 @@ impl Editor {
         S: AsRef<str>,
     {
         self.run.set_category_name(name);
-        self.raise_run_edited();
         self.run.clear_run_id();
+        self.raise_run_edited();
     }
 
     /// Accesses the timer offset. The timer offset specifies the time, the
@@ -260,8 +354,11 @@ impl Editor {
         self.run.game_icon()
     }
 
-    /// Sets the game's icon.
+    /// Sets the game's icon. Icons larger than `MAX_ICON_SIZE` are
+    /// downscaled.
     pub fn set_game_icon<D: Into<Image>>(&mut self, image: D) {
+        let mut image = image.into();
+        image.downscale(MAX_ICON_SIZE);
         self.run.set_game_icon(image);
         self.raise_run_edited();
     }
@@ -272,6 +369,84 @@ impl Editor {
         self.raise_run_edited();
     }
 
+    /// Accesses the Run's metadata, like the platform and region of the game.
+    pub fn metadata(&self) -> &RunMetadata {
+        self.run.metadata()
+    }
+
+    /// Sets the speedrun.com Run ID of the run. You need to ensure that the
+    /// record on speedrun.com matches up with the Personal Best of this run.
+    /// This may be empty if there's no association.
+    pub fn set_run_id<S>(&mut self, id: S)
+    where
+        S: AsRef<str>,
+    {
+        self.run.metadata_mut().set_run_id(id);
+        self.raise_run_edited();
+    }
+
+    /// Sets the name of the platform this game is run on. This may be empty if
+    /// it's not specified.
+    pub fn set_platform_name<S>(&mut self, name: S)
+    where
+        S: AsRef<str>,
+    {
+        self.run.metadata_mut().set_platform_name(name);
+        self.raise_run_edited();
+    }
+
+    /// Specifies whether this speedrun is done on an emulator. Keep in mind
+    /// that `false` may also mean that this information is simply not known.
+    pub fn set_emulator_usage(&mut self, uses_emulator: bool) {
+        self.run.metadata_mut().set_emulator_usage(uses_emulator);
+        self.raise_run_edited();
+    }
+
+    /// Sets the name of the region this game is from. This may be empty if it's
+    /// not specified.
+    pub fn set_region_name<S>(&mut self, name: S)
+    where
+        S: AsRef<str>,
+    {
+        self.run.metadata_mut().set_region_name(name);
+        self.raise_run_edited();
+    }
+
+    /// Sets the value of a variable. If there's no variable with that name
+    /// yet, it is added after all the other variables. A variable is an
+    /// arbitrary key value pair storing additional information about the
+    /// category.
+    pub fn set_variable<N, V>(&mut self, name: N, value: V)
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.run.metadata_mut().add_variable(name, value);
+        self.raise_run_edited();
+    }
+
+    /// Removes the variable with the name provided, if it exists.
+    pub fn remove_variable(&mut self, name: &str) {
+        self.run.metadata_mut().remove_variable(name);
+        self.raise_run_edited();
+    }
+
+    /// Renames a variable while keeping its value. The variable can't be
+    /// renamed if there already is a variable with the new name.
+    pub fn rename_variable(&mut self, old: &str, new: &str) -> Result<(), RenameError> {
+        if old == new {
+            return Ok(());
+        }
+        if self.run.metadata().variable(old).is_none() {
+            return Err(RenameError::OldNameNotFound);
+        }
+        if !self.run.metadata_mut().rename_variable(old, new) {
+            return Err(RenameError::NameAlreadyInUse);
+        }
+        self.raise_run_edited();
+        Ok(())
+    }
+
     /// Accesses all the custom comparisons that exist on the Run.
     pub fn custom_comparisons(&self) -> &[String] {
         self.run.custom_comparisons()
@@ -289,7 +464,6 @@ impl Editor {
             self.run.clear_run_id();
             self.previous_personal_best_time = pb_split_time;
         }
-        self.raise_run_edited();
     }
 
     fn fix(&mut self) {
@@ -583,6 +757,136 @@ impl Editor {
         self.fix();
     }
 
+    /// Checks if the active segment can be merged with the segment after it.
+    /// The last segment can't be merged.
+    pub fn can_merge_segments(&self) -> bool {
+        self.active_segment_index() + 1 < self.run.len()
+    }
+
+    /// Merges the active segment with the segment after it, unless the active
+    /// segment is the last segment. The merged segment keeps the name, the
+    /// icon and the split times of the segment after the active segment. The
+    /// segment times of both segments are combined in its Segment History. Its
+    /// Best Segment Time is the sum of both Best Segment Times, unless the
+    /// Segment History contains a faster time. If either segment has no Best
+    /// Segment Time, it is recalculated from the Segment History instead. The
+    /// merged segment then becomes the only selected segment and also the
+    /// active segment.
+    pub fn merge_segments(&mut self) {
+        if !self.can_merge_segments() {
+            return;
+        }
+
+        let index = self.active_segment_index();
+
+        // The Best Segment Times can only be combined if both segments have
+        // one. Otherwise the merged segment's one has to be recalculated.
+        let mut best_segments_combined = [false; 2];
+        for (combined, &method) in best_segments_combined
+            .iter_mut()
+            .zip(&TimingMethod::all())
+        {
+            *combined = self.run.segment(index).best_segment_time()[method].is_some()
+                && self.run.segment(index + 1).best_segment_time()[method].is_some();
+        }
+
+        self.fix_after_deletion(index);
+        self.run.segments_mut().remove(index);
+
+        for (&combined, &method) in best_segments_combined.iter().zip(&TimingMethod::all()) {
+            if combined {
+                continue;
+            }
+            let segment = self.run.segment_mut(index);
+            let best_segment_time = segment
+                .segment_history()
+                .iter()
+                .filter_map(|&(_, t)| t[method])
+                .min();
+            if best_segment_time.is_some() {
+                segment.best_segment_time_mut()[method] = best_segment_time;
+            }
+        }
+
+        self.select_only(index);
+
+        self.fix();
+    }
+
+    /// Splits the active segment into two segments by inserting a new empty
+    /// segment above it. If a ratio is provided, all the segment times of the
+    /// active segment are distributed between the two segments, with the new
+    /// segment receiving the given share of each of them. The ratio is clamped
+    /// to be between 0 and 1. Otherwise the new segment is considered skipped
+    /// in all the attempts and the comparisons, so the active segment keeps
+    /// all of its times. The new segment is then the only selected segment and
+    /// also the active segment.
+    pub fn split_segment(&mut self, ratio: Option<f64>) {
+        let index = self.active_segment_index();
+        let mut segment = Segment::new("");
+
+        if let Some(ratio) = ratio {
+            let ratio = ratio.max(0.0).min(1.0);
+            let share = |t: TimeSpan| TimeSpan::from_milliseconds(t.total_milliseconds() * ratio);
+
+            let comparisons = self.run
+                .segment_mut(index)
+                .comparisons_mut()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            for comparison in &comparisons {
+                let previous_time = if index > 0 {
+                    self.run.segment(index - 1).comparison(comparison)
+                } else {
+                    Time::zero()
+                };
+                let time = self.run.segment(index).comparison(comparison);
+                let split_time = segment.comparison_mut(comparison);
+                for &method in &TimingMethod::all() {
+                    split_time[method] = catch! {
+                        let previous_time = previous_time[method]?;
+                        previous_time + share(time[method]? - previous_time)
+                    };
+                }
+            }
+
+            let current = self.run.segment_mut(index);
+
+            for &mut (run_index, ref mut time) in current.segment_history_mut().iter_mut() {
+                let mut first = Time::default();
+                for &method in &TimingMethod::all() {
+                    if let Some(whole) = time[method] {
+                        let part = share(whole);
+                        first[method] = Some(part);
+                        time[method] = Some(whole - part);
+                    }
+                }
+                segment.segment_history_mut().insert(run_index, first);
+            }
+
+            for &method in &TimingMethod::all() {
+                if let Some(best_segment_time) = current.best_segment_time()[method] {
+                    let part = share(best_segment_time);
+                    segment.best_segment_time_mut()[method] = Some(part);
+                    current.best_segment_time_mut()[method] = Some(best_segment_time - part);
+                }
+            }
+        } else {
+            for &(run_index, _) in self.run.segment(index).segment_history() {
+                segment
+                    .segment_history_mut()
+                    .insert(run_index, Time::default());
+            }
+        }
+
+        self.run.segments_mut().insert(index, segment);
+
+        self.select_only(index);
+
+        self.fix();
+    }
+
     /// Adds a new custom comparison. It can't be added if it starts with
     /// `[Race]` or already exists.
     pub fn add_comparison<S: Into<String>>(&mut self, comparison: S) -> ComparisonResult<()> {
@@ -649,6 +953,7 @@ impl Editor {
             .retain(|c| c != comparison);
 
         if self.run.comparisons().any(|c| c == comparison) {
+            self.raise_run_edited();
             return;
         }
 
@@ -689,6 +994,79 @@ impl Editor {
         Ok(())
     }
 
+    /// Removes the attempt with the index provided from the Attempt History,
+    /// along with all of its segment times. Best Segment Times achieved in the
+    /// attempt are recalculated from the remaining history. If the attempt was
+    /// the Personal Best, the fastest remaining valid attempt becomes the new
+    /// Personal Best.
+    pub fn remove_attempt(&mut self, index: i32) {
+        if self.run.remove_attempt(index) {
+            self.times_modified();
+            self.fix();
+        }
+    }
+
+    /// Marks the attempt with the index provided as invalid, for example
+    /// because it was cheated, glitched or done in the wrong category. The
+    /// attempt stays in the Attempt History, but its segment times are removed
+    /// just like when removing the attempt.
+    pub fn invalidate_attempt(&mut self, index: i32) {
+        if self.run.invalidate_attempt(index) {
+            self.times_modified();
+            self.fix();
+        }
+    }
+
+    fn attempt_mut(&mut self, index: i32) -> Option<&mut Attempt> {
+        self.run
+            .attempt_history_mut()
+            .iter_mut()
+            .find(|a| a.index() == index)
+    }
+
+    /// Sets the note attached to the attempt with the index provided. The note
+    /// may be empty to remove it. Returns `false` if there is no attempt with
+    /// that index.
+    pub fn set_attempt_note<S>(&mut self, index: i32, note: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        if let Some(attempt) = self.attempt_mut(index) {
+            attempt.set_note(note);
+        } else {
+            return false;
+        }
+        self.raise_run_edited();
+        true
+    }
+
+    /// Attaches a tag to the attempt with the index provided. Returns `false`
+    /// if there is no attempt with that index.
+    pub fn add_attempt_tag<S>(&mut self, index: i32, tag: S) -> bool
+    where
+        S: Into<String>,
+    {
+        if let Some(attempt) = self.attempt_mut(index) {
+            attempt.add_tag(tag);
+        } else {
+            return false;
+        }
+        self.raise_run_edited();
+        true
+    }
+
+    /// Removes a tag from the attempt with the index provided. Returns `false`
+    /// if there is no attempt with that index.
+    pub fn remove_attempt_tag(&mut self, index: i32, tag: &str) -> bool {
+        if let Some(attempt) = self.attempt_mut(index) {
+            attempt.remove_tag(tag);
+        } else {
+            return false;
+        }
+        self.raise_run_edited();
+        true
+    }
+
     /// Clears out the Attempt History and the Segment Histories of all the
     /// segments.
     pub fn clear_history(&mut self) {
@@ -711,7 +1089,26 @@ impl Editor {
     /// combined segment time might be faster than the sum of the individual
     /// best segments. The Sum of Best Cleaner will point out all of these and
     /// allows you to delete them individually if any of them seem wrong.
+    /// The changes made with the Sum of Best Cleaner can be undone as a single
+    /// step.
     pub fn clean_sum_of_best(&mut self) -> SumOfBestCleaner {
-        SumOfBestCleaner::new(&mut self.run)
+        self.save_undo_step();
+        SumOfBestCleaner::with_change_tracking(&mut self.run, &mut self.has_unrecorded_changes)
+    }
+
+    /// Checks the Run for inconsistencies and returns all the issues found.
+    /// The fixes the issues provide can be offered to the user and applied
+    /// with `apply_fix`.
+    pub fn lint(&self) -> Vec<Issue> {
+        self.run.lint()
+    }
+
+    /// Applies a fix provided by an issue found by `lint`. The Run should be
+    /// linted again afterwards, as the fix may have resolved or changed other
+    /// issues as well.
+    pub fn apply_fix(&mut self, fix: &Fix) {
+        self.run.apply_fix(fix);
+        self.times_modified();
+        self.fix();
     }
 }
@@ -1,5 +1,6 @@
 use {Image, TimeSpan};
 use super::{Editor, ParseError};
+use run::MAX_ICON_SIZE;
 
 /// A Segment Row describes the segment in the Run Editor actively selected for
 /// editing.
@@ -21,15 +22,18 @@ impl<'a> SegmentRow<'a> {
         self.editor.run.segment(self.index).icon()
     }
 
-    /// Sets the icon of the segment.
+    /// Sets the icon of the segment. Icons larger than `MAX_ICON_SIZE` are
+    /// downscaled.
     pub fn set_icon<D: Into<Image>>(&mut self, image: D) {
-        self.editor.run.segment_mut(self.index).set_icon(image);
+        let mut image = image.into();
+        image.downscale(MAX_ICON_SIZE);
+        self.editor.run.set_segment_icon(self.index, image);
         self.editor.raise_run_edited();
     }
 
     /// Removes the icon of the segment.
     pub fn remove_icon(&mut self) {
-        self.editor.run.segment_mut(self.index).set_icon(&[]);
+        self.editor.run.set_segment_icon(self.index, &[]);
         self.editor.raise_run_edited();
     }
 
@@ -160,7 +164,7 @@ impl<'a> SegmentRow<'a> {
     }
 }
 
-fn parse_positive<S>(time: S) -> Result<Option<TimeSpan>, ParseError>
+pub(super) fn parse_positive<S>(time: S) -> Result<Option<TimeSpan>, ParseError>
 where
     S: AsRef<str>,
 {
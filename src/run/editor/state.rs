@@ -4,6 +4,7 @@ use time::formatter::{Accuracy, Short, TimeFormatter};
 use time::formatter::none_wrapper::EmptyWrapper;
 use serde_json::{to_writer, Result as JsonResult};
 use std::io::Write;
+use chrono::Local;
 
 /// Represents the current state of the Run Editor in order to visualize it
 /// properly.
@@ -30,10 +31,51 @@ pub struct State {
     pub segments: Vec<Segment>,
     /// The names of all the custom comparisons that exist for this Run.
     pub comparison_names: Vec<String>,
+    /// Additional information about the Run, like the platform and region of
+    /// the game.
+    pub metadata: Metadata,
+    /// All the elements of the active segment's Segment History for the
+    /// active timing method.
+    pub segment_history: Vec<SegmentHistoryElement>,
     /// Describes which actions are currently available.
     pub buttons: Buttons,
 }
 
+/// Describes the Run's metadata. All of this information is optional.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The speedrun.com Run ID of the run. This may be empty if there's no
+    /// association.
+    pub run_id: String,
+    /// The name of the platform this game is run on. This may be empty if it's
+    /// not specified.
+    pub platform_name: String,
+    /// Specifies whether this speedrun is done on an emulator. `false` may
+    /// also mean that this information is simply not known.
+    pub uses_emulator: bool,
+    /// The name of the region this game is from. This may be empty if it's
+    /// not specified.
+    pub region_name: String,
+    /// The names and values of all the variables, in the order they were
+    /// added.
+    pub variables: Vec<(String, String)>,
+}
+
+/// Describes a single element of the active segment's Segment History.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SegmentHistoryElement {
+    /// The index of the attempt the segment time belongs to.
+    pub attempt_index: i32,
+    /// The date the attempt was started at, or ended at if that isn't known.
+    /// This is `None` if neither is known.
+    pub date: Option<String>,
+    /// The segment time for the active timing method.
+    pub time: String,
+    /// Describes whether the segment time is a statistical outlier. Outliers
+    /// are often caused by missed or accidental splits.
+    pub is_outlier: bool,
+}
+
 /// Describes which actions are currently available. Depending on how many
 /// segments exist and which ones are selected, only some actions can be
 /// executed successfully.
@@ -50,6 +92,10 @@ pub struct Buttons {
     /// any one of the selected segments is the last segment, then they can't be
     /// moved.
     pub can_move_down: bool,
+    /// Describes whether there is a change that can be undone.
+    pub can_undo: bool,
+    /// Describes whether there is an undone change that can be redone.
+    pub can_redo: bool,
 }
 
 /// Describes the current state of a segment.
@@ -100,12 +146,12 @@ impl State {
 impl Editor {
     /// Calculates the Run Editor's state in order to visualize it.
     pub fn state(&mut self) -> State {
+        self.save_undo_step();
         let formatter = EmptyWrapper::new(Short::with_accuracy(Accuracy::Hundredths));
 
         let icon_change = self.run
             .game_icon()
-            .check_for_change(&mut self.game_icon_id)
-            .map(str::to_owned);
+            .check_for_change(&mut self.game_icon_id);
         let game = self.game_name().to_string();
         let category = self.category_name().to_string();
         let offset = formatter.format(self.offset()).to_string();
@@ -117,10 +163,37 @@ impl Editor {
             .filter(|n| n != personal_best::NAME)
             .collect::<Vec<_>>();
 
+        let metadata = {
+            let metadata = self.run.metadata();
+            Metadata {
+                run_id: metadata.run_id().to_owned(),
+                platform_name: metadata.platform_name().to_owned(),
+                uses_emulator: metadata.uses_emulator(),
+                region_name: metadata.region_name().to_owned(),
+                variables: metadata
+                    .variables()
+                    .map(|(n, v)| (n.clone(), v.clone()))
+                    .collect(),
+            }
+        };
+
+        let segment_history = self.segment_history()
+            .into_iter()
+            .map(|e| SegmentHistoryElement {
+                attempt_index: e.attempt_index,
+                date: e.date
+                    .map(|d| d.time.with_timezone(&Local).format("%F").to_string()),
+                time: formatter.format(e.time).to_string(),
+                is_outlier: e.is_outlier,
+            })
+            .collect();
+
         let buttons = Buttons {
             can_remove: self.can_remove_segments(),
             can_move_up: self.can_move_segments_up(),
             can_move_down: self.can_move_segments_down(),
+            can_undo: self.can_undo(),
+            can_redo: self.can_redo(),
         };
         let mut segments = Vec::with_capacity(self.run.len());
 
@@ -142,8 +215,7 @@ impl Editor {
             let icon_change = self.run
                 .segment(segment_index)
                 .icon()
-                .check_for_change(&mut self.segment_icon_ids[segment_index])
-                .map(str::to_owned);
+                .check_for_change(&mut self.segment_icon_ids[segment_index]);
 
             let selected = if self.active_segment_index() == segment_index {
                 SelectionState::Active
@@ -173,6 +245,8 @@ impl Editor {
             timing_method: timing_method,
             segments: segments,
             comparison_names: comparison_names,
+            metadata: metadata,
+            segment_history: segment_history,
             buttons: buttons,
         }
     }
@@ -1,5 +1,5 @@
 use {Run, Segment, Time, TimeSpan};
-use super::Editor;
+use super::{Editor, RenameError};
 
 #[test]
 fn new_best_segment() {
@@ -141,3 +141,340 @@ fn modifying_last_segments_split_time_clears_run_id() {
         .set_split_time(Some(TimeSpan::from_seconds(2.0)));
     assert_eq!(editor.run().metadata().run_id(), "");
 }
+
+#[test]
+fn undo_and_redo() {
+    let mut run = Run::new();
+    run.push_segment(Segment::new("A"));
+    run.push_segment(Segment::new("B"));
+
+    let mut editor = Editor::new(run).unwrap();
+    assert!(!editor.can_undo());
+    assert!(!editor.can_redo());
+
+    editor.active_segment().set_name("C");
+    editor.select_only(1);
+    editor.remove_segments();
+    assert_eq!(editor.run().len(), 1);
+
+    editor.undo();
+    assert_eq!(editor.run().len(), 2);
+    assert_eq!(editor.run().segment(0).name(), "C");
+    assert!(editor.can_redo());
+
+    editor.undo();
+    assert_eq!(editor.run().segment(0).name(), "A");
+    assert!(!editor.can_undo());
+
+    editor.redo();
+    assert_eq!(editor.run().segment(0).name(), "C");
+
+    editor.set_game_name("Game");
+    assert!(!editor.can_redo());
+    editor.redo();
+    assert_eq!(editor.run().len(), 2);
+
+    let state = editor.state();
+    assert!(state.buttons.can_undo);
+    assert!(!state.buttons.can_redo);
+}
+
+#[test]
+fn undo_steps_are_limited() {
+    let mut run = Run::new();
+    run.push_segment(Segment::new("A"));
+
+    let mut editor = Editor::new(run).unwrap();
+    for i in 0..150 {
+        editor.set_game_name(i.to_string());
+    }
+    for _ in 0..100 {
+        assert!(editor.can_undo());
+        editor.undo();
+    }
+    assert!(!editor.can_undo());
+    assert_eq!(editor.game_name(), "49");
+}
+
+#[test]
+fn opening_the_sum_of_best_cleaner_records_no_undo_step() {
+    let mut run = Run::new();
+    run.push_segment(Segment::new("A"));
+
+    let mut editor = Editor::new(run).unwrap();
+    editor.clean_sum_of_best();
+    assert!(!editor.can_undo());
+    editor.state();
+    assert!(!editor.can_undo());
+}
+
+fn run_with_history() -> Run {
+    let mut run = Run::new();
+    for &(name, split_time, best_segment_time) in &[("A", 10.0, 9.0), ("B", 30.0, 18.0)] {
+        let mut segment = Segment::new(name);
+        segment.set_personal_best_split_time(
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(split_time))),
+        );
+        segment.set_best_segment_time(
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(best_segment_time))),
+        );
+        run.push_segment(segment);
+    }
+    for &(index, a, b) in &[(1, 10.0, 20.0), (2, 9.0, 22.0), (3, 12.0, 18.0)] {
+        run.add_attempt_with_index(
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(a + b))),
+            index,
+            None,
+            None,
+            None,
+        );
+        run.segment_mut(0).segment_history_mut().insert(
+            index,
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(a))),
+        );
+        run.segment_mut(1).segment_history_mut().insert(
+            index,
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(b))),
+        );
+    }
+    run
+}
+
+#[test]
+fn merge_segments_combines_history() {
+    let mut editor = Editor::new(run_with_history()).unwrap();
+    assert!(editor.can_merge_segments());
+    editor.merge_segments();
+
+    let run = editor.close();
+    assert_eq!(run.len(), 1);
+    let segment = run.segment(0);
+    assert_eq!(segment.name(), "B");
+    assert_eq!(
+        segment.segment_history().get(2).unwrap().real_time,
+        Some(TimeSpan::from_seconds(31.0))
+    );
+    assert_eq!(
+        segment.best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(27.0))
+    );
+    assert_eq!(
+        segment.personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(30.0))
+    );
+}
+
+#[test]
+fn merge_segments_keeps_best_segments_with_pruned_history() {
+    let mut run = run_with_history();
+    for segment in run.segments_mut() {
+        segment.segment_history_mut().clear();
+    }
+
+    let mut editor = Editor::new(run).unwrap();
+    editor.merge_segments();
+
+    let run = editor.close();
+    assert_eq!(
+        run.segment(0).best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(27.0))
+    );
+}
+
+#[test]
+fn merge_segments_recalculates_missing_best_segments() {
+    let mut run = run_with_history();
+    run.segment_mut(0).best_segment_time_mut().real_time = None;
+
+    let mut editor = Editor::new(run).unwrap();
+    editor.merge_segments();
+
+    let run = editor.close();
+    assert_eq!(
+        run.segment(0).best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(30.0))
+    );
+}
+
+#[test]
+fn editing_missing_attempts_records_no_undo_step() {
+    let mut editor = Editor::new(run_with_history()).unwrap();
+    assert!(!editor.set_attempt_note(7, "Note"));
+    assert!(!editor.add_attempt_tag(7, "Tag"));
+    assert!(!editor.remove_attempt_tag(7, "Tag"));
+    assert!(!editor.can_undo());
+
+    assert!(editor.set_attempt_note(2, "Note"));
+    assert!(editor.add_attempt_tag(2, "Tag"));
+    assert!(editor.can_undo());
+
+    let run = editor.close();
+    let attempt = run.attempt_history().iter().find(|a| a.index() == 2).unwrap();
+    assert_eq!(attempt.note(), "Note");
+    assert_eq!(attempt.tags(), &[String::from("Tag")][..]);
+}
+
+#[test]
+fn split_segment_distributes_history() {
+    let mut editor = Editor::new(run_with_history()).unwrap();
+    editor.select_only(1);
+    editor.split_segment(Some(0.5));
+
+    let run = editor.close();
+    assert_eq!(run.len(), 3);
+    assert_eq!(
+        run.segment(1).personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(20.0))
+    );
+    assert_eq!(
+        run.segment(1).segment_history().get(1).unwrap().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+    assert_eq!(
+        run.segment(2).segment_history().get(1).unwrap().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+    assert_eq!(
+        run.segment(2).personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(30.0))
+    );
+}
+
+#[test]
+fn split_segment_without_ratio_skips_the_new_segment() {
+    let mut editor = Editor::new(run_with_history()).unwrap();
+    editor.split_segment(None);
+
+    let run = editor.close();
+    assert_eq!(run.len(), 3);
+    assert_eq!(run.segment(0).personal_best_split_time().real_time, None);
+    assert_eq!(run.segment(0).segment_history().get(1).unwrap().real_time, None);
+    assert_eq!(
+        run.segment(1).segment_history().get(1).unwrap().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+}
+
+#[test]
+fn edit_metadata() {
+    let mut run = Run::new();
+    run.push_segment(Segment::new(""));
+    let mut editor = Editor::new(run).unwrap();
+
+    editor.set_platform_name("Nintendo Switch");
+    editor.set_region_name("USA");
+    editor.set_emulator_usage(true);
+    editor.set_variable("Amiibos", "No");
+    editor.set_variable("Glitches", "Yes");
+    editor.set_variable("Mode", "Normal");
+
+    assert_eq!(editor.rename_variable("Amiibos", "Amiibo"), Ok(()));
+    assert_eq!(
+        editor.rename_variable("Amiibo", "Mode"),
+        Err(RenameError::NameAlreadyInUse)
+    );
+    assert_eq!(
+        editor.rename_variable("Missing", "Other"),
+        Err(RenameError::OldNameNotFound)
+    );
+    editor.remove_variable("Glitches");
+
+    let state = editor.state();
+    assert_eq!(state.metadata.platform_name, "Nintendo Switch");
+    assert_eq!(state.metadata.region_name, "USA");
+    assert!(state.metadata.uses_emulator);
+    assert_eq!(
+        state.metadata.variables,
+        vec![
+            ("Amiibo".to_owned(), "No".to_owned()),
+            ("Mode".to_owned(), "Normal".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn segment_history_outliers() {
+    let mut run = run_with_history();
+    run.segment_mut(0).segment_history_mut().insert(
+        4,
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(1.0))),
+    );
+    run.segment_mut(0).set_best_segment_time(
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(1.0))),
+    );
+    let mut editor = Editor::new(run).unwrap();
+
+    let outliers = editor
+        .segment_history()
+        .into_iter()
+        .filter(|e| e.is_outlier)
+        .map(|e| e.attempt_index)
+        .collect::<Vec<_>>();
+    assert_eq!(outliers, vec![4]);
+
+    editor.remove_outliers();
+    assert_eq!(editor.segment_history().len(), 3);
+    assert_eq!(
+        editor.run().segment(0).best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(9.0))
+    );
+
+    editor.parse_and_set_segment_history_time(2, "11").unwrap();
+    assert_eq!(
+        editor.run().segment(0).best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+
+    // The Personal Best's segment time of 10 seconds still is the best one.
+    editor.remove_segment_history_element(1);
+    assert_eq!(
+        editor.run().segment(0).best_segment_time().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+    assert!(editor.run().segment(0).segment_history().get(1).is_none());
+}
+
+#[test]
+fn csv_round_trip() {
+    use super::csv::{Delimiter, Matching};
+
+    let mut editor = Editor::new(run_with_history()).unwrap();
+    let mut table = Vec::new();
+    editor.export_csv(&mut table, Delimiter::Comma).unwrap();
+    assert!(table.starts_with(b"Segment Name,Split Time (Real Time),Segment Time (Real Time),"));
+
+    editor
+        .import_csv(
+            &b"Segment Name\tSegment Time (Real Time)\nB\t15\n"[..],
+            Delimiter::Tab,
+            Matching::ByName,
+        )
+        .unwrap();
+    assert_eq!(
+        editor.run().segment(1).personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(25.0))
+    );
+
+    // Nothing is applied if any of the rows is invalid.
+    assert!(
+        editor
+            .import_csv(
+                &b"Segment Name,Split Time (Real Time)\nA,5\nB,abc\n"[..],
+                Delimiter::Comma,
+                Matching::ByPosition,
+            )
+            .is_err()
+    );
+    assert_eq!(
+        editor.run().segment(0).personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(10.0))
+    );
+
+    editor
+        .import_csv(&table[..], Delimiter::Comma, Matching::ByPosition)
+        .unwrap();
+    assert_eq!(
+        editor.run().segment(1).personal_best_split_time().real_time,
+        Some(TimeSpan::from_seconds(30.0))
+    );
+}
@@ -0,0 +1,117 @@
+//! Provides the export of the Attempt History and the Segment Histories of a
+//! Run as CSV tables. Dates are written in the RFC 3339 format and times are
+//! written as seconds. Unknown dates and times are written as empty fields.
+//!
+//! # Examples
+//!
+//! ```
+//! use livesplit_core::{Run, Segment};
+//! use livesplit_core::run::export::csv;
+//!
+//! let mut run = Run::new();
+//! run.push_segment(Segment::new("Cap Kingdom"));
+//!
+//! let mut attempts = Vec::new();
+//! csv::write_attempts(&run, &mut attempts).unwrap();
+//!
+//! let mut segment_history = Vec::new();
+//! csv::write_segment_history(&run, &mut segment_history).unwrap();
+//! ```
+
+use std::io::{self, Write};
+use {AtomicDateTime, Run, TimeSpan, TimingMethod};
+use run::csv_util::write_row;
+
+/// Writes a table of all the attempts in the Attempt History of the Run. The
+/// table has one row per attempt, with the attempt's index, the dates it
+/// started and ended at, its final Real Time and Game Time and the amount of
+/// time it has been paused for.
+pub fn write_attempts<W: Write>(run: &Run, mut writer: W) -> io::Result<()> {
+    write_row(
+        &mut writer,
+        ',',
+        &[
+            "Attempt Index",
+            "Started",
+            "Ended",
+            "Real Time",
+            "Game Time",
+            "Pause Time",
+        ],
+    )?;
+
+    for attempt in run.attempt_history() {
+        let time = attempt.time();
+        write_row(
+            &mut writer,
+            ',',
+            &[
+                attempt.index().to_string(),
+                format_date(attempt.started()),
+                format_date(attempt.ended()),
+                format_time(time[TimingMethod::RealTime]),
+                format_time(time[TimingMethod::GameTime]),
+                format_time(attempt.pause_time()),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes a table of the Segment Histories of all the segments in long
+/// format. The table has one row per segment time, with the index of the
+/// attempt it belongs to, the index and the name of the segment and the
+/// segment's Real Time and Game Time. The rows are ordered by the attempt and
+/// then by the segment.
+pub fn write_segment_history<W: Write>(run: &Run, mut writer: W) -> io::Result<()> {
+    write_row(
+        &mut writer,
+        ',',
+        &[
+            "Attempt Index",
+            "Segment Index",
+            "Segment Name",
+            "Real Time",
+            "Game Time",
+        ],
+    )?;
+
+    let mut rows = run.segments()
+        .iter()
+        .enumerate()
+        .flat_map(|(segment_index, segment)| {
+            segment
+                .segment_history()
+                .iter()
+                .map(move |&(attempt_index, time)| (attempt_index, segment_index, time))
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|&(attempt_index, segment_index, _)| (attempt_index, segment_index));
+
+    for (attempt_index, segment_index, time) in rows {
+        write_row(
+            &mut writer,
+            ',',
+            &[
+                attempt_index.to_string(),
+                segment_index.to_string(),
+                run.segment(segment_index).name().to_owned(),
+                format_time(time[TimingMethod::RealTime]),
+                format_time(time[TimingMethod::GameTime]),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn format_date(date: Option<AtomicDateTime>) -> String {
+    date.map(|d| d.time.to_rfc3339().to_string())
+        .unwrap_or_default()
+}
+
+fn format_time(time: Option<TimeSpan>) -> String {
+    time.map(|t| t.total_seconds().to_string())
+        .unwrap_or_default()
+}
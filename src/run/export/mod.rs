@@ -0,0 +1,6 @@
+//! The export module provides ways to export the history of Runs, so it can be
+//! analyzed in other applications, like spreadsheets.
+
+pub mod csv;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
@@ -0,0 +1,288 @@
+//! Provides the export of Runs into a normalized SQLite database, so the
+//! splits of lots of runners can be aggregated and analyzed. Exporting a Run
+//! into a database that already contains it updates the Run's rows to match
+//! it, so new sessions can be exported without creating any duplicates.
+//!
+//! The database consists of the following tables:
+//!
+//! * `runs`: The game, category and runner of each Run along with its
+//!   metadata.
+//! * `variables`: The variables of each Run's metadata.
+//! * `segments`: The segments of each Run with their Personal Best split times
+//!   and their Best Segment Times.
+//! * `comparisons`: The split times of the custom comparisons of each Run.
+//! * `attempts`: The Attempt History of each Run.
+//! * `segment_times`: The Segment Histories of each Run, with one row per
+//!   segment time. The segment times refer to the segments by their index.
+//!
+//! Dates are stored in the RFC 3339 format and times are stored as seconds.
+//! Unknown dates and times are stored as `NULL`.
+//!
+//! This module is only available if the `sqlite` feature is enabled.
+
+use std::collections::HashSet;
+use std::result::Result as StdResult;
+use rusqlite::{self, Connection, Transaction};
+use {AtomicDateTime, Run, TimeSpan, TimingMethod};
+use comparison::personal_best;
+
+quick_error! {
+    /// The Error type for Runs that couldn't be exported.
+    #[derive(Debug)]
+    pub enum Error {
+        /// Failed to access the database.
+        Database(err: rusqlite::Error) {
+            from()
+        }
+    }
+}
+
+/// The Result type for exporting Runs.
+pub type Result<T> = StdResult<T, Error>;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    runner TEXT NOT NULL,
+    game TEXT NOT NULL,
+    category TEXT NOT NULL,
+    attempt_count INTEGER NOT NULL,
+    timer_offset REAL NOT NULL,
+    speedrun_com_id TEXT NOT NULL,
+    platform TEXT NOT NULL,
+    region TEXT NOT NULL,
+    uses_emulator INTEGER NOT NULL,
+    UNIQUE (runner, game, category)
+);
+CREATE TABLE IF NOT EXISTS variables (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (run_id, name)
+);
+CREATE TABLE IF NOT EXISTS segments (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    segment_index INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    split_real_time REAL,
+    split_game_time REAL,
+    best_segment_real_time REAL,
+    best_segment_game_time REAL,
+    PRIMARY KEY (run_id, segment_index)
+);
+CREATE TABLE IF NOT EXISTS comparisons (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    name TEXT NOT NULL,
+    segment_index INTEGER NOT NULL,
+    split_real_time REAL,
+    split_game_time REAL,
+    PRIMARY KEY (run_id, name, segment_index)
+);
+CREATE TABLE IF NOT EXISTS attempts (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    attempt_index INTEGER NOT NULL,
+    started TEXT,
+    ended TEXT,
+    real_time REAL,
+    game_time REAL,
+    pause_time REAL,
+    PRIMARY KEY (run_id, attempt_index)
+);
+CREATE TABLE IF NOT EXISTS segment_times (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    attempt_index INTEGER NOT NULL,
+    segment_index INTEGER NOT NULL,
+    real_time REAL,
+    game_time REAL,
+    PRIMARY KEY (run_id, attempt_index, segment_index)
+);
+";
+
+/// Describes the changes that exporting a Run made to the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// The ID of the Run in the `runs` table.
+    pub run_id: i64,
+    /// The amount of attempts that were added to the `attempts` table. The
+    /// attempts that already were in the database are not counted, even if
+    /// they got updated.
+    pub new_attempts: usize,
+}
+
+/// Creates all the tables of the schema in the database, unless they already
+/// exist. This is done automatically when exporting a Run.
+pub fn create_schema(connection: &Connection) -> Result<()> {
+    connection.execute_batch(SCHEMA)?;
+    Ok(())
+}
+
+/// Exports the Run of the runner provided into the database. The Run is
+/// identified by the runner's name and the names of the game and category. If
+/// the database already contains the Run, all of its rows are updated to match
+/// the Run. Attempts that got edited since the last export are updated, while
+/// attempts and segment times that aren't part of the Run anymore, like
+/// removed attempts, are deleted. Either the whole Run is exported or nothing
+/// is changed at all.
+pub fn export(connection: &mut Connection, run: &Run, runner: &str) -> Result<Report> {
+    create_schema(connection)?;
+    let transaction = connection.transaction()?;
+    let report = export_run(&transaction, run, runner)?;
+    transaction.commit()?;
+    Ok(report)
+}
+
+fn export_run(transaction: &Transaction, run: &Run, runner: &str) -> Result<Report> {
+    let metadata = run.metadata();
+    transaction.execute(
+        "INSERT OR IGNORE INTO runs
+         (runner, game, category, attempt_count, timer_offset, speedrun_com_id, platform,
+          region, uses_emulator)
+         VALUES (?1, ?2, ?3, 0, 0, '', '', '', 0)",
+        &[&runner, &run.game_name(), &run.category_name()],
+    )?;
+    let run_id: i64 = transaction.query_row(
+        "SELECT id FROM runs WHERE runner = ?1 AND game = ?2 AND category = ?3",
+        &[&runner, &run.game_name(), &run.category_name()],
+        |row| row.get(0),
+    )?;
+    transaction.execute(
+        "UPDATE runs
+         SET attempt_count = ?2, timer_offset = ?3, speedrun_com_id = ?4, platform = ?5,
+             region = ?6, uses_emulator = ?7
+         WHERE id = ?1",
+        &[
+            &run_id,
+            &run.attempt_count(),
+            &run.offset().total_seconds(),
+            &metadata.run_id(),
+            &metadata.platform_name(),
+            &metadata.region_name(),
+            &metadata.uses_emulator(),
+        ],
+    )?;
+
+    let stored_attempts = {
+        let mut statement =
+            transaction.prepare("SELECT attempt_index FROM attempts WHERE run_id = ?1")?;
+        let indices = statement
+            .query_map(&[&run_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<i32>>>()?;
+        indices
+    };
+    let attempts = run.attempt_history()
+        .iter()
+        .map(|a| a.index())
+        .collect::<HashSet<_>>();
+    for &attempt_index in stored_attempts.difference(&attempts) {
+        transaction.execute(
+            "DELETE FROM attempts WHERE run_id = ?1 AND attempt_index = ?2",
+            &[&run_id, &attempt_index],
+        )?;
+    }
+
+    // Replacing the Segment Histories entirely gets rid of removed segment
+    // times, as well as the segment times of segments that got moved.
+    for table in &["variables", "segments", "comparisons", "segment_times"] {
+        transaction.execute(
+            &format!("DELETE FROM {} WHERE run_id = ?1", table),
+            &[&run_id],
+        )?;
+    }
+
+    for (name, value) in metadata.variables() {
+        transaction.execute(
+            "INSERT INTO variables (run_id, name, value) VALUES (?1, ?2, ?3)",
+            &[&run_id, name, value],
+        )?;
+    }
+
+    for (index, segment) in run.segments().iter().enumerate() {
+        let index = index as i64;
+        let split_time = segment.personal_best_split_time();
+        let best_segment_time = segment.best_segment_time();
+        transaction.execute(
+            "INSERT INTO segments
+             (run_id, segment_index, name, split_real_time, split_game_time,
+              best_segment_real_time, best_segment_game_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            &[
+                &run_id,
+                &index,
+                &segment.name(),
+                &seconds(split_time[TimingMethod::RealTime]),
+                &seconds(split_time[TimingMethod::GameTime]),
+                &seconds(best_segment_time[TimingMethod::RealTime]),
+                &seconds(best_segment_time[TimingMethod::GameTime]),
+            ],
+        )?;
+
+        for comparison in run.custom_comparisons() {
+            if comparison == personal_best::NAME {
+                continue;
+            }
+            let time = segment.comparison(comparison);
+            transaction.execute(
+                "INSERT INTO comparisons
+                 (run_id, name, segment_index, split_real_time, split_game_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                &[
+                    &run_id,
+                    comparison,
+                    &index,
+                    &seconds(time[TimingMethod::RealTime]),
+                    &seconds(time[TimingMethod::GameTime]),
+                ],
+            )?;
+        }
+
+        for &(attempt_index, time) in segment.segment_history().iter() {
+            transaction.execute(
+                "INSERT INTO segment_times
+                 (run_id, attempt_index, segment_index, real_time, game_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                &[
+                    &run_id,
+                    &attempt_index,
+                    &index,
+                    &seconds(time[TimingMethod::RealTime]),
+                    &seconds(time[TimingMethod::GameTime]),
+                ],
+            )?;
+        }
+    }
+
+    let mut new_attempts = 0;
+    for attempt in run.attempt_history() {
+        let time = attempt.time();
+        if !stored_attempts.contains(&attempt.index()) {
+            new_attempts += 1;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO attempts
+             (run_id, attempt_index, started, ended, real_time, game_time, pause_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            &[
+                &run_id,
+                &attempt.index(),
+                &date(attempt.started()),
+                &date(attempt.ended()),
+                &seconds(time[TimingMethod::RealTime]),
+                &seconds(time[TimingMethod::GameTime]),
+                &seconds(attempt.pause_time()),
+            ],
+        )?;
+    }
+
+    Ok(Report {
+        run_id: run_id,
+        new_attempts: new_attempts,
+    })
+}
+
+fn seconds(time: Option<TimeSpan>) -> Option<f64> {
+    time.map(|t| t.total_seconds())
+}
+
+fn date(date: Option<AtomicDateTime>) -> Option<String> {
+    date.map(|d| d.time.to_rfc3339())
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use Image;
+
+/// An Icon Store keeps each distinct icon of a Run exactly once. Icons are
+/// identified by their data, so icons with the same data that were loaded
+/// separately all end up referencing the same stored icon. The game icon and
+/// the segment icons of a Run are handles to the icons in its store. They
+/// share the icon's data and ID, so each distinct icon is only kept in memory
+/// once and only needs to be sent to the user interface once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IconStore {
+    icons: HashMap<u64, Vec<Image>>,
+}
+
+/// The maximum width and height of icons in pixels. Larger icons set through
+/// the Run Editor are downscaled to this size, so huge icons don't need to be
+/// kept around. Icons of parsed splits files are kept as they are, unless
+/// `Run::downscale_icons` is called.
+pub const MAX_ICON_SIZE: u32 = 256;
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl IconStore {
+    /// Creates a new empty Icon Store.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Looks up the icon with the same data as the icon provided, storing the
+    /// icon if there is none yet. The handle to the stored icon is returned.
+    /// The icon's data is stored as it is. Empty icons are not stored.
+    pub fn intern(&mut self, icon: &Image) -> Image {
+        if icon.is_empty() {
+            return icon.clone();
+        }
+        let icons = self.icons.entry(hash(icon.data())).or_insert_with(Vec::new);
+        if let Some(stored) = icons.iter().find(|i| *i == icon) {
+            return stored.clone();
+        }
+        icons.push(icon.clone());
+        icon.clone()
+    }
+
+    /// Accesses the stored icon with the ID provided.
+    pub fn get(&self, id: usize) -> Option<&Image> {
+        self.icons.values().flat_map(|i| i).find(|i| i.id() == id)
+    }
+
+    /// Returns the amount of distinct icons stored.
+    pub fn len(&self) -> usize {
+        self.icons.values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if there are no icons stored.
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+
+    /// Removes all the stored icons.
+    pub fn clear(&mut self) {
+        self.icons.clear();
+    }
+}
@@ -0,0 +1,445 @@
+//! Keeps an append-only journal of the attempts committed to a Run next to its
+//! splits file. Appending a single line to the journal is a lot cheaper than
+//! rewriting the whole splits file after every attempt, and a crash in the
+//! middle of appending can at most lose the line that was being written. When
+//! loading the splits file, the journal is applied on top of it. Compacting the
+//! journal folds it into the splits file and removes it.
+//!
+//! Each line of the journal is a JSON object describing everything that
+//! changed in the Run when the attempt got committed: the attempt itself, its
+//! split times, the changes to the Segment Histories, the Best Segments and
+//! the Personal Best.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use livesplit_core::run::journal;
+//! use livesplit_core::Timer;
+//!
+//! let path = "path/to/splits_file.lss";
+//! let run = journal::load(path).expect("No usable splits file").run;
+//! let mut timer = Timer::new(run).expect("The splits file has no segments");
+//!
+//! timer.start();
+//! timer.split();
+//!
+//! // Reset the attempt and record it in the journal.
+//! if let Some(entry) = journal::reset(&mut timer) {
+//!     journal::append(&entry, path).expect("Couldn't append to the journal");
+//! }
+//!
+//! // Fold the journal back into the splits file, keeping 5 backups.
+//! journal::compact(path, 5).expect("Couldn't compact the journal");
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::cmp::min;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use chrono::{DateTime, Duration, Utc};
+use serde_json;
+use {AtomicDateTime, Attempt, Run, Time, TimeSpan, Timer};
+use super::saver::file::{self, LoadedRun};
+
+quick_error! {
+    /// The Error type for journals that couldn't be written, read or applied.
+    #[derive(Debug)]
+    pub enum Error {
+        /// Failed to access the journal.
+        Io(err: io::Error) {
+            from()
+        }
+        /// Failed to encode or decode an entry of the journal.
+        Json(err: serde_json::Error) {
+            from()
+        }
+        /// Failed to save or load the splits file the journal belongs to.
+        File(err: file::Error) {
+            from()
+        }
+        /// The entry was recorded for a Run with a different amount of
+        /// segments.
+        SegmentCountMismatch {}
+        /// A line of the journal, other than an incomplete last line, couldn't
+        /// be decoded. The line number starts at 1.
+        CorruptEntry(line: usize, err: serde_json::Error) {}
+    }
+}
+
+/// The Result type for working with journals.
+pub type Result<T> = StdResult<T, Error>;
+
+/// An entry of the journal, describing the changes a single attempt caused
+/// when it got committed to the Run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    attempt_count: u32,
+    attempt: AttemptRecord,
+    personal_best: bool,
+    segments: Vec<SegmentRecord>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct AttemptRecord {
+    index: i32,
+    time: TimeRecord,
+    started: Option<DateRecord>,
+    ended: Option<DateRecord>,
+    pause_time: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct DateRecord {
+    time: DateTime<Utc>,
+    synced: bool,
+}
+
+/// Times are stored as nanoseconds, so they are restored exactly.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct TimeRecord {
+    real_time: Option<i64>,
+    game_time: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct SegmentRecord {
+    split_time: TimeRecord,
+    /// The history entries that changed. `None` marks a removed entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<(i32, Option<TimeRecord>)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    best_segment_time: Option<TimeRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    personal_best_split_time: Option<TimeRecord>,
+}
+
+fn to_nanos(time: Option<TimeSpan>) -> Option<i64> {
+    time.and_then(|t| t.to_duration().num_nanoseconds())
+}
+
+fn from_nanos(nanos: Option<i64>) -> Option<TimeSpan> {
+    nanos.map(|n| Duration::nanoseconds(n).into())
+}
+
+impl From<Time> for TimeRecord {
+    fn from(time: Time) -> Self {
+        TimeRecord {
+            real_time: to_nanos(time.real_time),
+            game_time: to_nanos(time.game_time),
+        }
+    }
+}
+
+impl From<TimeRecord> for Time {
+    fn from(record: TimeRecord) -> Self {
+        Time::new()
+            .with_real_time(from_nanos(record.real_time))
+            .with_game_time(from_nanos(record.game_time))
+    }
+}
+
+impl From<AtomicDateTime> for DateRecord {
+    fn from(date: AtomicDateTime) -> Self {
+        DateRecord {
+            time: date.time,
+            synced: date.synced_with_atomic_clock,
+        }
+    }
+}
+
+impl<'a> From<&'a DateRecord> for AtomicDateTime {
+    fn from(record: &'a DateRecord) -> Self {
+        AtomicDateTime::new(record.time, record.synced)
+    }
+}
+
+impl Entry {
+    /// Describes the changes between a Run right before an attempt got
+    /// committed and the same Run right afterwards. The Run before committing
+    /// still needs to contain the split times of the attempt. If no attempt got
+    /// committed in between, `None` is returned.
+    pub fn between(before: &Run, after: &Run) -> Option<Entry> {
+        if before.len() != after.len() {
+            return None;
+        }
+
+        let attempt = after.attempt_history().iter().rev().find(|a| {
+            !before
+                .attempt_history()
+                .iter()
+                .any(|b| b.index() == a.index())
+        })?;
+
+        let segments = before
+            .segments()
+            .iter()
+            .zip(after.segments())
+            .map(|(old, new)| {
+                let mut history = Vec::new();
+                for &(index, time) in new.segment_history() {
+                    if old.segment_history().get(index) != Some(time) {
+                        history.push((index, Some(time.into())));
+                    }
+                }
+                for &(index, _) in old.segment_history() {
+                    if new.segment_history().get(index).is_none() {
+                        history.push((index, None));
+                    }
+                }
+
+                let changed = |old: Time, new: Time| if old != new { Some(new.into()) } else { None };
+
+                SegmentRecord {
+                    split_time: old.split_time().into(),
+                    history,
+                    best_segment_time: changed(old.best_segment_time(), new.best_segment_time()),
+                    personal_best_split_time: changed(
+                        old.personal_best_split_time(),
+                        new.personal_best_split_time(),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let personal_best = segments
+            .iter()
+            .any(|s| s.personal_best_split_time.is_some());
+
+        Some(Entry {
+            attempt_count: after.attempt_count(),
+            attempt: AttemptRecord {
+                index: attempt.index(),
+                time: attempt.time().into(),
+                started: attempt.started().map(Into::into),
+                ended: attempt.ended().map(Into::into),
+                pause_time: to_nanos(attempt.pause_time()),
+            },
+            personal_best,
+            segments,
+        })
+    }
+
+    /// Accesses the index of the attempt this entry describes.
+    pub fn attempt_index(&self) -> i32 {
+        self.attempt.index
+    }
+
+    /// Returns whether the attempt this entry describes is a new Personal
+    /// Best.
+    pub fn is_personal_best(&self) -> bool {
+        self.personal_best
+    }
+
+    /// Returns the split times the attempt reached for each segment.
+    pub fn split_times(&self) -> Vec<Time> {
+        self.segments.iter().map(|s| s.split_time.into()).collect()
+    }
+
+    /// Applies the entry to the Run provided. Entries of attempts that are
+    /// already part of the Attempt History are skipped, so applying a journal
+    /// to a splits file it already got folded into doesn't change anything.
+    /// Returns whether the entry got applied. The comparisons of the Run are
+    /// not regenerated.
+    pub fn apply(&self, run: &mut Run) -> Result<bool> {
+        let index = self.attempt.index;
+        if run.attempt_history().iter().any(|a| a.index() == index) {
+            return Ok(false);
+        }
+        if run.len() != self.segments.len() {
+            return Err(Error::SegmentCountMismatch);
+        }
+
+        let attempt = &self.attempt;
+        run.attempt_history_mut().push(Attempt::new(
+            index,
+            attempt.time.into(),
+            attempt.started.as_ref().map(Into::into),
+            attempt.ended.as_ref().map(Into::into),
+            from_nanos(attempt.pause_time),
+        ));
+        run.set_attempt_count(self.attempt_count);
+
+        for (segment, record) in run.segments_mut().iter_mut().zip(&self.segments) {
+            {
+                let history = segment.segment_history_mut();
+                for &(index, time) in &record.history {
+                    history.remove(index);
+                    if let Some(time) = time {
+                        history.insert(index, time.into());
+                    }
+                }
+            }
+            if let Some(time) = record.best_segment_time {
+                segment.set_best_segment_time(time.into());
+            }
+            if let Some(time) = record.personal_best_split_time {
+                segment.set_personal_best_split_time(time.into());
+            }
+        }
+
+        if self.personal_best {
+            run.clear_run_id();
+        }
+        run.mark_as_changed();
+
+        Ok(true)
+    }
+}
+
+/// Resets the current attempt of the Timer provided, updating its splits, and
+/// returns the journal entry describing the changes that caused. If there was
+/// no attempt in progress, `None` is returned.
+pub fn reset(timer: &mut Timer) -> Option<Entry> {
+    let before = timer.run().clone();
+    timer.reset(true);
+    Entry::between(&before, timer.run())
+}
+
+/// Writes a single entry as a line of a journal.
+pub fn write_entry<W: Write>(entry: &Entry, mut writer: W) -> Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    Ok(())
+}
+
+/// Reads all the entries of a journal. An incomplete last line is the result
+/// of a crash while appending to the journal and is ignored. Any other line
+/// that can't be decoded means the journal is corrupted and is reported as an
+/// error, so that the journal doesn't get folded into the splits file without
+/// it.
+pub fn read_entries<R: BufRead>(mut source: R) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        if source.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if !line.ends_with('\n') => break,
+            Err(e) => return Err(Error::CorruptEntry(line_number, e)),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Applies all the entries of a journal to the Run provided and regenerates
+/// its comparisons afterwards. Returns the amount of entries that got
+/// applied.
+pub fn apply<R: BufRead>(run: &mut Run, source: R) -> Result<usize> {
+    let mut applied = 0;
+    for entry in read_entries(source)? {
+        if entry.apply(run)? {
+            applied += 1;
+        }
+    }
+    if applied > 0 {
+        run.regenerate_comparisons();
+    }
+    Ok(applied)
+}
+
+/// Returns the path of the journal belonging to the splits file at the path
+/// provided.
+pub fn path<P: AsRef<Path>>(splits_path: P) -> PathBuf {
+    let mut path = splits_path.as_ref().as_os_str().to_owned();
+    path.push(".journal");
+    path.into()
+}
+
+/// Removes the incomplete last line a crash while appending left behind, so
+/// that the next entry starts on a line of its own. The file is positioned at
+/// its end afterwards.
+fn truncate_torn_line(file: &mut File) -> io::Result<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut buf = [0; 4096];
+    let mut end = len;
+
+    while end > 0 {
+        let chunk_len = min(end, buf.len() as u64) as usize;
+        let start = end - chunk_len as u64;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut buf[..chunk_len])?;
+        if let Some(index) = buf[..chunk_len].iter().rposition(|&b| b == b'\n') {
+            end = start + index as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+
+    if end != len {
+        file.set_len(end)?;
+    }
+    file.seek(SeekFrom::End(0))?;
+    Ok(())
+}
+
+/// Appends an entry to the journal of the splits file at the path provided.
+/// The journal is created if it doesn't exist yet. An incomplete last line,
+/// left behind by a crash while appending, is removed first. The entry is
+/// synchronized to the disk before this returns.
+pub fn append<P: AsRef<Path>>(entry: &Entry, splits_path: P) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(path(splits_path))?;
+    truncate_torn_line(&mut file)?;
+    write_entry(entry, &file)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Loads the splits file at the path provided, the same way `file::load` does,
+/// and applies its journal on top of it.
+pub fn load<P: AsRef<Path>>(splits_path: P) -> Result<LoadedRun> {
+    let splits_path = splits_path.as_ref();
+    let mut loaded = file::load(splits_path)?;
+
+    match File::open(path(splits_path)) {
+        Ok(journal) => {
+            apply(&mut loaded.run, BufReader::new(journal))?;
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(loaded)
+}
+
+/// Saves the Run as the splits file at the path provided, the same way
+/// `file::save_run` does, and removes the journal afterwards, as everything it
+/// recorded is part of the Run.
+pub fn save_run<P: AsRef<Path>>(run: &Run, splits_path: P, backup_count: usize) -> Result<()> {
+    let splits_path = splits_path.as_ref();
+    file::save_run(run, splits_path, backup_count)?;
+
+    match fs::remove_file(path(splits_path)) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Folds the journal of the splits file at the path provided into the splits
+/// file itself. If the process dies while compacting, the journal is still
+/// around and gets applied again the next time, which doesn't duplicate any
+/// of its entries. A corrupted journal is neither folded in nor removed.
+pub fn compact<P: AsRef<Path>>(splits_path: P, backup_count: usize) -> Result<()> {
+    let splits_path = splits_path.as_ref();
+    if !path(splits_path).exists() {
+        return Ok(());
+    }
+    let loaded = load(splits_path)?;
+    save_run(&loaded.run, splits_path, backup_count)
+}
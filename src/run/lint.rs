@@ -0,0 +1,493 @@
+use std::collections::HashSet;
+use std::fmt;
+use {Run, TimeSpan, TimingMethod};
+use time::formatter::{Short, TimeFormatter};
+
+/// The maximum difference in milliseconds between an attempt's time and the
+/// sum of its segment times that is still considered consistent. The times
+/// are stored with a limited precision, so small differences are expected.
+const TOLERANCE: f64 = 1.0;
+
+/// Describes an inconsistency found in a Run. Segments are referred to by
+/// their index and attempts by their attempt index. You can use the Display
+/// implementation to print out the details of the issue.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// The Personal Best split time of a segment is lower than the one of a
+    /// previous segment.
+    DecreasingSplitTime {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The timing method of the split times.
+        method: TimingMethod,
+        /// The split time of the segment.
+        time: TimeSpan,
+        /// The split time of the previous segment that has one.
+        previous_time: TimeSpan,
+    },
+    /// The Best Segment Time of a segment is slower than the segment time of
+    /// the Personal Best, or there is none even though the Personal Best has a
+    /// segment time.
+    SlowBestSegment {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The timing method of the segment times.
+        method: TimingMethod,
+        /// The Best Segment Time of the segment.
+        best_segment_time: Option<TimeSpan>,
+        /// The segment time of the Personal Best.
+        personal_best_segment_time: TimeSpan,
+    },
+    /// The Segment History of a segment contains a time for an attempt that
+    /// is not in the Attempt History.
+    UnknownAttempt {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The attempt index used by the Segment History.
+        attempt_index: i32,
+    },
+    /// The final time of an attempt doesn't match the sum of its segment times
+    /// stored in the Segment Histories.
+    AttemptTimeMismatch {
+        /// The index of the attempt.
+        attempt_index: i32,
+        /// The timing method of the times.
+        method: TimingMethod,
+        /// The final time stored for the attempt.
+        attempt_time: Option<TimeSpan>,
+        /// The sum of the attempt's segment times.
+        history_time: TimeSpan,
+    },
+    /// Only some of the segments have a Personal Best split time for Game
+    /// Time.
+    PartialGameTime {
+        /// The indices of the segments without a Game Time.
+        missing: Vec<usize>,
+    },
+    /// A custom comparison has the same name as another comparison.
+    DuplicateComparison(String),
+}
+
+/// Describes a fix for an issue found in a Run. A fix can be applied to the
+/// Run with `Run::apply_fix`. Applying a fix more than once has no further
+/// effect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Sets the Personal Best split time of a segment.
+    SetSplitTime {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The timing method to set the split time for.
+        method: TimingMethod,
+        /// The new split time.
+        time: TimeSpan,
+    },
+    /// Sets the Best Segment Time of a segment.
+    SetBestSegmentTime {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The timing method to set the segment time for.
+        method: TimingMethod,
+        /// The new Best Segment Time.
+        time: TimeSpan,
+    },
+    /// Removes the time of an attempt from the Segment History of a segment.
+    RemoveSegmentHistoryElement {
+        /// The index of the segment.
+        segment_index: usize,
+        /// The index of the attempt.
+        attempt_index: i32,
+    },
+    /// Sets the final time of an attempt.
+    SetAttemptTime {
+        /// The index of the attempt.
+        attempt_index: i32,
+        /// The timing method to set the final time for.
+        method: TimingMethod,
+        /// The new final time.
+        time: TimeSpan,
+    },
+    /// Renames all the custom comparisons with this name, except for the first
+    /// one, by appending a number to the name. If a Comparison Generator uses
+    /// the name, all of them are renamed. Their comparison times are kept.
+    RenameDuplicateComparison(String),
+}
+
+impl Issue {
+    /// Returns the fix that can be offered for this issue, if there is one.
+    pub fn fix(&self) -> Option<Fix> {
+        Some(match *self {
+            Issue::DecreasingSplitTime {
+                segment_index,
+                method,
+                previous_time,
+                ..
+            } => Fix::SetSplitTime {
+                segment_index,
+                method,
+                time: previous_time,
+            },
+            Issue::SlowBestSegment {
+                segment_index,
+                method,
+                personal_best_segment_time,
+                ..
+            } => Fix::SetBestSegmentTime {
+                segment_index,
+                method,
+                time: personal_best_segment_time,
+            },
+            Issue::UnknownAttempt {
+                segment_index,
+                attempt_index,
+            } => Fix::RemoveSegmentHistoryElement {
+                segment_index,
+                attempt_index,
+            },
+            Issue::AttemptTimeMismatch {
+                attempt_index,
+                method,
+                history_time,
+                ..
+            } => Fix::SetAttemptTime {
+                attempt_index,
+                method,
+                time: history_time,
+            },
+            Issue::PartialGameTime { .. } => return None,
+            Issue::DuplicateComparison(ref name) => Fix::RenameDuplicateComparison(name.clone()),
+        })
+    }
+}
+
+fn method_name(method: TimingMethod) -> &'static str {
+    match method {
+        TimingMethod::RealTime => "Real Time",
+        TimingMethod::GameTime => "Game Time",
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let short = Short::new();
+        match *self {
+            Issue::DecreasingSplitTime {
+                segment_index,
+                method,
+                time,
+                previous_time,
+            } => write!(
+                f,
+                "The {} split time of {} of segment {} is lower than the split time of {} \
+                 before it.",
+                method_name(method),
+                short.format(time),
+                segment_index + 1,
+                short.format(previous_time)
+            ),
+            Issue::SlowBestSegment {
+                segment_index,
+                method,
+                best_segment_time,
+                personal_best_segment_time,
+            } => write!(
+                f,
+                "The {} Best Segment Time of {} of segment {} is slower than the Personal \
+                 Best segment time of {}.",
+                method_name(method),
+                short.format(best_segment_time),
+                segment_index + 1,
+                short.format(personal_best_segment_time)
+            ),
+            Issue::UnknownAttempt {
+                segment_index,
+                attempt_index,
+            } => write!(
+                f,
+                "The Segment History of segment {} contains a time of attempt {}, which is \
+                 not in the Attempt History.",
+                segment_index + 1,
+                attempt_index
+            ),
+            Issue::AttemptTimeMismatch {
+                attempt_index,
+                method,
+                attempt_time,
+                history_time,
+            } => write!(
+                f,
+                "The {} of {} of attempt {} doesn't match the sum of its segment times of {}.",
+                method_name(method),
+                short.format(attempt_time),
+                attempt_index,
+                short.format(history_time)
+            ),
+            Issue::PartialGameTime { ref missing } => write!(
+                f,
+                "{} segments don't have a Game Time, even though the other segments have one.",
+                missing.len()
+            ),
+            Issue::DuplicateComparison(ref name) => {
+                write!(f, "There is more than one comparison named {}.", name)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let short = Short::new();
+        match *self {
+            Fix::SetSplitTime {
+                segment_index,
+                method,
+                time,
+            } => write!(
+                f,
+                "Set the {} split time of segment {} to {}.",
+                method_name(method),
+                segment_index + 1,
+                short.format(time)
+            ),
+            Fix::SetBestSegmentTime {
+                segment_index,
+                method,
+                time,
+            } => write!(
+                f,
+                "Set the {} Best Segment Time of segment {} to {}.",
+                method_name(method),
+                segment_index + 1,
+                short.format(time)
+            ),
+            Fix::RemoveSegmentHistoryElement {
+                segment_index,
+                attempt_index,
+            } => write!(
+                f,
+                "Remove the time of attempt {} from the Segment History of segment {}.",
+                attempt_index,
+                segment_index + 1
+            ),
+            Fix::SetAttemptTime {
+                attempt_index,
+                method,
+                time,
+            } => write!(
+                f,
+                "Set the {} of attempt {} to {}.",
+                method_name(method),
+                attempt_index,
+                short.format(time)
+            ),
+            Fix::RenameDuplicateComparison(ref name) => {
+                write!(f, "Rename the duplicate comparisons named {}.", name)
+            }
+        }
+    }
+}
+
+impl Run {
+    /// Checks the Run for inconsistencies and returns all the issues found.
+    /// Unlike `fix_splits`, this doesn't modify the Run. Instead each issue
+    /// may provide a fix that can be applied with `apply_fix`. Fixes describe
+    /// the Run at the time it got linted, so the Run should be linted again
+    /// after applying one.
+    pub fn lint(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for &method in &TimingMethod::all() {
+            self.lint_split_times(method, &mut issues);
+        }
+        self.lint_segment_histories(&mut issues);
+        for &method in &TimingMethod::all() {
+            self.lint_attempt_times(method, &mut issues);
+        }
+        self.lint_game_time(&mut issues);
+        self.lint_comparisons(&mut issues);
+
+        issues
+    }
+
+    fn lint_split_times(&self, method: TimingMethod, issues: &mut Vec<Issue>) {
+        let mut previous_time = TimeSpan::zero();
+        for (segment_index, segment) in self.segments().iter().enumerate() {
+            if let Some(time) = segment.personal_best_split_time()[method] {
+                if time < previous_time {
+                    issues.push(Issue::DecreasingSplitTime {
+                        segment_index,
+                        method,
+                        time,
+                        previous_time,
+                    });
+                    continue;
+                }
+
+                let personal_best_segment_time = time - previous_time;
+                let best_segment_time = segment.best_segment_time()[method];
+                if best_segment_time.map_or(true, |t| t > personal_best_segment_time) {
+                    issues.push(Issue::SlowBestSegment {
+                        segment_index,
+                        method,
+                        best_segment_time,
+                        personal_best_segment_time,
+                    });
+                }
+
+                previous_time = time;
+            }
+        }
+    }
+
+    fn lint_segment_histories(&self, issues: &mut Vec<Issue>) {
+        let attempts = self.attempt_history()
+            .iter()
+            .map(|a| a.index())
+            .collect::<HashSet<_>>();
+        for (segment_index, segment) in self.segments().iter().enumerate() {
+            for &(attempt_index, _) in segment.segment_history().iter_actual_runs() {
+                if !attempts.contains(&attempt_index) {
+                    issues.push(Issue::UnknownAttempt {
+                        segment_index,
+                        attempt_index,
+                    });
+                }
+            }
+        }
+    }
+
+    fn lint_attempt_times(&self, method: TimingMethod, issues: &mut Vec<Issue>) {
+        for attempt in self.attempt_history() {
+            let attempt_index = attempt.index();
+
+            // Only attempts with a complete Segment History that reached the
+            // end of the Run can be checked. Skipped segments are included in
+            // the segment time of the segment after them.
+            let history_time = catch! {
+                let mut sum = TimeSpan::zero();
+                for segment in self.segments() {
+                    if let Some(time) = segment.segment_history().get(attempt_index)?[method] {
+                        sum += time;
+                    }
+                }
+                self.segments().last()?.segment_history().get(attempt_index)?[method]?;
+                sum
+            };
+
+            if let Some(history_time) = history_time {
+                let attempt_time = attempt.time()[method];
+                let matches = attempt_time.map_or(false, |t| {
+                    (t - history_time).total_milliseconds().abs() <= TOLERANCE
+                });
+                if !matches {
+                    issues.push(Issue::AttemptTimeMismatch {
+                        attempt_index,
+                        method,
+                        attempt_time,
+                        history_time,
+                    });
+                }
+            }
+        }
+    }
+
+    fn lint_game_time(&self, issues: &mut Vec<Issue>) {
+        let missing = self.segments()
+            .iter()
+            .enumerate()
+            .filter(|&(_, s)| s.personal_best_split_time().game_time.is_none())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() && missing.len() < self.len() {
+            issues.push(Issue::PartialGameTime { missing });
+        }
+    }
+
+    fn lint_comparisons(&self, issues: &mut Vec<Issue>) {
+        let custom_comparisons = self.custom_comparisons();
+        for (i, comparison) in custom_comparisons.iter().enumerate() {
+            let is_duplicate = custom_comparisons[..i].contains(comparison);
+            let is_reported = issues.iter().any(|issue| match *issue {
+                Issue::DuplicateComparison(ref name) => name == comparison,
+                _ => false,
+            });
+            let is_generated = self.comparison_generators()
+                .iter()
+                .any(|g| g.name() == comparison);
+            if (is_duplicate || is_generated) && !is_reported {
+                issues.push(Issue::DuplicateComparison(comparison.clone()));
+            }
+        }
+    }
+
+    /// Applies a fix provided by an issue found by `lint`. Fixes referring to
+    /// segments or attempts that don't exist anymore are ignored.
+    pub fn apply_fix(&mut self, fix: &Fix) {
+        match *fix {
+            Fix::SetSplitTime {
+                segment_index,
+                method,
+                time,
+            } => if let Some(segment) = self.segments_mut().get_mut(segment_index) {
+                let mut split_time = segment.personal_best_split_time();
+                split_time[method] = Some(time);
+                segment.set_personal_best_split_time(split_time);
+            },
+            Fix::SetBestSegmentTime {
+                segment_index,
+                method,
+                time,
+            } => if let Some(segment) = self.segments_mut().get_mut(segment_index) {
+                segment.best_segment_time_mut()[method] = Some(time);
+            },
+            Fix::RemoveSegmentHistoryElement {
+                segment_index,
+                attempt_index,
+            } => if let Some(segment) = self.segments_mut().get_mut(segment_index) {
+                segment.segment_history_mut().remove(attempt_index);
+            },
+            Fix::SetAttemptTime {
+                attempt_index,
+                method,
+                time,
+            } => if let Some(attempt) = self.attempt_history_mut()
+                .iter_mut()
+                .find(|a| a.index() == attempt_index)
+            {
+                let mut attempt_time = attempt.time();
+                attempt_time[method] = Some(time);
+                attempt.set_time(attempt_time);
+            },
+            Fix::RenameDuplicateComparison(ref name) => {
+                let is_generated = self.comparison_generators()
+                    .iter()
+                    .any(|g| g.name() == name);
+                let mut is_first = !is_generated;
+                let mut new_names = Vec::new();
+                for index in 0..self.custom_comparisons().len() {
+                    if self.custom_comparisons()[index] != *name {
+                        continue;
+                    }
+                    if is_first {
+                        is_first = false;
+                        continue;
+                    }
+                    let new_name = (2..)
+                        .map(|number| format!("{} ({})", name, number))
+                        .find(|n| self.validate_comparison_name(n).is_ok())
+                        .unwrap();
+                    self.custom_comparisons_mut()[index] = new_name.clone();
+                    new_names.push(new_name);
+                }
+                for segment in self.segments_mut() {
+                    let time = segment.comparison(name);
+                    for new_name in &new_names {
+                        *segment.comparison_mut(new_name) = time;
+                    }
+                }
+            }
+        }
+        self.mark_as_changed();
+    }
+}
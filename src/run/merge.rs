@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use {Attempt, Run, SegmentHistory, TimeSpan, TimingMethod};
+
+/// Describes a problem encountered while merging two Runs. None of them
+/// prevent the merge, but some information of the other Run may not have been
+/// merged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    /// The other Run contains a segment with this name that doesn't exist in
+    /// the Run. Its history is not merged.
+    MissingSegment(String),
+    /// The Run contains a segment with this name that doesn't exist in the
+    /// other Run. The attempts of the other Run have no history for it.
+    ExtraSegment(String),
+    /// The segments that exist in both Runs are in a different order.
+    SegmentOrder,
+    /// The attempt of the other Run with this index started at the same time
+    /// as an attempt of the Run. It is considered to be the same attempt and
+    /// is not merged a second time.
+    DuplicateAttempt(i32),
+    /// The Personal Best of the other Run is faster, but the segments of the
+    /// Runs don't match, so it can't be taken over.
+    PersonalBest,
+}
+
+/// The report describing how two Runs got merged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// The amount of attempts of the other Run that got merged.
+    pub merged_attempts: usize,
+    /// Whether the Personal Best of the other Run got taken over.
+    pub new_personal_best: bool,
+    /// All the conflicts encountered while merging.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Finds the segment of the Run for each segment of the other Run by name.
+/// Segments with the same name are matched up in order.
+fn align_segments(run: &Run, other: &Run, report: &mut MergeReport) -> Vec<Option<usize>> {
+    let mut used = vec![false; run.len()];
+    let mapping = other
+        .segments()
+        .iter()
+        .map(|other_segment| {
+            let index = (0..run.len())
+                .find(|&i| !used[i] && run.segment(i).name() == other_segment.name());
+            match index {
+                Some(index) => used[index] = true,
+                None => report
+                    .conflicts
+                    .push(MergeConflict::MissingSegment(other_segment.name().to_owned())),
+            }
+            index
+        })
+        .collect::<Vec<_>>();
+
+    for (segment, _) in run.segments().iter().zip(&used).filter(|&(_, &u)| !u) {
+        report
+            .conflicts
+            .push(MergeConflict::ExtraSegment(segment.name().to_owned()));
+    }
+
+    let mut matched = mapping.iter().filter_map(|&i| i);
+    if let Some(mut previous) = matched.next() {
+        for index in matched {
+            if index < previous {
+                report.conflicts.push(MergeConflict::SegmentOrder);
+                break;
+            }
+            previous = index;
+        }
+    }
+
+    mapping
+}
+
+/// Determines the point in time each attempt is sorted by. Attempts without a
+/// start date are kept right after the attempt before them.
+fn sort_keys(attempts: &[Attempt]) -> Vec<Option<DateTime<Utc>>> {
+    let mut last = None;
+    attempts
+        .iter()
+        .map(|a| {
+            if let Some(started) = a.started() {
+                last = Some(started.time);
+            }
+            last
+        })
+        .collect()
+}
+
+fn final_time(run: &Run) -> Option<TimeSpan> {
+    let time = run.segments().last()?.personal_best_split_time();
+    time.real_time.or(time.game_time)
+}
+
+fn with_index(attempt: &Attempt, index: i32) -> Attempt {
+    let mut merged = Attempt::new(
+        index,
+        attempt.time(),
+        attempt.started(),
+        attempt.ended(),
+        attempt.pause_time(),
+    );
+    merged.set_invalid(attempt.is_invalid());
+    merged.set_note(attempt.note());
+    for tag in attempt.tags() {
+        merged.add_tag(tag.as_str());
+    }
+    merged
+        .unknown_elements_mut()
+        .extend_from_slice(attempt.unknown_elements());
+    merged
+}
+
+impl Run {
+    /// Merges the history of another Run for the same category into this Run.
+    /// This is useful if the same category has been run with different splits
+    /// files, for example on different machines. The segments are matched up
+    /// by their names. The attempts of both Runs are interleaved by the point
+    /// in time they started at and get new indices, and the Segment Histories
+    /// are merged accordingly. The Best Segments and the Personal Best are the
+    /// faster ones of both Runs. Everything that couldn't be merged is
+    /// described by the report returned.
+    pub fn merge(&mut self, other: &Run) -> MergeReport {
+        let mut report = MergeReport::default();
+        let mapping = align_segments(self, other, &mut report);
+
+        // Attempts of the other Run starting at the same time as an attempt of
+        // this Run are the same attempt.
+        let duplicates = other
+            .attempt_history()
+            .iter()
+            .filter(|a| {
+                a.started().map_or(false, |started| {
+                    self.attempt_history()
+                        .iter()
+                        .any(|b| b.started().map(|s| s.time) == Some(started.time))
+                })
+            })
+            .map(Attempt::index)
+            .collect::<Vec<_>>();
+        for &index in &duplicates {
+            report.conflicts.push(MergeConflict::DuplicateAttempt(index));
+        }
+
+        let own_attempts = self.attempt_history().to_vec();
+        let own_keys = sort_keys(&own_attempts);
+        let other_keys = sort_keys(other.attempt_history());
+
+        let mut attempts = own_attempts
+            .iter()
+            .zip(own_keys)
+            .map(|(a, k)| (k, false, a))
+            .chain(
+                other
+                    .attempt_history()
+                    .iter()
+                    .zip(other_keys)
+                    .filter(|&(a, _)| !duplicates.contains(&a.index()))
+                    .map(|(a, k)| (k, true, a)),
+            )
+            .collect::<Vec<_>>();
+        // The sort is stable, so the order within each Run is kept.
+        attempts.sort_by_key(|&(key, _, _)| key);
+
+        let mut own_indices = HashMap::new();
+        let mut other_indices = HashMap::new();
+        let mut merged_attempts = Vec::with_capacity(attempts.len());
+        for (new_index, &(_, is_other, attempt)) in attempts.iter().enumerate() {
+            let new_index = new_index as i32 + 1;
+            if is_other {
+                other_indices.insert(attempt.index(), new_index);
+                report.merged_attempts += 1;
+            } else {
+                own_indices.insert(attempt.index(), new_index);
+            }
+            merged_attempts.push(with_index(attempt, new_index));
+        }
+
+        // The segment times of this Run that aren't from actual attempts keep
+        // their indices. The ones of the other Run, as well as the ones
+        // belonging to attempts that aren't in the Attempt History anymore, get
+        // new indices below all the existing ones.
+        let mut next_index = self.segments()
+            .iter()
+            .chain(other.segments())
+            .filter_map(|s| s.segment_history().try_get_min_index())
+            .min()
+            .map_or(0, |m| if m < 1 { m - 1 } else { 0 });
+        for segment in self.segments() {
+            for &(index, _) in segment.segment_history() {
+                if index < 1 {
+                    own_indices.insert(index, index);
+                } else if !own_indices.contains_key(&index) {
+                    own_indices.insert(index, next_index);
+                    next_index -= 1;
+                }
+            }
+        }
+        for segment in other.segments() {
+            for &(index, _) in segment.segment_history() {
+                if !other_indices.contains_key(&index) && !duplicates.contains(&index) {
+                    other_indices.insert(index, next_index);
+                    next_index -= 1;
+                }
+            }
+        }
+
+        let mut histories = self.segments()
+            .iter()
+            .map(|s| {
+                let mut history = SegmentHistory::default();
+                for &(index, time) in s.segment_history() {
+                    history.insert(own_indices[&index], time);
+                }
+                history
+            })
+            .collect::<Vec<_>>();
+
+        for (other_segment, &segment_index) in other.segments().iter().zip(&mapping) {
+            if let Some(segment_index) = segment_index {
+                let history = &mut histories[segment_index];
+                for &(index, time) in other_segment.segment_history() {
+                    if let Some(&new_index) = other_indices.get(&index) {
+                        history.insert(new_index, time);
+                    }
+                }
+            }
+        }
+
+        // Take over the Personal Best if it's faster and the segments match.
+        let other_is_faster = match (final_time(self), final_time(other)) {
+            (Some(own_time), Some(other_time)) => other_time < own_time,
+            (None, Some(_)) => true,
+            _ => false,
+        };
+        if other_is_faster {
+            let segments_match = self.len() == other.len()
+                && mapping.iter().enumerate().all(|(i, &m)| m == Some(i));
+            if segments_match {
+                report.new_personal_best = true;
+            } else {
+                report.conflicts.push(MergeConflict::PersonalBest);
+            }
+        }
+
+        *self.attempt_history_mut() = merged_attempts;
+        let attempt_count = self.attempt_count().saturating_add(other.attempt_count());
+        self.set_attempt_count(attempt_count.saturating_sub(duplicates.len() as u32));
+
+        for (segment, history) in self.segments_mut().iter_mut().zip(histories) {
+            *segment.segment_history_mut() = history;
+        }
+
+        for (other_segment, &segment_index) in other.segments().iter().zip(&mapping) {
+            if let Some(segment_index) = segment_index {
+                let segment = self.segment_mut(segment_index);
+                let mut best_segment = segment.best_segment_time();
+                for &method in &TimingMethod::all() {
+                    if let Some(other_time) = other_segment.best_segment_time()[method] {
+                        if best_segment[method].map_or(true, |t| other_time < t) {
+                            best_segment[method] = Some(other_time);
+                        }
+                    }
+                }
+                segment.set_best_segment_time(best_segment);
+
+                if report.new_personal_best {
+                    segment.set_personal_best_split_time(other_segment.personal_best_split_time());
+                }
+            }
+        }
+
+        if report.new_personal_best {
+            self.clear_run_id();
+        }
+
+        if !self.is_empty() {
+            self.fix_splits();
+            self.regenerate_comparisons();
+        }
+        self.mark_as_changed();
+
+        report
+    }
+}
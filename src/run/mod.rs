@@ -16,11 +16,20 @@
 //! ```
 
 mod attempt;
+mod attempt_history;
+mod csv_util;
+mod icon_store;
+mod lint;
+mod merge;
 mod run_metadata;
 mod run;
 mod segment_history;
 mod segment;
+pub mod diff;
 pub mod editor;
+pub mod export;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod journal;
 pub mod parser;
 pub mod saver;
 
@@ -28,6 +37,9 @@ pub mod saver;
 mod tests;
 
 pub use self::attempt::Attempt;
+pub use self::icon_store::{IconStore, MAX_ICON_SIZE};
+pub use self::lint::{Fix, Issue};
+pub use self::merge::{MergeConflict, MergeReport};
 pub use self::run_metadata::RunMetadata;
 pub use self::run::{ComparisonError, ComparisonsIter, Run};
 pub use self::segment_history::SegmentHistory;
@@ -29,14 +29,31 @@
 //! // Get out the Run object.
 //! let run = parsed.run;
 //! ```
+//!
+//! Inspecting why a splits file couldn't be parsed.
+//!
+//! ```no_run
+//! use livesplit_core::run::parser::composite::{self, Error};
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! let file = BufReader::new(File::open("path/to/splits_file").expect("File not found"));
+//!
+//! if let Err(Error::NoParserParsedIt(rejections)) = composite::parse(file, None, false) {
+//!     for rejection in &rejections {
+//!         println!("{}", rejection);
+//!     }
+//! }
+//! ```
 
+use std::fmt::{self, Display};
 use std::path::PathBuf;
-use std::io::{self, BufRead, Seek, SeekFrom};
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
 use std::result::Result as StdResult;
 use Run;
 use super::{face_split, livesplit, llanfair, llanfair_gered, shit_split, source_live_timer,
-            splitterz, splitty, time_split_tracker, urn, wsplit, TimerKind, llanfair2,
-            portal2_live_timer};
+            splitterz, splitty, time_split_tracker, urn, wsplit, xml_util, LimitError,
+            ParseLimits, TimerKind, llanfair2, portal2_live_timer};
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the Composite
@@ -47,8 +64,18 @@ quick_error! {
         Seek(err: io::Error) {
             from()
         }
-        /// No parser was able to parse the splits file.
-        NoParserParsedIt {}
+        /// Failed to read the beginning of the splits file for detecting its
+        /// file format.
+        Read(err: io::Error) {}
+        /// No parser was able to parse the splits file. The reasons why each of
+        /// the parsers that considered the file plausible rejected it are
+        /// provided, ordered from the most to the least plausible parser.
+        NoParserParsedIt(rejections: Vec<Rejection>) {}
+        /// The splits file exceeds one of the limits configured for parsing.
+        /// No other parsers are tried in that case.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -64,93 +91,406 @@ pub struct ParsedRun {
     pub kind: TimerKind,
 }
 
+/// Describes how confident the format detection is that a splits file is
+/// stored in a certain file format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Nothing indicates that the file is stored in this format. The parser is
+    /// only tried as a last resort.
+    Unlikely,
+    /// The general structure of the file, like it being XML or JSON, fits the
+    /// format, but nothing more specific has been found.
+    Possible,
+    /// Elements or keys specific to this format have been found.
+    Likely,
+    /// The file starts with a signature that is unique to this format.
+    Certain,
+}
+
+/// A file format considered by the format detection, together with how
+/// confident the detection is that the splits file is stored in this format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    /// The timer whose file format is considered.
+    pub kind: TimerKind,
+    /// How confident the format detection is about this file format.
+    pub confidence: Confidence,
+}
+
+/// Describes why a parser that considered the splits file plausible rejected
+/// it.
+#[derive(Clone, Debug)]
+pub struct Rejection {
+    /// The timer whose parser rejected the splits file.
+    pub kind: TimerKind,
+    /// How confident the format detection was about this file format.
+    pub confidence: Confidence,
+    /// The error the parser reported.
+    pub reason: String,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?}): {}", self.kind, self.confidence, self.reason)
+    }
+}
+
+/// The amount of bytes at the beginning of a splits file that are inspected
+/// for detecting the file format.
+const DETECTION_LEN: u64 = 4096;
+
+/// The order the parsers are tried in if the format detection is equally
+/// confident about their file formats.
+const PARSE_ORDER: [TimerKind; 13] = [
+    TimerKind::LiveSplit,
+    TimerKind::WSplit,
+    TimerKind::SplitterZ,
+    TimerKind::ShitSplit,
+    TimerKind::Splitty,
+    TimerKind::TimeSplitTracker,
+    TimerKind::Portal2LiveTimer,
+    TimerKind::FaceSplit,
+    TimerKind::Llanfair,
+    // Should be parsed after LiveSplit's parser, as both use a Run element as
+    // the root element.
+    TimerKind::LlanfairGered,
+    // Llanfair 2's format is almost entirely optional so it should be parsed
+    // after all other XML based formats.
+    TimerKind::Llanfair2,
+    // SourceLiveTimer needs to be before Urn because of a false positive
+    // due to the nature of parsing json files.
+    TimerKind::SourceLiveTimer,
+    TimerKind::Urn,
+];
+
+const TEXT_FORMATS: [TimerKind; 6] = [
+    TimerKind::WSplit,
+    TimerKind::SplitterZ,
+    TimerKind::ShitSplit,
+    TimerKind::TimeSplitTracker,
+    TimerKind::Portal2LiveTimer,
+    TimerKind::FaceSplit,
+];
+
 fn parsed(run: Run, kind: TimerKind) -> ParsedRun {
     ParsedRun { run, kind }
 }
 
-/// Attempts to parse a splits file by invoking the corresponding parser for the
-/// file format detected. A path to the splits file can be provided, which helps
-/// saving the splits file again later. Additionally you need to specify if
-/// additional files, like external images are allowed to be loaded. If you are
-/// using livesplit-core in a server-like environment, set this to `false`. Only
-/// client-side applications should set this to `true`.
-pub fn parse<R>(mut source: R, path: Option<PathBuf>, load_files: bool) -> Result<ParsedRun>
-where
-    R: BufRead + Seek,
-{
-    let files_path =
-        if load_files { path.clone() } else { None };
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = livesplit::parse(&mut source, path) {
-        return Ok(parsed(run, TimerKind::LiveSplit));
-    }
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle).is_some()
+}
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = wsplit::parse(&mut source, load_files) {
-        return Ok(parsed(run, TimerKind::WSplit));
+fn trim_start(mut data: &[u8]) -> &[u8] {
+    if data.starts_with(b"\xEF\xBB\xBF") {
+        data = &data[3..];
     }
-
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = splitterz::parse(&mut source, load_files) {
-        return Ok(parsed(run, TimerKind::SplitterZ));
+    while let Some((&first, rest)) = data.split_first() {
+        if !(first as char).is_whitespace() {
+            break;
+        }
+        data = rest;
     }
+    data
+}
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = shit_split::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::ShitSplit));
+fn first_line(data: &[u8]) -> &[u8] {
+    let end = data.iter().position(|&b| b == b'\n').unwrap_or(data.len());
+    let line = &data[..end];
+    if line.ends_with(b"\r") {
+        &line[..end - 1]
+    } else {
+        line
     }
+}
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = splitty::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::Splitty));
+fn xml_root_name(mut data: &[u8]) -> Option<&[u8]> {
+    loop {
+        data = trim_start(data);
+        if !data.starts_with(b"<") {
+            return None;
+        }
+        if data.starts_with(b"<!--") {
+            data = &data[find(data, b"-->")? + 3..];
+        } else if data.starts_with(b"<?") || data.starts_with(b"<!") {
+            data = &data[data.iter().position(|&b| b == b'>')? + 1..];
+        } else {
+            let name = &data[1..];
+            let len = name.iter()
+                .position(|&b| b == b'>' || b == b'/' || (b as char).is_whitespace())
+                .unwrap_or(name.len());
+            return Some(&name[..len]);
+        }
     }
+}
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = time_split_tracker::parse(&mut source, files_path) {
-        return Ok(parsed(run, TimerKind::TimeSplitTracker));
-    }
+/// Inspects the beginning of a splits file and ranks all the file formats by
+/// how confident the format detection is that the splits file is stored in
+/// that format. Magic bytes, the name of the XML root element and the keys of
+/// JSON objects are taken into account. All file formats are returned, the
+/// most plausible ones first.
+pub fn detect(prefix: &[u8]) -> Vec<Candidate> {
+    let mut candidates = PARSE_ORDER
+        .iter()
+        .map(|&kind| Candidate {
+            kind,
+            confidence: Confidence::Unlikely,
+        })
+        .collect::<Vec<_>>();
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = portal2_live_timer::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::Portal2LiveTimer));
-    }
+    {
+        let mut raise = |kind: TimerKind, confidence: Confidence| {
+            for candidate in &mut candidates {
+                if candidate.kind == kind && candidate.confidence < confidence {
+                    candidate.confidence = confidence;
+                }
+            }
+        };
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = face_split::parse(&mut source, load_files) {
-        return Ok(parsed(run, TimerKind::FaceSplit));
+        let data = trim_start(prefix);
+
+        if data.starts_with(b"\xAC\xED") {
+            // Java Object Serialization Stream
+            raise(TimerKind::Llanfair, Confidence::Certain);
+        } else if let Some(root) = xml_root_name(data) {
+            if root == b"Run" {
+                if contains(data, b"serialization=\"custom\"") || contains(data, b"<default") {
+                    raise(TimerKind::LlanfairGered, Confidence::Likely);
+                    raise(TimerKind::LiveSplit, Confidence::Possible);
+                } else if contains(data, b"<GameIcon") || contains(data, b"<GameName") {
+                    raise(TimerKind::LiveSplit, Confidence::Certain);
+                    raise(TimerKind::LlanfairGered, Confidence::Possible);
+                } else {
+                    raise(TimerKind::LiveSplit, Confidence::Likely);
+                    raise(TimerKind::LlanfairGered, Confidence::Possible);
+                }
+            } else if root == b"run" {
+                raise(TimerKind::Llanfair2, Confidence::Likely);
+            }
+            raise(TimerKind::Llanfair2, Confidence::Possible);
+        } else if data.starts_with(b"{") {
+            raise(TimerKind::Splitty, Confidence::Possible);
+            raise(TimerKind::SourceLiveTimer, Confidence::Possible);
+            raise(TimerKind::Urn, Confidence::Possible);
+            if contains(data, b"\"Category\"") {
+                raise(TimerKind::SourceLiveTimer, Confidence::Likely);
+            }
+            if contains(data, b"\"run_name\"") || contains(data, b"\"timer_type\"") {
+                raise(TimerKind::Splitty, Confidence::Likely);
+            }
+            if contains(data, b"\"title\"") {
+                raise(TimerKind::Urn, Confidence::Likely);
+            }
+        } else {
+            for &kind in &TEXT_FORMATS {
+                raise(kind, Confidence::Possible);
+            }
+            let line = first_line(data);
+            if line.starts_with(b"Title=") {
+                raise(TimerKind::WSplit, Confidence::Likely);
+            } else if line.starts_with(b"#") && contains(line, b"|") {
+                raise(TimerKind::ShitSplit, Confidence::Likely);
+            } else if contains(line, b"\t") {
+                raise(TimerKind::TimeSplitTracker, Confidence::Likely);
+            } else if contains(data, b"sp_a1_intro1") {
+                raise(TimerKind::Portal2LiveTimer, Confidence::Likely);
+            } else if let Some(comma) = line.iter().rposition(|&b| b == b',') {
+                // SplitterZ stores the attempt count after the category name.
+                let attempts = &line[comma + 1..];
+                if !attempts.is_empty() && attempts.iter().all(|&b| b >= b'0' && b <= b'9') {
+                    raise(TimerKind::SplitterZ, Confidence::Likely);
+                }
+            }
+        }
     }
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = llanfair::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::Llanfair));
+    // The sort is stable, so equally plausible formats stay in the parse
+    // order.
+    candidates.sort_by(|a, b| b.confidence.cmp(&a.confidence));
+
+    candidates
+}
+
+/// Implemented by the Error types of the individual parsers, so that exceeding
+/// a limit can be told apart from the parser rejecting the file format.
+trait ParserError: Display {
+    fn limit(&self) -> Option<LimitError>;
+}
+
+macro_rules! parser_errors {
+    ($($module:ident),*) => {
+        $(impl ParserError for $module::Error {
+            fn limit(&self) -> Option<LimitError> {
+                match *self {
+                    $module::Error::Limit(err) => Some(err),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+parser_errors!(
+    face_split,
+    llanfair,
+    portal2_live_timer,
+    shit_split,
+    source_live_timer,
+    splitterz,
+    splitty,
+    time_split_tracker,
+    urn,
+    wsplit,
+    xml_util
+);
+
+impl ParserError for livesplit::Error {
+    fn limit(&self) -> Option<LimitError> {
+        match self.kind {
+            livesplit::ErrorKind::Limit(err) => Some(err),
+            _ => None,
+        }
     }
+}
 
-    // Should be parsed after LiveSplit's parser, as it also parses all
-    // LiveSplit files with the current implementation.
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = llanfair_gered::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::LlanfairGered));
+enum Failure {
+    Rejected(String),
+    Limit(LimitError),
+}
+
+fn reason<E: ParserError>(error: E) -> Failure {
+    match error.limit() {
+        Some(err) => Failure::Limit(err),
+        None => Failure::Rejected(error.to_string()),
     }
+}
 
-    // Llanfair 2's format is almost entirely optional so it should be parsed
-    // after all other XML based formats.
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = llanfair2::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::Llanfair2));
+fn parse_as<R>(
+    kind: TimerKind,
+    source: &mut R,
+    path: &Option<PathBuf>,
+    files_path: &Option<PathBuf>,
+    load_files: bool,
+    limits: &ParseLimits,
+) -> StdResult<Run, Failure>
+where
+    R: BufRead + Seek,
+{
+    match kind {
+        TimerKind::LiveSplit => {
+            livesplit::parse_with_limits(source, path.clone(), limits).map_err(reason)
+        }
+        TimerKind::WSplit => wsplit::parse_with_limits(source, load_files, limits).map_err(reason),
+        TimerKind::SplitterZ => {
+            splitterz::parse_with_limits(source, load_files, limits).map_err(reason)
+        }
+        TimerKind::ShitSplit => shit_split::parse_with_limits(source, limits).map_err(reason),
+        TimerKind::Splitty => splitty::parse_with_limits(source, limits).map_err(reason),
+        TimerKind::TimeSplitTracker => {
+            time_split_tracker::parse_with_limits(source, files_path.clone(), limits)
+                .map_err(reason)
+        }
+        TimerKind::Portal2LiveTimer => {
+            portal2_live_timer::parse_with_limits(source, limits).map_err(reason)
+        }
+        TimerKind::FaceSplit => {
+            face_split::parse_with_limits(source, load_files, limits).map_err(reason)
+        }
+        TimerKind::Llanfair => llanfair::parse_with_limits(source, limits).map_err(reason),
+        TimerKind::LlanfairGered => {
+            llanfair_gered::parse_with_limits(source, limits).map_err(reason)
+        }
+        TimerKind::Llanfair2 => llanfair2::parse_with_limits(source, limits).map_err(reason),
+        TimerKind::SourceLiveTimer => {
+            source_live_timer::parse_with_limits(source, limits).map_err(reason)
+        }
+        TimerKind::Urn => urn::parse_with_limits(source, limits).map_err(reason),
     }
+}
+
+/// Attempts to parse a splits file by invoking the corresponding parser for the
+/// file format detected. A path to the splits file can be provided, which helps
+/// saving the splits file again later. Additionally you need to specify if
+/// additional files, like external images are allowed to be loaded. If you are
+/// using livesplit-core in a server-like environment, set this to `false`. Only
+/// client-side applications should set this to `true`.
+///
+/// The parsers are tried in the order of how plausible the file format
+/// detection considers their file formats. If none of them is able to parse
+/// the splits file, the error lists why each plausible parser rejected it.
+pub fn parse<R>(source: R, path: Option<PathBuf>, load_files: bool) -> Result<ParsedRun>
+where
+    R: BufRead + Seek,
+{
+    parse_with_limits(source, path, load_files, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a splits file without exceeding the limits provided. This
+/// is how splits files from untrusted sources should be parsed. If the splits
+/// file exceeds any of the limits for a parser that considers the file format
+/// plausible, the parsing stops immediately, without trying any of the
+/// remaining parsers. Parsers that are only tried as a last resort can't stop
+/// the parsing that way. Their limit errors are only reported if no parser
+/// is able to parse the splits file at all.
+pub fn parse_with_limits<R>(
+    mut source: R,
+    path: Option<PathBuf>,
+    load_files: bool,
+    limits: &ParseLimits,
+) -> Result<ParsedRun>
+where
+    R: BufRead + Seek,
+{
+    let files_path =
+        if load_files { path.clone() } else { None };
+
+    let len = source.seek(SeekFrom::End(0))?;
+    limits.check_total_bytes(len)?;
 
-    // SourceLiveTimer needs to be before Urn because of a false positive
-    // due to the nature of parsing json files.
     source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = source_live_timer::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::SourceLiveTimer));
+    let mut prefix = Vec::new();
+    (&mut source)
+        .take(DETECTION_LEN)
+        .read_to_end(&mut prefix)
+        .map_err(Error::Read)?;
+
+    let mut rejections = Vec::new();
+    let mut unlikely_limit = None;
+
+    for candidate in detect(&prefix) {
+        source.seek(SeekFrom::Start(0))?;
+        match parse_as(
+            candidate.kind,
+            &mut source,
+            &path,
+            &files_path,
+            load_files,
+            limits,
+        ) {
+            Ok(run) => return Ok(parsed(run, candidate.kind)),
+            Err(Failure::Limit(err)) => if candidate.confidence > Confidence::Unlikely {
+                return Err(err.into());
+            } else if unlikely_limit.is_none() {
+                unlikely_limit = Some(err);
+            },
+            Err(Failure::Rejected(reason)) => if candidate.confidence > Confidence::Unlikely {
+                rejections.push(Rejection {
+                    kind: candidate.kind,
+                    confidence: candidate.confidence,
+                    reason,
+                });
+            },
+        }
     }
 
-    source.seek(SeekFrom::Start(0))?;
-    if let Ok(run) = urn::parse(&mut source) {
-        return Ok(parsed(run, TimerKind::Urn));
+    if rejections.is_empty() {
+        if let Some(err) = unlikely_limit {
+            return Err(err.into());
+        }
     }
 
-    Err(Error::NoParserParsedIt)
+    Err(Error::NoParserParsedIt(rejections))
 }
@@ -4,7 +4,9 @@ use std::borrow::Cow;
 use std::io::{self, BufRead};
 use std::result::Result as StdResult;
 use std::num::ParseIntError;
-use {time, Image, RealTime, Run, Segment, Time, TimeSpan};
+use {time, RealTime, Run, Segment, Time, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the FaceSplit
@@ -33,6 +35,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -64,6 +70,22 @@ fn replace<'a>(text: &'a str, a: &'a str, b: &str) -> Cow<'a, str> {
 /// server-like environment, set this to `false`. Only client-side applications
 /// should set this to `true`.
 pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
+    parse_with_limits(source, load_icons, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a FaceSplit splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(
+    source: R,
+    load_icons: bool,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    limits.read_limited(source, |source| {
+        parse_source(source, load_icons, &Budget::new(limits))
+    })
+}
+
+fn parse_source<R: BufRead>(source: R, load_icons: bool, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
     let mut icon_buf = Vec::new();
     let mut lines = source.lines();
@@ -95,13 +117,15 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
         if load_icons {
             if let Some(icon_path) = splits.next() {
                 if !icon_path.is_empty() {
-                    if let Ok(image) = Image::from_file(icon_path, &mut icon_buf) {
+                    if let Some(image) = limits.load_icon(icon_path, &mut icon_buf)? {
                         segment.set_icon(image);
                     }
                 }
             }
         }
 
+        limits.check_segments(run.len() + 1)?;
+        limits.charge_segments(1)?;
         run.push_segment(segment);
     }
 
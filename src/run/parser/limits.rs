@@ -0,0 +1,293 @@
+use std::cell::Cell;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::mem;
+use std::ops::Deref;
+use std::path::Path;
+use std::rc::Rc;
+use std::result::Result as StdResult;
+use std::usize;
+use {Attempt, Image, Segment, Time};
+
+quick_error! {
+    /// The Error type for splits files that exceed one of the limits
+    /// configured via `ParseLimits`.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum LimitError {
+        /// The splits file is larger than the maximum amount of bytes allowed.
+        TotalBytes {}
+        /// An icon is larger than the maximum amount of bytes allowed.
+        IconBytes {}
+        /// The splits file contains more segments than allowed.
+        Segments {}
+        /// The splits file contains more attempts or segment history entries
+        /// than allowed.
+        Attempts {}
+        /// The elements of the splits file are nested more deeply than
+        /// allowed.
+        Nesting {}
+        /// The parsed splits would take up more memory than allowed.
+        Allocation {}
+    }
+}
+
+/// Describes how many resources the parsers are allowed to spend on a single
+/// splits file. Splits files coming from untrusted sources, like files
+/// uploaded to a server, can otherwise make the parsers allocate as much memory
+/// as the splits file demands. All the parsers honour these limits and report
+/// a `LimitError` when a splits file exceeds any of them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use livesplit_core::run::parser::{composite, ParseLimits};
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let file = BufReader::new(File::open("path/to/splits_file").expect("File not found"));
+///
+/// let limits = ParseLimits {
+///     max_segments: 500,
+///     ..ParseLimits::untrusted()
+/// };
+///
+/// let parsed = composite::parse_with_limits(file, None, false, &limits);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The maximum amount of bytes read from the splits file. This only
+    /// bounds the input. The parsers may still buffer up to this amount of
+    /// bytes for a single element before the other limits are checked.
+    pub max_total_bytes: u64,
+    /// The maximum size of a single icon in bytes. For icons that are stored
+    /// as raw pixels, this is the size of the decoded pixels. Icons embedded
+    /// in the splits file are checked once their encoded data has been read,
+    /// but before they are decoded.
+    pub max_icon_bytes: usize,
+    /// The maximum amount of segments. Each segment is checked once it has
+    /// been parsed, before it is added to the Run.
+    pub max_segments: usize,
+    /// The maximum amount of attempts. This also limits the amount of
+    /// history entries stored for each segment.
+    pub max_attempts: usize,
+    /// The maximum depth of nested elements in XML-based splits files.
+    pub max_nesting: usize,
+    /// The maximum amount of memory in bytes that the segments, attempts,
+    /// segment history entries and icons of the parsed splits may take up
+    /// altogether. Unlike the other limits, this also catches small splits
+    /// files that expand into lots of memory, like a few attempts that each
+    /// add history entries to thousands of segments. Text, like the segment
+    /// names, isn't counted, as it is already bounded by `max_total_bytes`.
+    pub max_allocated_bytes: u64,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits::unlimited()
+    }
+}
+
+impl ParseLimits {
+    /// Creates limits that don't restrict the parsers at all. This is what
+    /// the parsers use if no limits are specified.
+    pub fn unlimited() -> Self {
+        ParseLimits {
+            max_total_bytes: u64::max_value(),
+            max_icon_bytes: usize::MAX,
+            max_segments: usize::MAX,
+            max_attempts: usize::MAX,
+            max_nesting: usize::MAX,
+            max_allocated_bytes: u64::max_value(),
+        }
+    }
+
+    /// Creates conservative limits suitable for parsing splits files from
+    /// untrusted sources. They are still large enough for any reasonable
+    /// splits file.
+    pub fn untrusted() -> Self {
+        ParseLimits {
+            max_total_bytes: 64 << 20,
+            max_icon_bytes: 4 << 20,
+            max_segments: 10_000,
+            max_attempts: 100_000,
+            max_nesting: 64,
+            max_allocated_bytes: 256 << 20,
+        }
+    }
+
+    pub(super) fn check_total_bytes(&self, len: u64) -> StdResult<(), LimitError> {
+        check(len <= self.max_total_bytes, LimitError::TotalBytes)
+    }
+
+    pub(super) fn check_icon_bytes(&self, len: usize) -> StdResult<(), LimitError> {
+        check(len <= self.max_icon_bytes, LimitError::IconBytes)
+    }
+
+    pub(super) fn check_segments(&self, count: usize) -> StdResult<(), LimitError> {
+        check(count <= self.max_segments, LimitError::Segments)
+    }
+
+    pub(super) fn check_attempts(&self, count: usize) -> StdResult<(), LimitError> {
+        check(count <= self.max_attempts, LimitError::Attempts)
+    }
+
+    pub(super) fn check_nesting(&self, depth: usize) -> StdResult<(), LimitError> {
+        check(depth <= self.max_nesting, LimitError::Nesting)
+    }
+
+    /// Runs a parser on a source that refuses to provide more bytes than the
+    /// limits allow. If the parser fails because the source ran out of bytes,
+    /// the error is reported as a `LimitError` instead.
+    pub(super) fn read_limited<R, F, T, E>(&self, source: R, f: F) -> StdResult<T, E>
+    where
+        F: FnOnce(&mut Limited<R>) -> StdResult<T, E>,
+        E: From<LimitError>,
+    {
+        let mut source = Limited::new(source, self.max_total_bytes);
+        let result = f(&mut source);
+        if source.exceeded() {
+            return Err(LimitError::TotalBytes.into());
+        }
+        result
+    }
+}
+
+/// Keeps track of how much of the memory allowed by the limits a single
+/// parser has used up so far. Clones of a budget share what is remaining.
+#[derive(Clone)]
+pub struct Budget {
+    limits: ParseLimits,
+    remaining: Rc<Cell<u64>>,
+}
+
+impl Budget {
+    pub fn new(limits: &ParseLimits) -> Self {
+        Budget {
+            limits: *limits,
+            remaining: Rc::new(Cell::new(limits.max_allocated_bytes)),
+        }
+    }
+
+    fn charge<T>(&self, count: usize) -> StdResult<(), LimitError> {
+        let bytes = (count as u64).saturating_mul(mem::size_of::<T>() as u64);
+        let remaining = self.remaining.get();
+        check(bytes <= remaining, LimitError::Allocation)?;
+        self.remaining.set(remaining - bytes);
+        Ok(())
+    }
+
+    pub fn charge_segments(&self, count: usize) -> StdResult<(), LimitError> {
+        self.charge::<Segment>(count)
+    }
+
+    pub fn charge_attempts(&self, count: usize) -> StdResult<(), LimitError> {
+        self.charge::<Attempt>(count)
+    }
+
+    pub fn charge_history_entries(&self, count: usize) -> StdResult<(), LimitError> {
+        self.charge::<(i32, Time)>(count)
+    }
+
+    /// Checks the size of an icon before it gets allocated and charges it
+    /// against the budget.
+    pub fn charge_icon(&self, len: usize) -> StdResult<(), LimitError> {
+        self.check_icon_bytes(len)?;
+        self.charge::<u8>(len)
+    }
+
+    /// Loads an icon from the file system. Icons that can't be loaded are
+    /// ignored, while icons exceeding the limits are reported before they get
+    /// loaded.
+    pub fn load_icon<P: AsRef<Path>>(
+        &self,
+        path: P,
+        buf: &mut Vec<u8>,
+    ) -> StdResult<Option<Image>, LimitError> {
+        if let Ok(metadata) = fs::metadata(path.as_ref()) {
+            if metadata.len() > self.max_icon_bytes as u64 {
+                return Err(LimitError::IconBytes);
+            }
+        }
+        let image = Image::from_file(path, buf).ok();
+        if let Some(ref image) = image {
+            self.charge_icon(image.data().len())?;
+        }
+        Ok(image)
+    }
+}
+
+impl Deref for Budget {
+    type Target = ParseLimits;
+
+    fn deref(&self) -> &ParseLimits {
+        &self.limits
+    }
+}
+
+fn check(within_limit: bool, error: LimitError) -> StdResult<(), LimitError> {
+    if within_limit {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// A source that fails once more bytes than allowed have been read from it.
+pub struct Limited<R> {
+    source: R,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<R> Limited<R> {
+    pub fn new(source: R, max_bytes: u64) -> Self {
+        Limited {
+            source,
+            remaining: max_bytes,
+            exceeded: false,
+        }
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    fn count(&mut self, len: usize) {
+        if len as u64 > self.remaining {
+            self.remaining = 0;
+            self.exceeded = true;
+        } else {
+            self.remaining -= len as u64;
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.exceeded {
+            Err(io::Error::new(io::ErrorKind::Other, LimitError::TotalBytes))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        let len = self.source.read(buf)?;
+        self.count(len);
+        self.check()?;
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for Limited<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.check()?;
+        self.source.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count(amt);
+        self.source.consume(amt)
+    }
+}
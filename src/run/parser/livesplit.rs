@@ -1,22 +1,178 @@
 //! Provides the parser for LiveSplit splits files.
-
+//!
+//! Errors describe the byte offset and the path of the XML element at which
+//! the problem was detected. Splits files often get corrupted after crashes,
+//! so a lenient mode is available as well, which skips malformed history
+//! entries and times and reports them as warnings instead of rejecting the
+//! whole splits file.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use livesplit_core::run::parser::livesplit;
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! let file = BufReader::new(File::open("path/to/splits_file.lss").expect("File not found"));
+//! let parsed = livesplit::parse_lenient(file, None).expect("Not a LiveSplit splits file");
+//!
+//! for warning in &parsed.warnings {
+//!     println!("Skipped: {}", warning);
+//! }
+//!
+//! let run = parsed.run;
+//! ```
+
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::fmt;
 use std::io::BufRead;
 use std::path::PathBuf;
-use {AtomicDateTime, Run, RunMetadata, Segment, Time, TimeSpan, base64};
+use std::result::Result as StdResult;
+use {AtomicDateTime, Attempt, Run, RunMetadata, Segment, Time, TimeSpan, base64};
 use super::super::run::ComparisonError;
-use quick_xml::reader::Reader;
+use comparison::{ComparisonGenerator, GoalTime, PercentileSegments, RecentAttempts};
+use comparison::recent_attempts::{Kind, Window};
 use chrono::{DateTime, TimeZone, Utc};
-use std::str;
-use super::xml_util::{attribute, attribute_err, end_tag, optional_attribute_err, parse_attributes,
-                      parse_base, parse_children, reencode_children, text, text_as_bytes_err,
-                      text_err, text_parsed};
+use super::{LimitError, ParseLimits};
+use super::limits::{Budget, Limited};
+use super::xml_util::{self, attribute, attribute_err, end_tag, optional_attribute_err,
+                      parse_attributes, parse_base, parse_children, reencode_children,
+                      reencode_element, text, text_as_bytes_err, Reader, Tag};
+
+pub use super::xml_util::Error as ErrorKind;
+
+type XmlResult<T> = xml_util::Result<T>;
+
+/// The Error type for LiveSplit splits files that couldn't be parsed. Next to
+/// the kind of problem that occurred, it describes where in the splits file
+/// the problem has been detected.
+#[derive(Debug)]
+pub struct Error {
+    /// The byte offset in the splits file at which the problem has been
+    /// detected.
+    pub position: usize,
+    /// The path of the XML element the problem occurred in, like
+    /// `Run/Segments/Segment[12]/SegmentHistory/Time[id=5]`. Segments are
+    /// counted starting at 1, while history entries are identified by their
+    /// IDs.
+    pub path: String,
+    /// The kind of problem that occurred.
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {} in {}", self.kind, self.position, self.path)
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        self.kind.description()
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        Some(&self.kind)
+    }
+}
+
+/// The Result type for the LiveSplit Parser.
+pub type Result<T> = StdResult<T, Error>;
+
+/// A Run parsed in lenient mode. Malformed history entries and times are
+/// skipped while parsing. Each of them is described by a warning.
+#[derive(Debug)]
+pub struct LenientRun {
+    /// The parsed run.
+    pub run: Run,
+    /// Describes all the parts of the splits file that got skipped.
+    pub warnings: Vec<Error>,
+}
+
+struct Context {
+    lenient: bool,
+    path: RefCell<Vec<String>>,
+    error_location: RefCell<Option<(usize, String)>>,
+    warnings: RefCell<Vec<Error>>,
+}
+
+impl Context {
+    fn new(lenient: bool) -> Self {
+        Context {
+            lenient,
+            path: RefCell::new(Vec::new()),
+            error_location: RefCell::new(None),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn path(&self) -> String {
+        self.path.borrow().join("/")
+    }
+
+    /// Remembers the location of an error. Only the innermost location is
+    /// kept, as the error bubbles up through all the elements it is nested in.
+    fn locate<R: BufRead>(&self, reader: &Reader<R>) {
+        let mut location = self.error_location.borrow_mut();
+        if location.is_none() {
+            *location = Some((reader.buffer_position(), self.path()));
+        }
+    }
+
+    fn enter<R, F, T>(&self, reader: &mut Reader<R>, element: String, f: F) -> XmlResult<T>
+    where
+        R: BufRead,
+        F: FnOnce(&mut Reader<R>) -> XmlResult<T>,
+    {
+        self.path.borrow_mut().push(element);
+        let result = f(reader);
+        if result.is_err() {
+            self.locate(reader);
+        }
+        self.path.borrow_mut().pop();
+        result
+    }
+
+    /// Reports a malformed value. In lenient mode this results in a warning,
+    /// otherwise the error is returned.
+    fn invalid<R: BufRead>(&self, reader: &Reader<R>, error: ErrorKind) -> XmlResult<()> {
+        if self.lenient {
+            self.warnings.borrow_mut().push(Error {
+                position: reader.buffer_position(),
+                path: self.path(),
+                kind: error,
+            });
+            Ok(())
+        } else {
+            self.locate(reader);
+            Err(error)
+        }
+    }
+
+    fn warning_count(&self) -> usize {
+        self.warnings.borrow().len()
+    }
+}
 
-pub use super::xml_util::{Error, Result};
+/// Builds the name of a history element for the element path, like
+/// `Time[id=5]`.
+fn entry_name(name: &str, tag: &Tag) -> String {
+    let mut id = None;
+    let _ = optional_attribute_err(tag, b"id", |t| {
+        id = Some(t.into_owned());
+        Ok(())
+    });
+    match id {
+        Some(id) => format!("{}[id={}]", name, id),
+        None => name.to_owned(),
+    }
+}
 
 #[derive(Copy, Clone, PartialOrd, PartialEq, Ord, Eq)]
 struct Version(u32, u32, u32, u32);
 
-fn parse_version<S: AsRef<str>>(version: S) -> Result<Version> {
+fn parse_version<S: AsRef<str>>(version: S) -> XmlResult<Version> {
     let splits = version.as_ref().split('.');
     let mut v = [1, 0, 0, 0];
     for (d, s) in v.iter_mut().zip(splits) {
@@ -25,7 +181,7 @@ fn parse_version<S: AsRef<str>>(version: S) -> Result<Version> {
     Ok(Version(v[0], v[1], v[2], v[3]))
 }
 
-fn parse_date_time<S: AsRef<str>>(text: S) -> Result<DateTime<Utc>> {
+fn parse_date_time<S: AsRef<str>>(text: S) -> XmlResult<DateTime<Utc>> {
     Utc.datetime_from_str(text.as_ref(), "%m/%d/%Y %T")
         .map_err(Into::into)
 }
@@ -35,12 +191,14 @@ fn image<R, F>(
     result: &mut Vec<u8>,
     image_buf: &mut Vec<u8>,
     f: F,
-) -> Result<()>
+) -> XmlResult<()>
 where
     R: BufRead,
     F: FnOnce(&[u8]),
 {
+    let limits = reader.limits().clone();
     text_as_bytes_err(reader, result, |text| {
+        limits.charge_icon(text.len() / 4 * 3)?;
         if text.len() >= 216 {
             image_buf.clear();
             if base64::decode_config_buf(&text[212..], base64::STANDARD, image_buf).is_ok() {
@@ -53,52 +211,65 @@ where
     })
 }
 
-fn time_span<R, F>(reader: &mut Reader<R>, buf: &mut Vec<u8>, f: F) -> Result<()>
+fn parse_time_span(text: &str) -> XmlResult<TimeSpan> {
+    if let (Some(dot_index), Some(colon_index)) = (text.find('.'), text.find(':')) {
+        if dot_index < colon_index {
+            let days = TimeSpan::from_days(text[..dot_index].parse()?);
+            let time = text[dot_index + 1..].parse()?;
+            return Ok(days + time);
+        }
+    }
+    text.parse().map_err(Into::into)
+}
+
+fn time_span<R, F>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    f: F,
+) -> XmlResult<()>
 where
     R: BufRead,
     F: FnOnce(TimeSpan),
 {
-    text_err(reader, buf, |text| {
-        let time_span = || -> Result<TimeSpan> {
-            if let (Some(dot_index), Some(colon_index)) = (text.find('.'), text.find(':')) {
-                if dot_index < colon_index {
-                    let days = TimeSpan::from_days(text[..dot_index].parse()?);
-                    let time = text[dot_index + 1..].parse()?;
-                    return Ok(days + time);
-                }
-            }
-            text.parse().map_err(Into::into)
-        }()?;
-        f(time_span);
-        Ok(())
-    })
+    let mut time_span = Ok(TimeSpan::zero());
+    text(reader, buf, |text| time_span = parse_time_span(&text))?;
+    match time_span {
+        Ok(time_span) => f(time_span),
+        Err(e) => ctx.invalid(reader, e)?,
+    }
+    Ok(())
 }
 
-fn time_span_opt<R, F>(reader: &mut Reader<R>, buf: &mut Vec<u8>, f: F) -> Result<()>
+fn time_span_opt<R, F>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    f: F,
+) -> XmlResult<()>
 where
     R: BufRead,
     F: FnOnce(Option<TimeSpan>),
 {
-    text_err(reader, buf, |text| {
-        let time_span = || -> Result<Option<TimeSpan>> {
-            if text.is_empty() {
-                return Ok(None);
-            }
-            if let (Some(dot_index), Some(colon_index)) = (text.find('.'), text.find(':')) {
-                if dot_index < colon_index {
-                    let days = TimeSpan::from_days(text[..dot_index].parse()?);
-                    let time = text[dot_index + 1..].parse()?;
-                    return Ok(Some(days + time));
-                }
-            }
-            Ok(Some(text.parse()?))
-        }()?;
-        f(time_span);
-        Ok(())
-    })
+    let mut time_span = Ok(None);
+    text(reader, buf, |text| {
+        if !text.is_empty() {
+            time_span = parse_time_span(&text).map(Some);
+        }
+    })?;
+    match time_span {
+        Ok(time_span) => f(time_span),
+        Err(e) => ctx.invalid(reader, e)?,
+    }
+    Ok(())
 }
 
-fn time<R, F>(reader: &mut Reader<R>, buf: &mut Vec<u8>, f: F) -> Result<()>
+fn time<R, F>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    f: F,
+) -> XmlResult<()>
 where
     R: BufRead,
     F: FnOnce(Time),
@@ -107,11 +278,11 @@ where
 
     parse_children(reader, buf, |reader, tag| {
         if tag.name() == b"RealTime" {
-            time_span_opt(reader, tag.into_buf(), |t| {
+            time_span_opt(ctx, reader, tag.into_buf(), |t| {
                 time.real_time = t;
             })
         } else if tag.name() == b"GameTime" {
-            time_span_opt(reader, tag.into_buf(), |t| {
+            time_span_opt(ctx, reader, tag.into_buf(), |t| {
                 time.game_time = t;
             })
         } else {
@@ -124,38 +295,98 @@ where
     Ok(())
 }
 
-fn time_old<R, F>(reader: &mut Reader<R>, buf: &mut Vec<u8>, f: F) -> Result<()>
+fn time_old<R, F>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    f: F,
+) -> XmlResult<()>
 where
     R: BufRead,
     F: FnOnce(Time),
 {
-    time_span_opt(reader, buf, |t| f(Time::new().with_real_time(t)))
+    time_span_opt(ctx, reader, buf, |t| f(Time::new().with_real_time(t)))
 }
 
-fn parse_bool<S: AsRef<str>>(text: S) -> Result<bool> {
+fn parse_bool<S: AsRef<str>>(text: S) -> XmlResult<bool> {
     match text.as_ref() {
         "True" => Ok(true),
         "False" => Ok(false),
-        _ => Err(Error::Bool),
+        _ => Err(ErrorKind::Bool),
     }
 }
 
+/// Parses a history entry, which is an element with an `id` attribute. In
+/// lenient mode, the entry is skipped if anything about it is malformed.
+fn history_entry<R, F>(
+    ctx: &Context,
+    version: Version,
+    reader: &mut Reader<R>,
+    tag: Tag,
+    f: F,
+) -> XmlResult<()>
+where
+    R: BufRead,
+    F: FnOnce(i32, Time),
+{
+    let name = entry_name("Time", &tag);
+    ctx.enter(reader, name, |reader| {
+        let mut index = Err(ErrorKind::AttributeNotFound);
+        let id = attribute_err(&tag, b"id", |t| {
+            index = t.parse::<i32>().map_err(Into::into);
+            Ok(())
+        });
+        if let Err(e) = id {
+            index = Err(e);
+        }
+
+        let index = match index {
+            Ok(index) => index,
+            Err(e) => {
+                ctx.invalid(reader, e)?;
+                return end_tag(reader, tag.into_buf());
+            }
+        };
+
+        let warnings = ctx.warning_count();
+        let mut entry = None;
+        if version >= Version(1, 4, 1, 0) {
+            time(ctx, reader, tag.into_buf(), |t| entry = Some(t))?;
+        } else {
+            time_old(ctx, reader, tag.into_buf(), |t| entry = Some(t))?;
+        }
+
+        if let Some(time) = entry {
+            if ctx.warning_count() == warnings {
+                f(index, time);
+            }
+        }
+
+        Ok(())
+    })
+}
+
 fn parse_metadata<R: BufRead>(
+    ctx: &Context,
     version: Version,
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
     metadata: &mut RunMetadata,
-) -> Result<()> {
+) -> XmlResult<()> {
     if version >= Version(1, 6, 0, 0) {
         parse_children(reader, buf, |reader, tag| {
             if tag.name() == b"Run" {
                 attribute(&tag, b"id", |t| metadata.set_run_id(t))?;
                 end_tag(reader, tag.into_buf())
             } else if tag.name() == b"Platform" {
-                attribute_err(&tag, b"usesEmulator", |t| {
-                    metadata.set_emulator_usage(parse_bool(t)?);
-                    Ok(())
+                let mut uses_emulator = Ok(false);
+                attribute(&tag, b"usesEmulator", |t| {
+                    uses_emulator = parse_bool(t);
                 })?;
+                match uses_emulator {
+                    Ok(uses_emulator) => metadata.set_emulator_usage(uses_emulator),
+                    Err(e) => ctx.invalid(reader, e)?,
+                }
                 text(reader, tag.into_buf(), |t| metadata.set_platform_name(t))
             } else if tag.name() == b"Region" {
                 text(reader, tag.into_buf(), |t| metadata.set_region_name(t))
@@ -182,12 +413,13 @@ fn parse_metadata<R: BufRead>(
 }
 
 fn parse_segment<R: BufRead>(
+    ctx: &Context,
     version: Version,
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
     buf2: &mut Vec<u8>,
     run: &mut Run,
-) -> Result<Segment> {
+) -> XmlResult<Segment> {
     let mut segment = Segment::new("");
 
     parse_children(reader, buf, |reader, tag| {
@@ -197,71 +429,75 @@ fn parse_segment<R: BufRead>(
             image(reader, tag.into_buf(), buf2, |i| segment.set_icon(i))
         } else if tag.name() == b"SplitTimes" {
             if version >= Version(1, 3, 0, 0) {
-                parse_children(reader, tag.into_buf(), |reader, tag| {
-                    if tag.name() == b"SplitTime" {
-                        let mut comparison = String::new();
-                        attribute(&tag, b"name", |t| {
-                            comparison = t.into_owned();
-                        })?;
-                        if version >= Version(1, 4, 1, 0) {
-                            time(reader, tag.into_buf(), |t| {
-                                *segment.comparison_mut(&comparison) = t;
+                ctx.enter(reader, "SplitTimes".to_owned(), |reader| {
+                    parse_children(reader, tag.into_buf(), |reader, tag| {
+                        if tag.name() == b"SplitTime" {
+                            let mut comparison = String::new();
+                            attribute(&tag, b"name", |t| {
+                                comparison = t.into_owned();
                             })?;
-                        } else {
-                            time_old(reader, tag.into_buf(), |t| {
-                                *segment.comparison_mut(&comparison) = t;
+                            let name = format!("SplitTime[name={}]", comparison);
+                            ctx.enter(reader, name, |reader| {
+                                if version >= Version(1, 4, 1, 0) {
+                                    time(ctx, reader, tag.into_buf(), |t| {
+                                        *segment.comparison_mut(&comparison) = t;
+                                    })?;
+                                } else {
+                                    time_old(ctx, reader, tag.into_buf(), |t| {
+                                        *segment.comparison_mut(&comparison) = t;
+                                    })?;
+                                }
+                                Ok(())
                             })?;
+                            if let Err(ComparisonError::NameStartsWithRace) =
+                                run.add_custom_comparison(comparison)
+                            {
+                                return Err(ComparisonError::NameStartsWithRace.into());
+                            }
+                            Ok(())
+                        } else {
+                            end_tag(reader, tag.into_buf())
                         }
-                        if let Err(ComparisonError::NameStartsWithRace) =
-                            run.add_custom_comparison(comparison)
-                        {
-                            return Err(ComparisonError::NameStartsWithRace.into());
-                        }
-                        Ok(())
-                    } else {
-                        end_tag(reader, tag.into_buf())
-                    }
+                    })
                 })
             } else {
                 end_tag(reader, tag.into_buf())
             }
         } else if tag.name() == b"PersonalBestSplitTime" {
             if version < Version(1, 3, 0, 0) {
-                time_old(reader, tag.into_buf(), |t| {
-                    segment.set_personal_best_split_time(t);
+                ctx.enter(reader, "PersonalBestSplitTime".to_owned(), |reader| {
+                    time_old(ctx, reader, tag.into_buf(), |t| {
+                        segment.set_personal_best_split_time(t);
+                    })
                 })
             } else {
                 end_tag(reader, tag.into_buf())
             }
         } else if tag.name() == b"BestSegmentTime" {
-            if version >= Version(1, 4, 1, 0) {
-                time(reader, tag.into_buf(), |t| {
-                    segment.set_best_segment_time(t);
-                })
-            } else {
-                time_old(reader, tag.into_buf(), |t| {
-                    segment.set_best_segment_time(t);
-                })
-            }
-        } else if tag.name() == b"SegmentHistory" {
-            parse_children(reader, tag.into_buf(), |reader, tag| {
-                let mut index = 0;
-                attribute_err(&tag, b"id", |t| {
-                    index = t.parse()?;
-                    Ok(())
-                })?;
+            ctx.enter(reader, "BestSegmentTime".to_owned(), |reader| {
                 if version >= Version(1, 4, 1, 0) {
-                    time(reader, tag.into_buf(), |t| {
-                        segment.segment_history_mut().insert(index, t);
+                    time(ctx, reader, tag.into_buf(), |t| {
+                        segment.set_best_segment_time(t);
                     })
                 } else {
-                    time_old(reader, tag.into_buf(), |t| {
-                        segment.segment_history_mut().insert(index, t);
+                    time_old(ctx, reader, tag.into_buf(), |t| {
+                        segment.set_best_segment_time(t);
                     })
                 }
             })
+        } else if tag.name() == b"SegmentHistory" {
+            ctx.enter(reader, "SegmentHistory".to_owned(), |reader| {
+                parse_children(reader, tag.into_buf(), |reader, tag| {
+                    let entries = segment.segment_history().iter().len();
+                    reader.limits().check_attempts(entries + 1)?;
+                    reader.limits().charge_history_entries(1)?;
+                    history_entry(ctx, version, reader, tag, |index, t| {
+                        segment.segment_history_mut().insert(index, t);
+                    })
+                })
+            })
         } else {
-            end_tag(reader, tag.into_buf())
+            reencode_element(reader, tag, segment.unknown_elements_mut())
         }
     })?;
 
@@ -269,169 +505,386 @@ fn parse_segment<R: BufRead>(
 }
 
 fn parse_run_history<R: BufRead>(
+    ctx: &Context,
     version: Version,
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
     run: &mut Run,
-) -> Result<()> {
+) -> XmlResult<()> {
     if version >= Version(1, 5, 0, 0) {
         end_tag(reader, buf)
-    } else if version >= Version(1, 4, 1, 0) {
+    } else {
         parse_children(reader, buf, |reader, tag| {
-            let mut index = 0;
-            attribute_err(&tag, b"id", |t| {
-                index = t.parse()?;
-                Ok(())
-            })?;
-            time(reader, tag.into_buf(), |time| {
+            let attempts = run.attempt_history().len();
+            reader.limits().check_attempts(attempts + 1)?;
+            reader.limits().charge_attempts(1)?;
+            history_entry(ctx, version, reader, tag, |index, time| {
                 run.add_attempt_with_index(time, index, None, None, None);
             })
         })
-    } else {
-        parse_children(reader, buf, |reader, tag| {
-            let mut index = 0;
-            attribute_err(&tag, b"id", |t| {
-                index = t.parse()?;
+    }
+}
+
+fn parse_comparison_generators<R: BufRead>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    run: &mut Run,
+) -> XmlResult<()> {
+    parse_children(reader, buf, |reader, tag| {
+        if tag.name() == b"PercentileSegments" {
+            let mut percentile = None;
+            optional_attribute_err(&tag, b"percentile", |t| {
+                percentile = Some(t.parse()?);
                 Ok(())
             })?;
-            time_old(reader, tag.into_buf(), |time| {
-                run.add_attempt_with_index(time, index, None, None, None);
+            if let Some(percentile) = percentile {
+                add_generator(run, PercentileSegments::new(percentile));
+            }
+        } else if tag.name() == b"GoalTime" {
+            let mut goal = None;
+            optional_attribute_err(&tag, b"goal", |t| {
+                goal = Some(parse_time_span(&t)?);
+                Ok(())
+            })?;
+            if let Some(goal) = goal {
+                add_generator(run, GoalTime::new(goal));
+            }
+        } else if tag.name() == b"RecentAttempts" {
+            let (mut kind, mut window) = (None, None);
+            optional_attribute_err(&tag, b"comparison", |t| {
+                kind = Kind::from_name(&t);
+                Ok(())
+            })?;
+            optional_attribute_err(&tag, b"attempts", |t| {
+                window = Some(Window::Attempts(t.parse()?));
+                Ok(())
+            })?;
+            optional_attribute_err(&tag, b"days", |t| {
+                window = Some(Window::Days(t.parse()?));
+                Ok(())
+            })?;
+            if let (Some(kind), Some(window)) = (kind, window) {
+                add_generator(run, RecentAttempts::new(kind, window));
+            }
+        }
+        end_tag(reader, tag.into_buf())
+    })
+}
+
+fn add_generator<G: ComparisonGenerator + 'static>(run: &mut Run, generator: G) {
+    let exists = run.comparison_generators()
+        .iter()
+        .any(|g| g.name() == generator.name());
+    if !exists {
+        run.comparison_generators_mut().push(Box::new(generator));
+    }
+}
+
+fn parse_attempt<R: BufRead>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    tag: Tag,
+    run: &mut Run,
+) -> XmlResult<()> {
+    let mut time = Time::new();
+    let mut pause_time = None;
+    let mut unknown_elements = Vec::new();
+    let mut index = None;
+    let (mut started, mut started_synced) = (None, false);
+    let (mut ended, mut ended_synced) = (None, false);
+    let mut is_invalid = false;
+    let mut note = String::new();
+    let mut tags = Vec::new();
+
+    let attributes = parse_attributes(&tag, |k, v| {
+        if k == b"id" {
+            index = Some(v.get()?.parse()?);
+        } else if k == b"started" {
+            started = Some(parse_date_time(v.get()?)?);
+        } else if k == b"isStartedSynced" {
+            started_synced = parse_bool(v.get()?)?;
+        } else if k == b"ended" {
+            ended = Some(parse_date_time(v.get()?)?);
+        } else if k == b"isEndedSynced" {
+            ended_synced = parse_bool(v.get()?)?;
+        } else if k == b"isInvalid" {
+            is_invalid = parse_bool(v.get()?)?;
+        }
+        Ok(true)
+    });
+
+    let index = match attributes.and_then(|_| index.ok_or(ErrorKind::AttributeNotFound)) {
+        Ok(index) => index,
+        Err(e) => {
+            ctx.invalid(reader, e)?;
+            return end_tag(reader, tag.into_buf());
+        }
+    };
+
+    let warnings = ctx.warning_count();
+
+    parse_children(reader, tag.into_buf(), |reader, tag| {
+        if tag.name() == b"RealTime" {
+            time_span_opt(ctx, reader, tag.into_buf(), |t| {
+                time.real_time = t;
             })
-        })
+        } else if tag.name() == b"GameTime" {
+            time_span_opt(ctx, reader, tag.into_buf(), |t| {
+                time.game_time = t;
+            })
+        } else if tag.name() == b"PauseTime" {
+            time_span_opt(ctx, reader, tag.into_buf(), |t| {
+                pause_time = t;
+            })
+        } else if tag.name() == b"Note" {
+            text(reader, tag.into_buf(), |t| {
+                note = t.into_owned();
+            })
+        } else if tag.name() == b"Tags" {
+            parse_children(reader, tag.into_buf(), |reader, tag| {
+                text(reader, tag.into_buf(), |t| {
+                    tags.push(t.into_owned());
+                })
+            })
+        } else {
+            reencode_element(reader, tag, &mut unknown_elements)
+        }
+    })?;
+
+    if ctx.warning_count() == warnings {
+        let started = started.map(|t| AtomicDateTime::new(t, started_synced));
+        let ended = ended.map(|t| AtomicDateTime::new(t, ended_synced));
+
+        let mut attempt = Attempt::new(index, time, started, ended, pause_time);
+        attempt.set_invalid(is_invalid);
+        attempt.set_note(note);
+        for tag in tags {
+            attempt.add_tag(tag);
+        }
+        *attempt.unknown_elements_mut() = unknown_elements;
+        run.attempt_history_mut().push(attempt);
     }
+
+    Ok(())
 }
 
 fn parse_attempt_history<R: BufRead>(
+    ctx: &Context,
     version: Version,
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
     run: &mut Run,
-) -> Result<()> {
+) -> XmlResult<()> {
     if version >= Version(1, 5, 0, 0) {
         parse_children(reader, buf, |reader, tag| {
-            let mut time = Time::new();
-            let mut pause_time = None;
-            let mut index = None;
-            let (mut started, mut started_synced) = (None, false);
-            let (mut ended, mut ended_synced) = (None, false);
-
-            parse_attributes(&tag, |k, v| {
-                if k == b"id" {
-                    index = Some(v.get()?.parse()?);
-                } else if k == b"started" {
-                    started = Some(parse_date_time(v.get()?)?);
-                } else if k == b"isStartedSynced" {
-                    started_synced = parse_bool(v.get()?)?;
-                } else if k == b"ended" {
-                    ended = Some(parse_date_time(v.get()?)?);
-                } else if k == b"isEndedSynced" {
-                    ended_synced = parse_bool(v.get()?)?;
-                }
-                Ok(true)
-            })?;
+            let attempts = run.attempt_history().len();
+            reader.limits().check_attempts(attempts + 1)?;
+            reader.limits().charge_attempts(1)?;
+            let name = entry_name("Attempt", &tag);
+            ctx.enter(reader, name, |reader| parse_attempt(ctx, reader, tag, run))
+        })
+    } else {
+        end_tag(reader, buf)
+    }
+}
 
-            let index = index.ok_or(Error::AttributeNotFound)?;
+fn parse_run<R: BufRead>(
+    ctx: &Context,
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    run: &mut Run,
+) -> XmlResult<()> {
+    let mut buf2 = Vec::with_capacity(4096);
+
+    let mut required_flags = 0u8;
+
+    parse_base(reader, buf, b"Run", |reader, tag| {
+        ctx.enter(reader, "Run".to_owned(), |reader| {
+            let mut version = Version(1, 0, 0, 0);
+            optional_attribute_err(&tag, b"version", |t| {
+                version = parse_version(t)?;
+                Ok(())
+            })?;
 
             parse_children(reader, tag.into_buf(), |reader, tag| {
-                if tag.name() == b"RealTime" {
-                    time_span_opt(reader, tag.into_buf(), |t| {
-                        time.real_time = t;
+                if tag.name() == b"GameIcon" {
+                    required_flags |= 1 << 0;
+                    image(reader, tag.into_buf(), &mut buf2, |i| run.set_game_icon(i))
+                } else if tag.name() == b"GameName" {
+                    required_flags |= 1 << 1;
+                    text(reader, tag.into_buf(), |t| run.set_game_name(t))
+                } else if tag.name() == b"CategoryName" {
+                    required_flags |= 1 << 2;
+                    text(reader, tag.into_buf(), |t| run.set_category_name(t))
+                } else if tag.name() == b"Offset" {
+                    required_flags |= 1 << 3;
+                    ctx.enter(reader, "Offset".to_owned(), |reader| {
+                        time_span(ctx, reader, tag.into_buf(), |t| run.set_offset(t))
                     })
-                } else if tag.name() == b"GameTime" {
-                    time_span_opt(reader, tag.into_buf(), |t| {
-                        time.game_time = t;
+                } else if tag.name() == b"AttemptCount" {
+                    required_flags |= 1 << 4;
+                    ctx.enter(reader, "AttemptCount".to_owned(), |reader| {
+                        let mut attempt_count = Ok(0);
+                        text(reader, tag.into_buf(), |t| attempt_count = t.parse::<u32>())?;
+                        match attempt_count {
+                            Ok(attempt_count) => run.set_attempt_count(attempt_count),
+                            Err(e) => ctx.invalid(reader, e.into())?,
+                        }
+                        Ok(())
+                    })
+                } else if tag.name() == b"AttemptHistory" {
+                    ctx.enter(reader, "AttemptHistory".to_owned(), |reader| {
+                        parse_attempt_history(ctx, version, reader, tag.into_buf(), run)
+                    })
+                } else if tag.name() == b"RunHistory" {
+                    ctx.enter(reader, "RunHistory".to_owned(), |reader| {
+                        parse_run_history(ctx, version, reader, tag.into_buf(), run)
+                    })
+                } else if tag.name() == b"Metadata" {
+                    ctx.enter(reader, "Metadata".to_owned(), |reader| {
+                        parse_metadata(ctx, version, reader, tag.into_buf(), run.metadata_mut())
                     })
-                } else if tag.name() == b"PauseTime" {
-                    time_span_opt(reader, tag.into_buf(), |t| {
-                        pause_time = t;
+                } else if tag.name() == b"Segments" {
+                    required_flags |= 1 << 5;
+                    ctx.enter(reader, "Segments".to_owned(), |reader| {
+                        let mut segment_count = 0;
+                        parse_children(reader, tag.into_buf(), |reader, tag| {
+                            if tag.name() == b"Segment" {
+                                segment_count += 1;
+                                let name = format!("Segment[{}]", segment_count);
+                                let segment = ctx.enter(reader, name, |reader| {
+                                    parse_segment(
+                                        ctx,
+                                        version,
+                                        reader,
+                                        tag.into_buf(),
+                                        &mut buf2,
+                                        run,
+                                    )
+                                })?;
+                                reader.limits().check_segments(run.len() + 1)?;
+                                reader.limits().charge_segments(1)?;
+                                run.push_segment(segment);
+                                Ok(())
+                            } else {
+                                end_tag(reader, tag.into_buf())
+                            }
+                        })
                     })
+                } else if tag.name() == b"ComparisonGenerators" {
+                    ctx.enter(reader, "ComparisonGenerators".to_owned(), |reader| {
+                        parse_comparison_generators(reader, tag.into_buf(), run)
+                    })
+                } else if tag.name() == b"AutoSplitterSettings" {
+                    let settings = run.auto_splitter_settings_mut();
+                    reencode_children(reader, tag.into_buf(), settings)
                 } else {
-                    end_tag(reader, tag.into_buf())
+                    reencode_element(reader, tag, run.unknown_elements_mut())
                 }
             })?;
 
-            let started = started.map(|t| AtomicDateTime::new(t, started_synced));
-            let ended = ended.map(|t| AtomicDateTime::new(t, ended_synced));
-
-            run.add_attempt_with_index(time, index, started, ended, pause_time);
+            if required_flags != (1 << 6) - 1 {
+                return Err(ErrorKind::ElementNotFound);
+            }
 
             Ok(())
         })
-    } else {
-        end_tag(reader, buf)
+    })
+}
+
+fn parse_with_context<R: BufRead>(
+    ctx: &Context,
+    source: R,
+    path: Option<PathBuf>,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    let mut source = Limited::new(source, limits.max_total_bytes);
+    let result = parse_source(ctx, &mut source, path, limits);
+    if source.exceeded() {
+        let (position, path) = match result {
+            Err(e) => (e.position, e.path),
+            Ok(_) => (limits.max_total_bytes as usize, String::new()),
+        };
+        return Err(Error {
+            position,
+            path,
+            kind: LimitError::TotalBytes.into(),
+        });
     }
+    result
 }
 
-/// Attempts to parse a LiveSplit splits file. In addition to the source to
-/// parse, you can provide a path to the splits file, which helps saving the
-/// splits file again later.
-pub fn parse<R: BufRead>(source: R, path: Option<PathBuf>) -> Result<Run> {
-    let reader = &mut Reader::from_reader(source);
+fn parse_source<R: BufRead>(
+    ctx: &Context,
+    source: R,
+    path: Option<PathBuf>,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    let reader = &mut Reader::from_reader(source, &Budget::new(limits));
     reader.expand_empty_elements(true);
     reader.trim_text(true);
 
     let mut buf = Vec::with_capacity(4096);
-    let mut buf2 = Vec::with_capacity(4096);
 
     let mut run = Run::new();
 
-    let mut required_flags = 0u8;
-
-    parse_base(reader, &mut buf, b"Run", |reader, tag| {
-        let mut version = Version(1, 0, 0, 0);
-        optional_attribute_err(&tag, b"version", |t| {
-            version = parse_version(t)?;
-            Ok(())
-        })?;
-
-        parse_children(reader, tag.into_buf(), |reader, tag| {
-            if tag.name() == b"GameIcon" {
-                required_flags |= 1 << 0;
-                image(reader, tag.into_buf(), &mut buf2, |i| run.set_game_icon(i))
-            } else if tag.name() == b"GameName" {
-                required_flags |= 1 << 1;
-                text(reader, tag.into_buf(), |t| run.set_game_name(t))
-            } else if tag.name() == b"CategoryName" {
-                required_flags |= 1 << 2;
-                text(reader, tag.into_buf(), |t| run.set_category_name(t))
-            } else if tag.name() == b"Offset" {
-                required_flags |= 1 << 3;
-                time_span(reader, tag.into_buf(), |t| run.set_offset(t))
-            } else if tag.name() == b"AttemptCount" {
-                required_flags |= 1 << 4;
-                text_parsed(reader, tag.into_buf(), |t| run.set_attempt_count(t))
-            } else if tag.name() == b"AttemptHistory" {
-                parse_attempt_history(version, reader, tag.into_buf(), &mut run)
-            } else if tag.name() == b"RunHistory" {
-                parse_run_history(version, reader, tag.into_buf(), &mut run)
-            } else if tag.name() == b"Metadata" {
-                parse_metadata(version, reader, tag.into_buf(), run.metadata_mut())
-            } else if tag.name() == b"Segments" {
-                required_flags |= 1 << 5;
-                parse_children(reader, tag.into_buf(), |reader, tag| {
-                    if tag.name() == b"Segment" {
-                        let segment =
-                            parse_segment(version, reader, tag.into_buf(), &mut buf2, &mut run)?;
-                        run.push_segment(segment);
-                        Ok(())
-                    } else {
-                        end_tag(reader, tag.into_buf())
-                    }
-                })
-            } else if tag.name() == b"AutoSplitterSettings" {
-                let settings = run.auto_splitter_settings_mut();
-                reencode_children(reader, tag.into_buf(), settings)
-            } else {
-                end_tag(reader, tag.into_buf())
-            }
-        })
-    })?;
-
-    if required_flags != (1 << 6) - 1 {
-        return Err(Error::ElementNotFound);
+    if let Err(kind) = parse_run(ctx, reader, &mut buf, &mut run) {
+        let (position, path) = ctx.error_location
+            .borrow_mut()
+            .take()
+            .unwrap_or_else(|| (reader.buffer_position(), String::new()));
+        return Err(Error {
+            position,
+            path,
+            kind,
+        });
     }
 
     run.set_path(path);
 
     Ok(run)
 }
+
+/// Attempts to parse a LiveSplit splits file. In addition to the source to
+/// parse, you can provide a path to the splits file, which helps saving the
+/// splits file again later.
+pub fn parse<R: BufRead>(source: R, path: Option<PathBuf>) -> Result<Run> {
+    parse_with_limits(source, path, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a LiveSplit splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(
+    source: R,
+    path: Option<PathBuf>,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    parse_with_context(&Context::new(false), source, path, limits)
+}
+
+/// Attempts to parse a LiveSplit splits file in lenient mode. Malformed
+/// segment history entries, attempts and times are skipped instead of
+/// rejecting the whole splits file. A warning describing each of them is
+/// returned alongside the Run. Problems with the structure of the XML itself
+/// still cause the parsing to fail. In addition to the source to parse, you
+/// can provide a path to the splits file, which helps saving the splits file
+/// again later.
+pub fn parse_lenient<R: BufRead>(source: R, path: Option<PathBuf>) -> Result<LenientRun> {
+    parse_lenient_with_limits(source, path, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a LiveSplit splits file in lenient mode without
+/// exceeding the limits provided. Exceeding a limit is never skipped, but
+/// always causes the parsing to fail.
+pub fn parse_lenient_with_limits<R: BufRead>(
+    source: R,
+    path: Option<PathBuf>,
+    limits: &ParseLimits,
+) -> Result<LenientRun> {
+    let ctx = Context::new(true);
+    let run = parse_with_context(&ctx, source, path, limits)?;
+    Ok(LenientRun {
+        run,
+        warnings: ctx.warnings.into_inner(),
+    })
+}
@@ -6,6 +6,8 @@ use std::str::{Utf8Error, from_utf8};
 use byteorder::{ReadBytesExt, BE};
 use imagelib::{png, ColorType, ImageBuffer, Rgba};
 use {Image, RealTime, Run, Segment, Time, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the Llanfair
@@ -25,6 +27,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -52,7 +58,14 @@ fn read_string<R: Read>(mut source: R, buf: &mut Vec<u8>, max_length: u64) -> Re
 }
 
 /// Attempts to parse a Llanfair splits file.
-pub fn parse<R: Read + Seek>(mut source: R) -> Result<Run> {
+pub fn parse<R: Read + Seek>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a Llanfair splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: Read + Seek>(mut source: R, limits: &ParseLimits) -> Result<Run> {
+    let limits = &Budget::new(limits);
     let mut buf = Vec::new();
     let mut buf2 = Vec::new();
 
@@ -77,6 +90,7 @@ pub fn parse<R: Read + Seek>(mut source: R) -> Result<Run> {
 
     // Determine total length of the source
     let total_len = source.seek(SeekFrom::End(0))?;
+    limits.check_total_bytes(total_len)?;
 
     let mut run = Run::new();
 
@@ -90,6 +104,8 @@ pub fn parse<R: Read + Seek>(mut source: R) -> Result<Run> {
 
     source.seek(SeekFrom::Current(0x6))?;
     let segment_count = source.read_u32::<BE>()?;
+    limits.check_segments(segment_count as usize)?;
+    limits.charge_segments(segment_count as usize)?;
 
     // The object header changes if there is no instance of one of the object
     // used by the Run class. The 2 objects that can be affected are the Time
@@ -142,6 +158,7 @@ pub fn parse<R: Read + Seek>(mut source: R) -> Result<Run> {
             if len as u64 > total_len || width == 0 || height == 0 {
                 return Err(Error::LengthOutOfBounds);
             }
+            limits.charge_icon(len)?;
 
             buf.clear();
             buf.reserve(len);
@@ -2,12 +2,13 @@
 
 use std::io::BufRead;
 use {RealTime, Run, Segment, Time, TimeSpan};
-use quick_xml::reader::Reader;
 use std::cmp::min;
 use byteorder::{ByteOrder, BE};
 use imagelib::{png, ColorType, ImageBuffer, Rgba};
+use super::ParseLimits;
+use super::limits::Budget;
 use super::xml_util::{end_tag, parse_base, parse_children, single_child, text, text_err,
-                      text_parsed};
+                      text_parsed, Reader};
 
 pub use super::xml_util::{Error, Result};
 
@@ -44,6 +45,7 @@ where
     F: FnMut(&[u8]),
 {
     let (mut width, mut height) = (None, None);
+    let limits = reader.limits().clone();
     image_buf.clear();
 
     single_child(reader, buf, b"javax.swing.ImageIcon", |reader, tag| {
@@ -62,6 +64,7 @@ where
                 image_buf.clear();
                 if let (Some(width), Some(height)) = (width, height) {
                     let len = width as usize * height as usize * 4;
+                    limits.charge_icon(len)?;
                     image_buf.reserve(min(len, 32 << 20));
                 }
 
@@ -71,7 +74,8 @@ where
                     text_parsed(reader, tag.into_buf(), |value: i32| {
                         BE::write_i32(&mut tmp, value);
                         image_buf.extend_from_slice(&[tmp[1], tmp[2], tmp[3], tmp[0]]);
-                    })
+                    })?;
+                    limits.check_icon_bytes(image_buf.len()).map_err(Into::into)
                 })
             } else {
                 end_tag(reader, tag.into_buf())
@@ -124,7 +128,18 @@ fn parse_segment<R: BufRead>(
 
 /// Attempts to parse a splits file used by the Llanfair Rewrite.
 pub fn parse<R: BufRead>(source: R) -> Result<Run> {
-    let reader = &mut Reader::from_reader(source);
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a splits file used by the Llanfair Rewrite without
+/// exceeding the limits provided. This is how splits files from untrusted
+/// sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: BufRead>(source: R, limits: &Budget) -> Result<Run> {
+    let reader = &mut Reader::from_reader(source, limits);
     reader.expand_empty_elements(true);
     reader.trim_text(true);
 
@@ -155,6 +170,8 @@ pub fn parse<R: BufRead>(source: R) -> Result<Run> {
                 parse_children(reader, tag.into_buf(), |reader, tag| {
                     if tag.name() == b"segment" {
                         let segment = parse_segment(reader, tag.into_buf(), &mut image_buf)?;
+                        limits.check_segments(run.len() + 1)?;
+                        limits.charge_segments(1)?;
                         run.push_segment(segment);
                         Ok(())
                     } else {
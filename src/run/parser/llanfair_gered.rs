@@ -2,12 +2,13 @@
 
 use std::io::{BufRead, Cursor, Seek, SeekFrom};
 use {RealTime, Run, Segment, Time, TimeSpan};
-use quick_xml::reader::Reader;
 use byteorder::{ReadBytesExt, BE};
 use imagelib::{png, ColorType, ImageBuffer, Rgba};
 use base64::{self, STANDARD};
+use super::ParseLimits;
+use super::limits::Budget;
 use super::xml_util::{end_tag, optional_attribute_err, parse_base, parse_children, single_child,
-                      text, text_as_bytes_err, text_err, text_parsed};
+                      text, text_as_bytes_err, text_err, text_parsed, Reader};
 
 pub use super::xml_util::{Error, Result};
 
@@ -41,9 +42,12 @@ where
     R: BufRead,
     F: FnMut(&[u8]),
 {
+    let limits = reader.limits().clone();
+
     single_child(reader, tag_buf, b"ImageIcon", |reader, tag| {
         let tag_buf = tag.into_buf();
         let (width, height, image) = text_as_bytes_err(reader, tag_buf, |t| {
+            limits.check_icon_bytes(t.len() / 4 * 3)?;
             buf.clear();
             base64::decode_config_buf(&t, STANDARD, buf).map_err(|_| Error::ElementNotFound)?;
 
@@ -59,6 +63,7 @@ where
                 .checked_mul(height as usize)
                 .and_then(|b| b.checked_mul(4))
                 .ok_or(Error::LengthOutOfBounds)?;
+            limits.charge_icon(len)?;
 
             if buf.len() < 0xFE + len {
                 return Err(Error::ElementNotFound);
@@ -147,7 +152,18 @@ where
 
 /// Attempts to parse a splits file used by Gered's Llanfair fork.
 pub fn parse<R: BufRead>(source: R) -> Result<Run> {
-    let reader = &mut Reader::from_reader(source);
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a splits file used by Gered's Llanfair fork without
+/// exceeding the limits provided. This is how splits files from untrusted
+/// sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: BufRead>(source: R, limits: &Budget) -> Result<Run> {
+    let reader = &mut Reader::from_reader(source, limits);
     reader.expand_empty_elements(true);
     reader.trim_text(true);
 
@@ -175,6 +191,8 @@ pub fn parse<R: BufRead>(source: R) -> Result<Run> {
                         parse_children(reader, tag.into_buf(), |reader, tag| {
                             let segment =
                                 parse_segment(&mut total_time, reader, tag.into_buf(), &mut buf2)?;
+                            limits.check_segments(run.len() + 1)?;
+                            limits.charge_segments(1)?;
                             run.push_segment(segment);
                             Ok(())
                         })
@@ -46,7 +46,9 @@ pub mod time_split_tracker;
 pub mod urn;
 pub mod wsplit;
 
+mod limits;
 mod timer_kind;
 mod xml_util;
 
+pub use self::limits::{LimitError, ParseLimits};
 pub use self::timer_kind::TimerKind;
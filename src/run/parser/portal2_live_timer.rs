@@ -4,6 +4,8 @@ use std::io::{self, BufRead};
 use std::result::Result as StdResult;
 use std::num::ParseFloatError;
 use {GameTime, Run, Segment, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error types for splits files that couldn't be parsed by the Portal 2
@@ -28,6 +30,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -149,6 +155,17 @@ static CHAPTERS: [(&str, &[&str]); 9] = [
 
 /// Attempts to parse a Portal 2 Live Timer splits file.
 pub fn parse<R: BufRead>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a Portal 2 Live Timer splits file without exceeding the
+/// limits provided. This is how splits files from untrusted sources should be
+/// parsed.
+pub fn parse_with_limits<R: BufRead>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: BufRead>(source: R, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
 
     run.set_game_name("Portal 2");
@@ -177,6 +194,8 @@ pub fn parse<R: BufRead>(source: R) -> Result<Run> {
         let mut segment = Segment::new(chapter_name);
         segment.set_personal_best_split_time(time);
 
+        limits.check_segments(run.len() + 1)?;
+        limits.charge_segments(1)?;
         run.push_segment(segment);
     }
 
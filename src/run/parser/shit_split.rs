@@ -4,6 +4,8 @@ use std::io::{self, BufRead};
 use std::result::Result as StdResult;
 use std::num::ParseIntError;
 use {time, GameTime, Run, Segment, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the ShitSplit
@@ -32,6 +34,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -40,6 +46,16 @@ pub type Result<T> = StdResult<T, Error>;
 
 /// Attempts to parse a ShitSplit splits file.
 pub fn parse<R: BufRead>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a ShitSplit splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: BufRead>(source: R, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
 
     let mut lines = source.lines();
@@ -67,6 +83,10 @@ pub fn parse<R: BufRead>(source: R) -> Result<Run> {
         while let Some(line) = next_line {
             let line = line?;
             if line.starts_with('*') {
+                limits.check_segments(run.len() + 1)?;
+                limits.charge_segments(1)?;
+            limits.charge_segments(1)?;
+        limits.charge_segments(1)?;
                 run.push_segment(Segment::new(&line[1..]));
                 has_acts = true;
                 next_line = lines.next();
@@ -84,6 +104,9 @@ pub fn parse<R: BufRead>(source: R) -> Result<Run> {
         } else {
             let mut segment = Segment::new(world_name);
             segment.set_personal_best_split_time(time);
+            limits.check_segments(run.len() + 1)?;
+            limits.charge_segments(1)?;
+        limits.charge_segments(1)?;
             run.push_segment(segment);
         }
     }
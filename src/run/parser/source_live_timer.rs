@@ -5,6 +5,8 @@ use std::result::Result as StdResult;
 use serde_json::de::from_reader;
 use serde_json::Error as JsonError;
 use {GameTime, Run, Segment, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the
@@ -15,6 +17,10 @@ quick_error! {
         Json(err: JsonError) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -51,6 +57,16 @@ fn time_span_from_ticks(category_name: &str, ticks: u64) -> TimeSpan {
 
 /// Attempts to parse a SourceLiveTimer splits file.
 pub fn parse<R: Read>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a SourceLiveTimer splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: Read>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: Read>(source: R, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
     let splits: Splits = from_reader(source)?;
 
@@ -73,6 +89,9 @@ pub fn parse<R: Read>(source: R) -> Result<Run> {
     }
 
     if let Some(segments) = splits.Splits {
+        limits.check_segments(segments.len())?;
+        limits.charge_segments(segments.len())?;
+
         for split in segments {
             let name = if let Some(name) = split.Name {
                 name.to_owned()
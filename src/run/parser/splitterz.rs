@@ -4,7 +4,9 @@ use std::borrow::Cow;
 use std::io::{self, BufRead};
 use std::result::Result as StdResult;
 use std::num::ParseIntError;
-use {time, Image, RealTime, Run, Segment, TimeSpan};
+use {time, RealTime, Run, Segment, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the SplitterZ
@@ -35,6 +37,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -55,6 +61,22 @@ fn unescape(text: &str) -> Cow<str> {
 /// server-like environment, set this to `false`. Only client-side applications
 /// should set this to `true`.
 pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
+    parse_with_limits(source, load_icons, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a SplitterZ splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(
+    source: R,
+    load_icons: bool,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    limits.read_limited(source, |source| {
+        parse_source(source, load_icons, &Budget::new(limits))
+    })
+}
+
+fn parse_source<R: BufRead>(source: R, load_icons: bool, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
 
     let mut icon_buf = Vec::new();
@@ -87,8 +109,8 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
             if load_icons {
                 if let Some(icon_path) = splits.next() {
                     if !icon_path.is_empty() {
-                        if let Ok(image) =
-                            Image::from_file(unescape(icon_path).as_ref(), &mut icon_buf)
+                        if let Some(image) =
+                            limits.load_icon(unescape(icon_path).as_ref(), &mut icon_buf)?
                         {
                             segment.set_icon(image);
                         }
@@ -96,6 +118,9 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
                 }
             }
 
+            limits.check_segments(run.len() + 1)?;
+            limits.charge_segments(1)?;
+        limits.charge_segments(1)?;
             run.push_segment(segment);
         } else {
             break;
@@ -5,6 +5,8 @@ use std::result::Result as StdResult;
 use serde_json::de::from_reader;
 use serde_json::Error as JsonError;
 use {Run, Segment, Time, TimeSpan, TimingMethod};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the Splitty
@@ -15,6 +17,10 @@ quick_error! {
         Json(err: JsonError) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -49,6 +55,16 @@ fn parse_time(milliseconds: Option<f64>, method: TimingMethod) -> Time {
 
 /// Attempts to parse a Splitty splits file.
 pub fn parse<R: Read>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a Splitty splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: Read>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: Read>(source: R, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
 
     let splits: Splits = from_reader(source)?;
@@ -63,6 +79,9 @@ pub fn parse<R: Read>(source: R) -> Result<Run> {
         TimingMethod::GameTime
     };
 
+    limits.check_segments(splits.splits.len())?;
+    limits.charge_segments(splits.splits.len())?;
+
     for split in splits.splits {
         let mut segment = Segment::new(split.name);
         segment.set_personal_best_split_time(parse_time(split.pb_split, method));
@@ -7,7 +7,9 @@ use std::result::Result as StdResult;
 use std::num::ParseIntError;
 use super::super::ComparisonError;
 use chrono::{TimeZone, Utc};
-use {time, AtomicDateTime, Image, RealTime, Run, Segment, Time, TimeSpan};
+use {time, AtomicDateTime, RealTime, Run, Segment, Time, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the Time
@@ -44,6 +46,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -65,6 +71,27 @@ fn parse_time_optional(time: &str) -> Result<Option<TimeSpan>> {
 /// optional. If you are using livesplit-core in a server-like environment, set
 /// this to `None`. Only client-side applications should provide the path here.
 pub fn parse<R: BufRead>(source: R, path_for_loading_other_files: Option<PathBuf>) -> Result<Run> {
+    parse_with_limits(source, path_for_loading_other_files, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a Time Split Tracker splits file without exceeding the
+/// limits provided. This is how splits files from untrusted sources should be
+/// parsed. The limits also apply to the run log file.
+pub fn parse_with_limits<R: BufRead>(
+    source: R,
+    path_for_loading_other_files: Option<PathBuf>,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    limits.read_limited(source, |source| {
+        parse_source(source, path_for_loading_other_files, &Budget::new(limits))
+    })
+}
+
+fn parse_source<R: BufRead>(
+    source: R,
+    path_for_loading_other_files: Option<PathBuf>,
+    limits: &Budget,
+) -> Result<Run> {
     let mut run = Run::new();
     let mut buf = Vec::new();
     let path = path_for_loading_other_files;
@@ -76,11 +103,11 @@ pub fn parse<R: BufRead>(source: R, path_for_loading_other_files: Option<PathBuf
     run.set_attempt_count(splits.next().ok_or(Error::ExpectedAttemptCount)?.parse()?);
     run.set_offset(splits.next().ok_or(Error::ExpectedOffset)?.parse()?);
 
-    catch! {
-        let path = path.as_ref()?.with_file_name(splits.next()?);
-        let image = Image::from_file(path, &mut buf).ok()?;
-        run.set_game_icon(image);
-    };
+    if let Some(path) = catch! { path.as_ref()?.with_file_name(splits.next()?) } {
+        if let Some(image) = limits.load_icon(path, &mut buf)? {
+            run.set_game_icon(image);
+        }
+    }
 
     let line = lines.next().ok_or(Error::ExpectedTitleLine)??;
     let mut splits = line.split('\t');
@@ -130,88 +157,107 @@ pub fn parse<R: BufRead>(source: R, path_for_loading_other_files: Option<PathBuf
 
         let line = lines.next().ok_or(Error::ExpectedIconLine)??;
 
-        catch! {
-            let file = line.trim_right();
-            if !file.is_empty() {
-                let path = path.as_ref()?.with_file_name(file);
-                let image = Image::from_file(path, &mut buf).ok()?;
-                segment.set_icon(image);
+        let file = line.trim_right();
+        if !file.is_empty() {
+            if let Some(path) = path.as_ref() {
+                if let Some(image) = limits.load_icon(path.with_file_name(file), &mut buf)? {
+                    segment.set_icon(image);
+                }
             }
-        };
+        }
 
+        limits.check_segments(run.len() + 1)?;
+        limits.charge_segments(1)?;
         run.push_segment(segment);
     }
 
-    parse_history(&mut run, path).ok();
+    // The run log is optional, so only exceeding the limits is an error.
+    if let Err(Some(error)) = parse_history(&mut run, path, limits) {
+        return Err(error.into());
+    }
 
     Ok(run)
 }
 
-fn parse_history(run: &mut Run, path: Option<PathBuf>) -> StdResult<(), ()> {
+fn parse_history(
+    run: &mut Run,
+    path: Option<PathBuf>,
+    limits: &Budget,
+) -> StdResult<(), Option<LimitError>> {
     if let Some(mut path) = path {
         path.set_extension("");
         let mut path = path.into_os_string();
         path.push("-RunLog.txt");
         let path = PathBuf::from(path);
 
-        let lines = BufReader::new(File::open(path).map_err(|_| ())?).lines();
-        let mut attempt_id = 1;
-
-        for line in lines.skip(1) {
-            let line = line.map_err(|_| ())?;
-            let mut splits = line.split('\t');
-            let time_stamp = splits.next().ok_or(())?;
-            let started = Utc.datetime_from_str(time_stamp, "%Y/%m/%d %R")
-                .map_err(|_| ())?;
-            let completed = splits.next().ok_or(())? == "C";
-            let split_times: Vec<_> = splits
-                .map(parse_time_optional)
-                .collect::<Result<_>>()
-                .map_err(|_| ())?;
-            let mut final_time = Time::default();
-            let mut ended = None;
-            if completed {
-                catch! {
-                    let last_split_time = split_times.last()?;
-                    final_time.real_time = *last_split_time;
-                    let final_time = final_time.real_time?;
-                    let ended_date = started + final_time.to_duration();
-                    ended = Some(AtomicDateTime::new(ended_date, false));
-                };
-            }
+        let file = BufReader::new(File::open(path).map_err(|_| None)?);
 
-            run.add_attempt_with_index(
-                final_time,
-                attempt_id,
-                Some(AtomicDateTime::new(started, false)),
-                ended,
-                None,
-            );
-
-            let mut last_split = TimeSpan::zero();
-            for (segment, current_split) in
-                run.segments_mut().iter_mut().zip(split_times.into_iter())
-            {
-                let mut segment_time = Time::default();
-                if let Some(current_split) = current_split {
-                    segment_time.real_time = Some(current_split - last_split);
-                    last_split = current_split;
+        limits.read_limited(file, |file| {
+            let lines = file.lines();
+            let mut attempt_id = 1;
+
+            for line in lines.skip(1) {
+                let line = line.map_err(|_| None)?;
+                let mut splits = line.split('\t');
+                let time_stamp = splits.next().ok_or(None)?;
+                let started = Utc.datetime_from_str(time_stamp, "%Y/%m/%d %R")
+                    .map_err(|_| None)?;
+                let completed = splits.next().ok_or(None)? == "C";
+                let split_times: Vec<_> = splits
+                    .map(parse_time_optional)
+                    .collect::<Result<_>>()
+                    .map_err(|_| None)?;
+                let mut final_time = Time::default();
+                let mut ended = None;
+                if completed {
+                    catch! {
+                        let last_split_time = split_times.last()?;
+                        final_time.real_time = *last_split_time;
+                        let final_time = final_time.real_time?;
+                        let ended_date = started + final_time.to_duration();
+                        ended = Some(AtomicDateTime::new(ended_date, false));
+                    };
                 }
 
-                segment
-                    .segment_history_mut()
-                    .insert(attempt_id, segment_time);
+                limits.check_attempts(attempt_id as usize)?;
+                limits.charge_attempts(1)?;
+                limits.charge_history_entries(run.len().min(split_times.len()))?;
+                run.add_attempt_with_index(
+                    final_time,
+                    attempt_id,
+                    Some(AtomicDateTime::new(started, false)),
+                    ended,
+                    None,
+                );
 
-                if catch! {
-                    segment_time.real_time? < segment.best_segment_time().real_time?
-                }.unwrap_or(false)
+                let mut last_split = TimeSpan::zero();
+                for (segment, current_split) in
+                    run.segments_mut().iter_mut().zip(split_times.into_iter())
                 {
-                    segment.set_best_segment_time(segment_time);
+                    let mut segment_time = Time::default();
+                    if let Some(current_split) = current_split {
+                        segment_time.real_time = Some(current_split - last_split);
+                        last_split = current_split;
+                    }
+
+                    segment
+                        .segment_history_mut()
+                        .insert(attempt_id, segment_time);
+
+                    if catch! {
+                        segment_time.real_time? < segment.best_segment_time().real_time?
+                    }.unwrap_or(false)
+                    {
+                        segment.set_best_segment_time(segment_time);
+                    }
                 }
+
+                attempt_id += 1;
             }
 
-            attempt_id += 1;
-        }
+            Ok(())
+        })
+    } else {
+        Ok(())
     }
-    Ok(())
 }
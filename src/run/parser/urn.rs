@@ -5,6 +5,8 @@ use std::result::Result as StdResult;
 use serde_json::de::from_reader;
 use serde_json::Error as JsonError;
 use {time, Run, Segment, Time, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the Urn
@@ -19,6 +21,10 @@ quick_error! {
         Json(err: JsonError) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -56,6 +62,16 @@ fn parse_time(time: &str) -> Result<Time> {
 
 /// Attempts to parse a Urn splits file.
 pub fn parse<R: Read>(source: R) -> Result<Run> {
+    parse_with_limits(source, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a Urn splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: Read>(source: R, limits: &ParseLimits) -> Result<Run> {
+    limits.read_limited(source, |source| parse_source(source, &Budget::new(limits)))
+}
+
+fn parse_source<R: Read>(source: R, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
 
     let splits: Splits = from_reader(source)?;
@@ -77,6 +93,9 @@ pub fn parse<R: Read>(source: R) -> Result<Run> {
     let mut attempt_history_index = 1;
 
     if let Some(splits) = splits.splits {
+        limits.check_segments(splits.len())?;
+        limits.charge_segments(splits.len())?;
+
         for split in splits {
             let mut segment = Segment::new(split.title.unwrap_or_default());
             if let Some(time) = split.time {
@@ -89,6 +108,9 @@ pub fn parse<R: Read>(source: R) -> Result<Run> {
             if let Some(best_time) = split.best_time {
                 let best_split_time = parse_time(&best_time)?;
                 if best_split_time.real_time.is_some() {
+                    limits.check_attempts(attempt_history_index as usize)?;
+                    limits.charge_attempts(1)?;
+                    limits.charge_history_entries(run.len() + 1)?;
                     run.add_attempt_with_index(
                         Time::default(),
                         attempt_history_index,
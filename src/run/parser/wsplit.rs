@@ -4,6 +4,8 @@ use std::io::{self, BufRead};
 use std::result::Result as StdResult;
 use std::num::{ParseFloatError, ParseIntError};
 use {Image, RealTime, Run, Segment, TimeSpan};
+use super::{LimitError, ParseLimits};
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for splits files that couldn't be parsed by the WSplit
@@ -30,6 +32,10 @@ quick_error! {
         Io(err: io::Error) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
@@ -42,6 +48,22 @@ pub type Result<T> = StdResult<T, Error>;
 /// environment, set this to `false`. Only client-side applications should set
 /// this to `true`.
 pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
+    parse_with_limits(source, load_icons, &ParseLimits::unlimited())
+}
+
+/// Attempts to parse a WSplit splits file without exceeding the limits
+/// provided. This is how splits files from untrusted sources should be parsed.
+pub fn parse_with_limits<R: BufRead>(
+    source: R,
+    load_icons: bool,
+    limits: &ParseLimits,
+) -> Result<Run> {
+    limits.read_limited(source, |source| {
+        parse_source(source, load_icons, &Budget::new(limits))
+    })
+}
+
+fn parse_source<R: BufRead>(source: R, load_icons: bool, limits: &Budget) -> Result<Run> {
     let mut run = Run::new();
     let mut icon_buf = Vec::new();
     let mut icons_list = Vec::new();
@@ -69,7 +91,7 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
                     for path in icons.split(',') {
                         if path.len() >= 2 {
                             let path = &path[1..path.len() - 1];
-                            if let Ok(image) = Image::from_file(path, &mut icon_buf) {
+                            if let Some(image) = limits.load_icon(path, &mut icon_buf)? {
                                 icons_list.push(image);
                                 continue;
                             }
@@ -105,6 +127,10 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
                     old_run_exists = true;
                 }
 
+                limits.check_segments(run.len() + 1)?;
+                limits.charge_segments(1)?;
+            limits.charge_segments(1)?;
+        limits.charge_segments(1)?;
                 run.push_segment(segment);
             }
         }
@@ -124,8 +150,8 @@ pub fn parse<R: BufRead>(source: R, load_icons: bool) -> Result<Run> {
             .expect("WSplit: Old Run");
     }
 
-    for (icon, segment) in icons_list.into_iter().zip(run.segments_mut().iter_mut()) {
-        segment.set_icon(icon);
+    for (index, icon) in icons_list.into_iter().take(run.len()).enumerate() {
+        run.set_segment_icon(index, icon);
     }
 
     Ok(run)
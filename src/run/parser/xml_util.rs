@@ -1,8 +1,8 @@
-use quick_xml::reader::Reader;
+use quick_xml::reader::Reader as XmlReader;
 use quick_xml::Writer;
 use quick_xml::errors::Error as XmlError;
-use quick_xml::events::{attributes, BytesStart, BytesText, Event};
-use std::ops::Deref;
+use quick_xml::events::{attributes, BytesEnd, BytesStart, BytesText, Event};
+use std::ops::{Deref, DerefMut};
 use std::borrow::Cow;
 use std::{str, string};
 use std::io::{self, BufRead};
@@ -11,6 +11,8 @@ use std::num::{ParseFloatError, ParseIntError};
 use time;
 use chrono::ParseError as ChronoError;
 use super::super::ComparisonError;
+use super::LimitError;
+use super::limits::Budget;
 
 quick_error! {
     /// The Error type for XML-based splits files that couldn't be parsed.
@@ -64,12 +66,65 @@ quick_error! {
         Date(err: ChronoError) {
             from()
         }
+        /// The splits file exceeds one of the limits configured for parsing.
+        Limit(err: LimitError) {
+            from()
+        }
     }
 }
 
 /// The Result type for Parsers that parse XML-based splits files.
 pub type Result<T> = StdResult<T, Error>;
 
+/// An XML reader that keeps track of how deeply the current element is nested
+/// and enforces the limits configured for parsing.
+pub struct Reader<R: BufRead> {
+    reader: XmlReader<R>,
+    limits: Budget,
+    depth: usize,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn from_reader(source: R, limits: &Budget) -> Self {
+        Reader {
+            reader: XmlReader::from_reader(source),
+            limits: limits.clone(),
+            depth: 0,
+        }
+    }
+
+    pub fn limits(&self) -> &Budget {
+        &self.limits
+    }
+
+    pub fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let event = self.reader.read_event(buf)?;
+        match event {
+            Event::Start(_) => {
+                self.depth += 1;
+                self.limits.check_nesting(self.depth)?;
+            }
+            Event::End(_) => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        Ok(event)
+    }
+}
+
+impl<R: BufRead> Deref for Reader<R> {
+    type Target = XmlReader<R>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl<R: BufRead> DerefMut for Reader<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
 pub struct Tag<'a>(BytesStart<'a>, *mut Vec<u8>);
 
 impl<'a> Deref for Tag<'a> {
@@ -202,7 +257,7 @@ where
                 writer.write_event(Event::End(end))?;
             }
             Event::Text(text) => {
-                writer.write_event(Event::Text(BytesText::borrowed(&text.unescaped()?)))?;
+                writer.write_event(Event::Text(BytesText::borrowed(text.escaped())))?;
             }
             event @ Event::Comment(_)
             | event @ Event::CData(_)
@@ -221,6 +276,18 @@ where
     }
 }
 
+pub fn reencode_element<R>(reader: &mut Reader<R>, tag: Tag, target_buf: &mut Vec<u8>) -> Result<()>
+where
+    R: BufRead,
+{
+    let name = tag.name().to_owned();
+    Writer::new(&mut *target_buf)
+        .write_event(Event::Start(BytesStart::borrowed(&tag, name.len())))?;
+    reencode_children(reader, tag.into_buf(), target_buf)?;
+    Writer::new(target_buf).write_event(Event::End(BytesEnd::borrowed(&name)))?;
+    Ok(())
+}
+
 pub fn end_tag<R: BufRead>(reader: &mut Reader<R>, buf: &mut Vec<u8>) -> Result<()> {
     let mut depth = 0;
     loop {
@@ -3,6 +3,7 @@ use std::cmp::max;
 use std::collections::HashSet;
 use std::path::PathBuf;
 use {AtomicDateTime, Attempt, Image, RunMetadata, Segment, Time, TimeSpan, TimingMethod};
+use super::{IconStore, MAX_ICON_SIZE};
 use comparison::{default_generators, personal_best, ComparisonGenerator};
 use ordered_float::OrderedFloat;
 use unicase;
@@ -25,6 +26,7 @@ use unicase;
 #[derive(Clone, Debug, PartialEq)]
 pub struct Run {
     game_icon: Image,
+    icons: Icons,
     game_name: String,
     category_name: String,
     offset: TimeSpan,
@@ -37,6 +39,19 @@ pub struct Run {
     custom_comparisons: Vec<String>,
     comparison_generators: ComparisonGenerators,
     auto_splitter_settings: Vec<u8>,
+    unknown_elements: Vec<u8>,
+}
+
+/// The Icon Store only keeps the icons referenced by the game icon and the
+/// segment icons, which are compared already. Icons that aren't used anymore
+/// may still be stored, so the Icon Store is not compared at all.
+#[derive(Clone, Debug)]
+struct Icons(IconStore);
+
+impl PartialEq for Icons {
+    fn eq(&self, _: &Icons) -> bool {
+        true
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -70,6 +85,7 @@ impl Run {
     pub fn new() -> Self {
         Self {
             game_icon: Image::default(),
+            icons: Icons(IconStore::new()),
             game_name: String::new(),
             category_name: String::new(),
             offset: TimeSpan::zero(),
@@ -82,6 +98,7 @@ impl Run {
             custom_comparisons: vec![personal_best::NAME.to_string()],
             comparison_generators: ComparisonGenerators(default_generators()),
             auto_splitter_settings: Vec::new(),
+            unknown_elements: Vec::new(),
         }
     }
 
@@ -107,10 +124,55 @@ impl Run {
         &self.game_icon
     }
 
-    /// Sets the game's icon.
+    /// Sets the game's icon. The icon is stored in the Run's Icon Store.
     #[inline]
     pub fn set_game_icon<D: Into<Image>>(&mut self, image: D) {
-        self.game_icon = image.into();
+        self.game_icon = self.icons.0.intern(&image.into());
+    }
+
+    /// Sets the icon of a certain segment of this Run. The icon is stored in
+    /// the Run's Icon Store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn set_segment_icon<D: Into<Image>>(&mut self, index: usize, image: D) {
+        let icon = self.icons.0.intern(&image.into());
+        self.segments[index].set_icon(icon);
+    }
+
+    /// Downscales the game icon and the segment icons, so that none of them
+    /// exceed `MAX_ICON_SIZE`. The Icon Store is rebuilt afterwards. Icons of
+    /// parsed splits files are kept as they are, so call this after importing
+    /// a splits file if huge icons shouldn't be kept around.
+    pub fn downscale_icons(&mut self) {
+        self.game_icon.downscale(MAX_ICON_SIZE);
+        for segment in &mut self.segments {
+            let mut icon = segment.icon().clone();
+            if icon.downscale(MAX_ICON_SIZE) {
+                segment.set_icon(icon);
+            }
+        }
+        self.intern_icons();
+    }
+
+    /// Accesses the Icon Store that keeps all the distinct icons of this Run.
+    #[inline]
+    pub fn icons(&self) -> &IconStore {
+        &self.icons.0
+    }
+
+    /// Rebuilds the Icon Store from the game icon and the segment icons,
+    /// dropping all the icons that aren't used anymore. Icons that were
+    /// modified directly on the segments are stored as well. Icons with the
+    /// same data end up sharing the same handle.
+    pub fn intern_icons(&mut self) {
+        self.icons.0.clear();
+        self.game_icon = self.icons.0.intern(&self.game_icon);
+        for segment in &mut self.segments {
+            let icon = self.icons.0.intern(segment.icon());
+            segment.set_icon(icon);
+        }
     }
 
     /// Accesses the name of the category this Run is for.
@@ -192,9 +254,12 @@ impl Run {
         &mut self.segments
     }
 
-    /// Pushes the segment provided to the end of the list of segments of this Run.
+    /// Pushes the segment provided to the end of the list of segments of this
+    /// Run. The segment's icon is stored in the Run's Icon Store.
     #[inline]
-    pub fn push_segment(&mut self, segment: Segment) {
+    pub fn push_segment(&mut self, mut segment: Segment) {
+        let icon = self.icons.0.intern(segment.icon());
+        segment.set_icon(icon);
         self.segments.push(segment);
     }
 
@@ -227,6 +292,17 @@ impl Run {
         &self.attempt_history
     }
 
+    /// Grants mutable access to the history of all the runs that have been
+    /// attempted.
+    ///
+    /// # Warning
+    ///
+    /// The indices of the attempts need to stay unique.
+    #[inline]
+    pub fn attempt_history_mut(&mut self) -> &mut Vec<Attempt> {
+        &mut self.attempt_history
+    }
+
     /// Accesses the custom comparisons that are stored in this Run. This
     /// includes `Personal Best` but excludes all the other Comparison
     /// Generators.
@@ -287,6 +363,30 @@ impl Run {
         &mut self.auto_splitter_settings
     }
 
+    /// Accesses the XML elements of the Run that weren't understood when
+    /// parsing it, like elements added by newer versions of LiveSplit. They
+    /// are kept as they were, so they can be written back when saving the Run
+    /// as a LiveSplit splits file again. They are written back after all the
+    /// elements of the Run that are understood, so their position among those
+    /// elements is not preserved. LiveSplit looks up the elements by their
+    /// names, so their order doesn't affect how the splits file is loaded.
+    #[inline]
+    pub fn unknown_elements(&self) -> &[u8] {
+        &self.unknown_elements
+    }
+
+    /// Grants mutable access to the XML elements of the Run that weren't
+    /// understood when parsing it.
+    ///
+    /// # Warning
+    ///
+    /// You need to ensure that the elements are encoded as data that would be
+    /// valid as an interior of an XML element.
+    #[inline]
+    pub fn unknown_elements_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.unknown_elements
+    }
+
     /// Returns the amount of segments stored in this Run.
     #[inline]
     pub fn len(&self) -> usize {
@@ -1,3 +1,4 @@
+use std::mem::replace;
 use indexmap::map::{IndexMap, Iter};
 
 /// The Run Metadata stores additional information about a run, like the
@@ -105,4 +106,41 @@ impl RunMetadata {
     pub fn variables(&self) -> Iter<String, String> {
         self.variables.iter()
     }
+
+    /// Accesses the value of the variable with the name provided, if it
+    /// exists.
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Removes the variable with the name provided, if it exists. The order of
+    /// the remaining variables is kept.
+    pub fn remove_variable(&mut self, name: &str) {
+        if self.variables.contains_key(name) {
+            self.variables = replace(&mut self.variables, IndexMap::new())
+                .into_iter()
+                .filter(|&(ref n, _)| n != name)
+                .collect();
+        }
+    }
+
+    /// Renames a variable while keeping its value and its position. If there
+    /// is no variable with the old name or there already is a variable with
+    /// the new name, nothing is changed and `false` is returned.
+    pub fn rename_variable(&mut self, old: &str, new: &str) -> bool {
+        if !self.variables.contains_key(old) || self.variables.contains_key(new) {
+            return false;
+        }
+        self.variables = replace(&mut self.variables, IndexMap::new())
+            .into_iter()
+            .map(|(name, value)| {
+                if name == old {
+                    (new.to_owned(), value)
+                } else {
+                    (name, value)
+                }
+            })
+            .collect();
+        true
+    }
 }
@@ -0,0 +1,243 @@
+//! Saves Runs as LiveSplit splits files directly to the file system, without
+//! ever leaving a partially written splits file behind. The splits file is
+//! written to a temporary file first, which then atomically replaces the
+//! actual splits file. The previous versions of the splits file are kept as
+//! rotating, timestamped backups next to it. If the splits file ends up
+//! unusable anyway, the latest backup that can still be parsed can be loaded
+//! instead.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use livesplit_core::run::saver::file;
+//! use livesplit_core::{Run, Segment};
+//!
+//! let mut run = Run::new();
+//! run.set_game_name("Super Mario Odyssey");
+//! run.set_category_name("Any%");
+//! run.push_segment(Segment::new("Cap Kingdom"));
+//!
+//! // Save the splits file, keeping the last 5 versions as backups.
+//! file::save_run(&run, "path/to/splits_file.lss", 5).expect("Couldn't save the splits file");
+//!
+//! // Load the splits file again, falling back to the backups if necessary.
+//! let loaded = file::load("path/to/splits_file.lss").expect("No usable splits file");
+//! if let Some(backup) = loaded.recovered_from {
+//!     println!("Recovered from {}", backup.display());
+//! }
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use chrono::Utc;
+use {Run, Timer, TimerPhase};
+use super::livesplit;
+use run::parser::livesplit as parser;
+
+quick_error! {
+    /// The Error type for splits files that couldn't be saved to or loaded
+    /// from the file system.
+    #[derive(Debug)]
+    pub enum Error {
+        /// Failed to access the file system.
+        Io(err: io::Error) {
+            from()
+        }
+        /// Failed to write the splits file.
+        Save(err: livesplit::Error) {
+            from()
+        }
+        /// The path doesn't point to a file.
+        InvalidPath {}
+        /// All the backups that can be told apart within the same millisecond
+        /// already exist.
+        TooManyBackups {}
+        /// Neither the splits file nor any of its backups could be parsed. The
+        /// problem with the splits file itself is provided, if it exists.
+        NoUsableFile(err: Option<parser::Error>) {}
+    }
+}
+
+/// The Result type for saving and loading splits files.
+pub type Result<T> = StdResult<T, Error>;
+
+/// A Run loaded from the file system.
+#[derive(Debug)]
+pub struct LoadedRun {
+    /// The Run that got loaded.
+    pub run: Run,
+    /// The backup the Run got recovered from, if the splits file itself
+    /// couldn't be parsed.
+    pub recovered_from: Option<PathBuf>,
+}
+
+const BACKUP_EXTENSION: &str = ".bak";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+const TIMESTAMP_LEN: usize = 19;
+// Multiple backups created within the same millisecond are told apart by a
+// counter following the timestamp.
+const COUNTER_LEN: usize = 4;
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(ToOwned::to_owned)
+        .ok_or(Error::InvalidPath)
+}
+
+fn directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Makes sure the renaming of a file is persisted as well.
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Lists the backups of the splits file provided, ordered from the newest to
+/// the oldest.
+pub fn backups<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let prefix = format!("{}.", file_name(path)?);
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(directory(path))? {
+        let entry = entry?;
+        let is_backup = entry.file_name().to_str().map_or(false, |name| {
+            let len = prefix.len() + TIMESTAMP_LEN + BACKUP_EXTENSION.len();
+            name.starts_with(&prefix) && name.ends_with(BACKUP_EXTENSION)
+                && (name.len() == len || name.len() == len + COUNTER_LEN)
+        });
+        if is_backup {
+            backups.push(entry.path());
+        }
+    }
+
+    // The timestamps and counters sort chronologically.
+    backups.sort();
+    backups.reverse();
+
+    Ok(backups)
+}
+
+/// Saves the Run in use by the Timer provided as a LiveSplit splits file at
+/// the path provided. See `save_run` for how the splits file is saved.
+pub fn save_timer<P: AsRef<Path>>(timer: &Timer, path: P, backup_count: usize) -> Result<()> {
+    let run;
+    let run = if timer.current_phase() == TimerPhase::NotRunning {
+        timer.run()
+    } else {
+        run = timer.clone().into_run(true);
+        &run
+    };
+    save_run(run, path, backup_count)
+}
+
+/// Saves a Run as a LiveSplit splits file at the path provided. The splits
+/// file is written to a temporary file, which is synchronized to the disk
+/// before it replaces the splits file. That way the splits file is always
+/// either in its old or its new state, even if the process dies while saving.
+/// Before replacing it, the old splits file is copied to a timestamped backup
+/// and only the newest `backup_count` backups are kept. A `backup_count` of 0
+/// neither creates new backups nor removes existing ones. Use `save_timer` if
+/// the Run is in use by a timer in order to properly save the current attempt
+/// as well.
+pub fn save_run<P: AsRef<Path>>(run: &Run, path: P, backup_count: usize) -> Result<()> {
+    let path = path.as_ref();
+    let name = file_name(path)?;
+    let directory = directory(path);
+    let temp_path = directory.join(format!("{}.tmp", name));
+
+    let temp_file = File::create(&temp_path)?;
+    {
+        let mut writer = BufWriter::new(&temp_file);
+        livesplit::save_run(run, &mut writer)?;
+        writer.flush()?;
+    }
+    temp_file.sync_all()?;
+    drop(temp_file);
+
+    if backup_count > 0 && path.exists() {
+        let timestamp = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+        let mut counter = 0;
+        let backup_path = loop {
+            let backup_path = directory.join(format!(
+                "{}.{}-{:03}{}",
+                name, timestamp, counter, BACKUP_EXTENSION
+            ));
+            if !backup_path.exists() {
+                break backup_path;
+            }
+            if counter == 999 {
+                let _ = fs::remove_file(&temp_path);
+                return Err(Error::TooManyBackups);
+            }
+            counter += 1;
+        };
+        fs::copy(path, &backup_path)?;
+        OpenOptions::new()
+            .write(true)
+            .open(&backup_path)?
+            .sync_all()?;
+    }
+
+    fs::rename(&temp_path, path)?;
+    sync_directory(directory)?;
+
+    if backup_count > 0 {
+        for old_backup in backups(path)?.into_iter().skip(backup_count) {
+            fs::remove_file(old_backup)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse(path: &Path, original_path: &Path) -> StdResult<Run, Option<parser::Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Err(None),
+    };
+    parser::parse(BufReader::new(file), Some(original_path.to_owned())).map_err(Some)
+}
+
+/// Loads a LiveSplit splits file from the path provided. If the splits file
+/// is missing or can't be parsed, its backups are tried from the newest to the
+/// oldest, and the first one that can be parsed is loaded instead. The path of
+/// the Run is always set to the path of the splits file, so saving the Run
+/// again replaces the broken splits file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<LoadedRun> {
+    let path = path.as_ref();
+
+    let error = match parse(path, path) {
+        Ok(run) => {
+            return Ok(LoadedRun {
+                run,
+                recovered_from: None,
+            })
+        }
+        Err(error) => error,
+    };
+
+    for backup in backups(path)? {
+        if let Ok(run) = parse(&backup, path) {
+            return Ok(LoadedRun {
+                run,
+                recovered_from: Some(backup),
+            });
+        }
+    }
+
+    Err(Error::NoUsableFile(error))
+}
@@ -30,6 +30,8 @@ use std::fmt::Display;
 use std::borrow::Cow;
 use std::mem::replace;
 use {Image, Run, Time, TimeSpan, Timer, TimerPhase, base64};
+use comparison::Parameters;
+use comparison::recent_attempts::Window;
 use time::formatter::{Complete, TimeFormatter};
 use chrono::{DateTime, Utc};
 use byteorder::{WriteBytesExt, LE};
@@ -54,6 +56,90 @@ quick_error! {
 /// The Result type for the LiveSplit Saver.
 pub type Result<T> = StdResult<T, Error>;
 
+/// The versions of the LiveSplit splits file format that the saver is able to
+/// write. Older versions can be targeted for compatibility with old LiveSplit
+/// builds and third-party tools, at the cost of not being able to represent
+/// everything stored in a Run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Version {
+    /// The original format. Only the real time of the Personal Best is stored
+    /// for each segment.
+    V1_0,
+    /// Stores the split times of custom comparisons.
+    V1_3,
+    /// Stores game times next to the real times.
+    V1_4_1,
+    /// Replaces the run history with an attempt history that stores when each
+    /// attempt started and ended.
+    V1_5,
+    /// Stores metadata about the run, the auto splitter settings and whether
+    /// the attempt timestamps are synchronized with an atomic clock.
+    V1_6,
+    /// Stores the pause time of each attempt and the Comparison Generators that
+    /// are stored in splits files.
+    V1_7,
+}
+
+impl Version {
+    /// The newest version of the format, which is able to represent everything
+    /// stored in a Run.
+    pub fn latest() -> Self {
+        Version::V1_7
+    }
+
+    fn as_bytes(&self) -> &'static [u8] {
+        match *self {
+            Version::V1_0 => b"1.0.0",
+            Version::V1_3 => b"1.3.0",
+            Version::V1_4_1 => b"1.4.1",
+            Version::V1_5 => b"1.5.0",
+            Version::V1_6 => b"1.6.0",
+            Version::V1_7 => b"1.7.0",
+        }
+    }
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Version::latest()
+    }
+}
+
+/// Describes data stored in a Run that can't be represented by the version of
+/// the LiveSplit splits file format that has been targeted and thus didn't get
+/// saved.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LostData {
+    /// The split times of comparisons other than the Personal Best.
+    CustomComparisons,
+    /// Game times of any of the times stored.
+    GameTimes,
+    /// When the attempts started and ended.
+    AttemptTimestamps,
+    /// The metadata of the Run, like the platform and region.
+    Metadata,
+    /// The settings of the auto splitter.
+    AutoSplitterSettings,
+    /// Whether the attempt timestamps are synchronized with an atomic clock.
+    AtomicClockSync,
+    /// How long the attempts have been paused for.
+    PauseTimes,
+    /// The notes and tags of the attempts and whether they are invalid.
+    AttemptAnnotations,
+    /// The elements of the attempts that weren't understood when parsing the
+    /// splits file.
+    AttemptUnknownElements,
+    /// The Comparison Generators that are stored in splits files, like the
+    /// Percentile Segments.
+    ComparisonGenerators,
+}
+
+fn lose(lost: &mut Vec<LostData>, data: LostData) {
+    if !lost.contains(&data) {
+        lost.push(data);
+    }
+}
+
 fn new_tag(name: &[u8]) -> BytesStart {
     BytesStart::borrowed(name, name.len())
 }
@@ -119,10 +205,10 @@ where
     })
 }
 
-fn text<W: Write, T: AsRef<[u8]>>(writer: &mut Writer<W>, tag: BytesStart, text: T) -> Result<()> {
+fn text<W: Write, T: AsRef<str>>(writer: &mut Writer<W>, tag: BytesStart, text: T) -> Result<()> {
     let text = text.as_ref();
     scoped(writer, tag, text.is_empty(), |writer| {
-        writer.write_event(Event::Text(BytesText::borrowed(text)))?;
+        writer.write_event(Event::Text(BytesText::from_str(text)))?;
         Ok(())
     })
 }
@@ -146,28 +232,24 @@ fn image<W: Write>(
     buf: &mut Vec<u8>,
     image_buf: &mut Cow<[u8]>,
 ) -> Result<()> {
-    let url = image.url();
-    if url.starts_with("data:;base64,") {
-        let src = &url["data:;base64,".len()..];
+    let data = image.data();
+    if !data.is_empty() {
+        let len = data.len();
+        let image_buf = image_buf.to_mut();
+        image_buf.truncate(LSS_IMAGE_HEADER.len());
+        image_buf.reserve(len + 6);
+        image_buf.write_u32::<LE>(len as u32).unwrap();
+        image_buf.push(0x2);
+        image_buf.extend_from_slice(data);
+        image_buf.push(0xB);
         buf.clear();
-        if base64::decode_config_buf(src, base64::STANDARD, buf).is_ok() {
-            let len = buf.len();
-            let image_buf = image_buf.to_mut();
-            image_buf.truncate(LSS_IMAGE_HEADER.len());
-            image_buf.reserve(len + 6);
-            image_buf.write_u32::<LE>(len as u32).unwrap();
-            image_buf.push(0x2);
-            image_buf.append(buf);
-            image_buf.push(0xB);
-            buf.clear();
-            vec_as_string(buf, |s| {
-                base64::encode_config_buf(image_buf, base64::STANDARD, s)
-            });
-            return scoped(writer, tag, buf.is_empty(), |writer| {
-                writer.write_event(Event::CData(BytesText::borrowed(buf)))?;
-                Ok(())
-            });
-        }
+        vec_as_string(buf, |s| {
+            base64::encode_config_buf(image_buf, base64::STANDARD, s)
+        });
+        return scoped(writer, tag, buf.is_empty(), |writer| {
+            writer.write_event(Event::CData(BytesText::borrowed(buf)))?;
+            Ok(())
+        });
     }
     writer.write_event(Event::Empty(tag))?;
     Ok(())
@@ -189,7 +271,7 @@ fn write_display<W: Write, D: Display>(
     value: D,
     buf: &mut Vec<u8>,
 ) -> Result<()> {
-    text(writer, tag, fmt_buf(value, buf))
+    text(writer, tag, String::from_utf8_lossy(fmt_buf(value, buf)))
 }
 
 fn time_span<W: Write>(
@@ -227,6 +309,30 @@ fn time<W: Write>(
     )
 }
 
+fn versioned_time<W: Write>(
+    writer: &mut Writer<W>,
+    tag: BytesStart,
+    time: Time,
+    version: Version,
+    lost: &mut Vec<LostData>,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    if version >= Version::V1_4_1 {
+        return self::time(writer, tag, time, buf);
+    }
+
+    // Older versions only store the real time as the text of the element.
+    if time.game_time.is_some() {
+        lose(lost, LostData::GameTimes);
+    }
+    if let Some(real_time) = time.real_time {
+        time_span(writer, tag, real_time, buf)
+    } else {
+        writer.write_event(Event::Empty(tag))?;
+        Ok(())
+    }
+}
+
 /// Saves the Run in use by the Timer provided as a LiveSplit splits file
 /// (*.lss).
 pub fn save_timer<W: Write>(timer: &Timer, writer: W) -> Result<()> {
@@ -244,16 +350,26 @@ pub fn save_timer<W: Write>(timer: &Timer, writer: W) -> Result<()> {
 /// function if the Run is in use by a timer in order to properly save the
 /// current attempt as well.
 pub fn save_run<W: Write>(run: &Run, writer: W) -> Result<()> {
+    save_run_as(run, writer, Version::latest())?;
+    Ok(())
+}
+
+/// Saves a Run as a LiveSplit splits file (*.lss) of the version provided.
+/// Targeting an older version makes the splits file usable with old LiveSplit
+/// builds and third-party tools that don't understand newer versions. All the
+/// kinds of data stored in the Run that can't be represented by that version
+/// are returned. Nothing is lost when targeting the latest version.
+pub fn save_run_as<W: Write>(run: &Run, writer: W, version: Version) -> Result<Vec<LostData>> {
     let writer = &mut Writer::new(writer);
 
     let buf = &mut Vec::new();
     let image_buf = &mut Cow::Borrowed(&LSS_IMAGE_HEADER[..]);
+    let mut lost = Vec::new();
 
     writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
-    writer.write_event(Event::Start(BytesStart::borrowed(
-        br#"Run version="1.7.0""#,
-        3,
-    )))?;
+    let mut tag = new_tag(b"Run");
+    tag.push_attribute((&b"version"[..], version.as_bytes()));
+    write_start(writer, tag)?;
 
     image(
         writer,
@@ -265,69 +381,143 @@ pub fn save_run<W: Write>(run: &Run, writer: W) -> Result<()> {
     text(writer, new_tag(b"GameName"), run.game_name())?;
     text(writer, new_tag(b"CategoryName"), run.category_name())?;
 
-    write_start(writer, new_tag(b"Metadata"))?;
     let metadata = run.metadata();
+    if version >= Version::V1_6 {
+        write_start(writer, new_tag(b"Metadata"))?;
 
-    let mut tag = new_tag(b"Run");
-    tag.push_attribute((&b"id"[..], metadata.run_id().as_bytes()));
-    writer.write_event(Event::Empty(tag))?;
-
-    tag = new_tag(b"Platform");
-    tag.push_attribute((&b"usesEmulator"[..], bool(metadata.uses_emulator())));
-    text(writer, tag, metadata.platform_name())?;
-
-    text(writer, new_tag(b"Region"), metadata.region_name())?;
+        let mut tag = new_tag(b"Run");
+        tag.push_attribute(("id", metadata.run_id()));
+        writer.write_event(Event::Empty(tag))?;
 
-    scoped_iter(
-        writer,
-        new_tag(b"Variables"),
-        metadata.variables(),
-        |writer, (name, value)| {
-            let mut tag = new_tag(b"Variable");
-            tag.push_attribute((&b"name"[..], name.as_bytes()));
-            text(writer, tag, value)
-        },
-    )?;
-    write_end(writer, b"Metadata")?;
+        tag = new_tag(b"Platform");
+        tag.push_attribute((&b"usesEmulator"[..], bool(metadata.uses_emulator())));
+        text(writer, tag, metadata.platform_name())?;
+
+        text(writer, new_tag(b"Region"), metadata.region_name())?;
+
+        scoped_iter(
+            writer,
+            new_tag(b"Variables"),
+            metadata.variables(),
+            |writer, (name, value)| {
+                let mut tag = new_tag(b"Variable");
+                tag.push_attribute(("name", name.as_str()));
+                text(writer, tag, value)
+            },
+        )?;
+        write_end(writer, b"Metadata")?;
+    } else if !metadata.run_id().is_empty() || !metadata.platform_name().is_empty()
+        || metadata.uses_emulator() || !metadata.region_name().is_empty()
+        || metadata.variables().next().is_some()
+    {
+        lose(&mut lost, LostData::Metadata);
+    }
 
     time_span(writer, new_tag(b"Offset"), run.offset(), buf)?;
     write_display(writer, new_tag(b"AttemptCount"), run.attempt_count(), buf)?;
 
-    scoped_iter(
-        writer,
-        new_tag(b"AttemptHistory"),
-        run.attempt_history(),
-        |writer, attempt| {
-            let mut tag = new_tag(b"Attempt");
-            tag.push_attribute((&b"id"[..], fmt_buf(attempt.index(), buf)));
-
-            if let Some(started) = attempt.started() {
-                tag.push_attribute((&b"started"[..], fmt_date(started.time, buf)));
-                tag.push_attribute((
-                    &b"isStartedSynced"[..],
-                    bool(started.synced_with_atomic_clock),
-                ));
-            }
-
-            if let Some(ended) = attempt.ended() {
-                tag.push_attribute((&b"ended"[..], fmt_date(ended.time, buf)));
-                tag.push_attribute((&b"isEndedSynced"[..], bool(ended.synced_with_atomic_clock)));
-            }
-
-            let is_empty = attempt.time().real_time.is_none() && attempt.time().game_time.is_none()
-                && attempt.pause_time().is_none();
+    if version >= Version::V1_5 {
+        scoped_iter(
+            writer,
+            new_tag(b"AttemptHistory"),
+            run.attempt_history(),
+            |writer, attempt| {
+                let mut tag = new_tag(b"Attempt");
+                tag.push_attribute((&b"id"[..], fmt_buf(attempt.index(), buf)));
+
+                if let Some(started) = attempt.started() {
+                    tag.push_attribute((&b"started"[..], fmt_date(started.time, buf)));
+                    if version >= Version::V1_6 {
+                        tag.push_attribute((
+                            &b"isStartedSynced"[..],
+                            bool(started.synced_with_atomic_clock),
+                        ));
+                    } else if started.synced_with_atomic_clock {
+                        lose(&mut lost, LostData::AtomicClockSync);
+                    }
+                }
 
-            scoped(writer, tag, is_empty, |writer| {
-                time_inner(writer, attempt.time(), buf)?;
+                if let Some(ended) = attempt.ended() {
+                    tag.push_attribute((&b"ended"[..], fmt_date(ended.time, buf)));
+                    if version >= Version::V1_6 {
+                        tag.push_attribute((
+                            &b"isEndedSynced"[..],
+                            bool(ended.synced_with_atomic_clock),
+                        ));
+                    } else if ended.synced_with_atomic_clock {
+                        lose(&mut lost, LostData::AtomicClockSync);
+                    }
+                }
 
-                if let Some(pause_time) = attempt.pause_time() {
-                    time_span(writer, new_tag(b"PauseTime"), pause_time, buf)?;
+                if attempt.is_invalid() {
+                    tag.push_attribute((&b"isInvalid"[..], bool(true)));
                 }
 
-                Ok(())
-            })
-        },
-    )?;
+                let pause_time = if version >= Version::V1_7 {
+                    attempt.pause_time()
+                } else {
+                    if attempt.pause_time().is_some() {
+                        lose(&mut lost, LostData::PauseTimes);
+                    }
+                    None
+                };
+
+                let is_empty = attempt.time().real_time.is_none()
+                    && attempt.time().game_time.is_none()
+                    && pause_time.is_none()
+                    && attempt.note().is_empty()
+                    && attempt.tags().is_empty()
+                    && attempt.unknown_elements().is_empty();
+
+                scoped(writer, tag, is_empty, |writer| {
+                    time_inner(writer, attempt.time(), buf)?;
+
+                    if let Some(pause_time) = pause_time {
+                        time_span(writer, new_tag(b"PauseTime"), pause_time, buf)?;
+                    }
+
+                    if !attempt.note().is_empty() {
+                        text(writer, new_tag(b"Note"), attempt.note())?;
+                    }
+
+                    if !attempt.tags().is_empty() {
+                        scoped_iter(writer, new_tag(b"Tags"), attempt.tags(), |writer, name| {
+                            text(writer, new_tag(b"Tag"), name)
+                        })?;
+                    }
+
+                    writer.write(attempt.unknown_elements())?;
+
+                    Ok(())
+                })
+            },
+        )?;
+    } else {
+        scoped_iter(
+            writer,
+            new_tag(b"RunHistory"),
+            run.attempt_history(),
+            |writer, attempt| {
+                if attempt.started().is_some() || attempt.ended().is_some() {
+                    lose(&mut lost, LostData::AttemptTimestamps);
+                }
+                if attempt.pause_time().is_some() {
+                    lose(&mut lost, LostData::PauseTimes);
+                }
+                if attempt.is_invalid() || !attempt.note().is_empty()
+                    || !attempt.tags().is_empty()
+                {
+                    lose(&mut lost, LostData::AttemptAnnotations);
+                }
+                if !attempt.unknown_elements().is_empty() {
+                    lose(&mut lost, LostData::AttemptUnknownElements);
+                }
+                let mut tag = new_tag(b"Time");
+                tag.push_attribute((&b"id"[..], fmt_buf(attempt.index(), buf)));
+                versioned_time(writer, tag, attempt.time(), version, &mut lost, buf)
+            },
+        )?;
+    }
 
     scoped_iter(
         writer,
@@ -339,21 +529,38 @@ pub fn save_run<W: Write>(run: &Run, writer: W) -> Result<()> {
             text(writer, new_tag(b"Name"), segment.name())?;
             image(writer, new_tag(b"Icon"), segment.icon(), buf, image_buf)?;
 
-            scoped_iter(
-                writer,
-                new_tag(b"SplitTimes"),
-                run.custom_comparisons(),
-                |writer, comparison| {
-                    let mut tag = new_tag(b"SplitTime");
-                    tag.push_attribute((&b"name"[..], comparison.as_bytes()));
-                    time(writer, tag, segment.comparison(comparison), buf)
-                },
-            )?;
+            if version >= Version::V1_3 {
+                scoped_iter(
+                    writer,
+                    new_tag(b"SplitTimes"),
+                    run.custom_comparisons(),
+                    |writer, comparison| {
+                        let mut tag = new_tag(b"SplitTime");
+                        tag.push_attribute(("name", comparison.as_str()));
+                        let time = segment.comparison(comparison);
+                        versioned_time(writer, tag, time, version, &mut lost, buf)
+                    },
+                )?;
+            } else {
+                if run.custom_comparisons().len() > 1 {
+                    lose(&mut lost, LostData::CustomComparisons);
+                }
+                versioned_time(
+                    writer,
+                    new_tag(b"PersonalBestSplitTime"),
+                    segment.personal_best_split_time(),
+                    version,
+                    &mut lost,
+                    buf,
+                )?;
+            }
 
-            time(
+            versioned_time(
                 writer,
                 new_tag(b"BestSegmentTime"),
                 segment.best_segment_time(),
+                version,
+                &mut lost,
                 buf,
             )?;
 
@@ -364,24 +571,77 @@ pub fn save_run<W: Write>(run: &Run, writer: W) -> Result<()> {
                 |writer, &(index, history_time)| {
                     let mut tag = new_tag(b"Time");
                     tag.push_attribute((&b"id"[..], fmt_buf(index, buf)));
-                    time(writer, tag, history_time, buf)
+                    versioned_time(writer, tag, history_time, version, &mut lost, buf)
                 },
             )?;
 
+            writer.write(segment.unknown_elements())?;
+
             write_end(writer, b"Segment")
         },
     )?;
 
-    scoped(
-        writer,
-        new_tag(b"AutoSplitterSettings"),
-        run.auto_splitter_settings().is_empty(),
-        |writer| {
-            writer.write(run.auto_splitter_settings())?;
-            Ok(())
-        },
-    )?;
+    let stored_generators = run.comparison_generators()
+        .iter()
+        .filter_map(|g| g.parameters())
+        .collect::<Vec<_>>();
+    if !stored_generators.is_empty() && version < Version::V1_7 {
+        lose(&mut lost, LostData::ComparisonGenerators);
+    } else if !stored_generators.is_empty() {
+        scoped_iter(
+            writer,
+            new_tag(b"ComparisonGenerators"),
+            stored_generators,
+            |writer, parameters| {
+                let tag = match parameters {
+                    Parameters::PercentileSegments { percentile } => {
+                        let mut tag = new_tag(b"PercentileSegments");
+                        tag.push_attribute((&b"percentile"[..], fmt_buf(percentile, buf)));
+                        tag
+                    }
+                    Parameters::GoalTime { goal } => {
+                        let mut tag = new_tag(b"GoalTime");
+                        tag.push_attribute((&b"goal"[..], fmt_buf(Complete.format(goal), buf)));
+                        tag
+                    }
+                    Parameters::RecentAttempts { kind, window } => {
+                        let mut tag = new_tag(b"RecentAttempts");
+                        tag.push_attribute((&b"comparison"[..], kind.name().as_bytes()));
+                        match window {
+                            Window::Attempts(count) => {
+                                tag.push_attribute((&b"attempts"[..], fmt_buf(count, buf)))
+                            }
+                            Window::Days(count) => {
+                                tag.push_attribute((&b"days"[..], fmt_buf(count, buf)))
+                            }
+                        }
+                        tag
+                    }
+                };
+                writer.write_event(Event::Empty(tag))?;
+                Ok(())
+            },
+        )?;
+    }
+
+    if version >= Version::V1_6 {
+        scoped(
+            writer,
+            new_tag(b"AutoSplitterSettings"),
+            run.auto_splitter_settings().is_empty(),
+            |writer| {
+                writer.write(run.auto_splitter_settings())?;
+                Ok(())
+            },
+        )?;
+    } else if !run.auto_splitter_settings().is_empty() {
+        lose(&mut lost, LostData::AutoSplitterSettings);
+    }
+
+    writer.write(run.unknown_elements())?;
 
     write_end(writer, b"Run")?;
-    Ok(())
+
+    lost.sort();
+    Ok(lost)
 }
@@ -25,4 +25,6 @@
 //! livesplit::save_run(&run, writer).expect("Couldn't save the splits file");
 //! ```
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod file;
 pub mod livesplit;
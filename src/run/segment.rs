@@ -24,6 +24,7 @@ pub struct Segment {
     split_time: Time,
     segment_history: SegmentHistory,
     comparisons: HashMap<String, Time>,
+    unknown_elements: Vec<u8>,
 }
 
 impl Segment {
@@ -183,4 +184,26 @@ impl Segment {
     pub fn segment_history_mut(&mut self) -> &mut SegmentHistory {
         &mut self.segment_history
     }
+
+    /// Accesses the XML elements of the segment that weren't understood when
+    /// parsing it. They are kept as they were, so they can be written back
+    /// when saving the segment again. Just like the unknown elements of the
+    /// Run, they are written back after all the elements of the segment that
+    /// are understood.
+    #[inline]
+    pub fn unknown_elements(&self) -> &[u8] {
+        &self.unknown_elements
+    }
+
+    /// Grants mutable access to the XML elements of the segment that weren't
+    /// understood when parsing it.
+    ///
+    /// # Warning
+    ///
+    /// You need to ensure that the elements are encoded as data that would be
+    /// valid as an interior of an XML element.
+    #[inline]
+    pub fn unknown_elements_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.unknown_elements
+    }
 }
@@ -1,6 +1,6 @@
 use {AtomicDateTime, Run, Segment, Time, TimeSpan, TimeStamp, TimerPhase, TimingMethod};
 use TimerPhase::*;
-use comparison::personal_best;
+use comparison::{personal_best, ComparisonGenerator, PastAttempt};
 use parking_lot::RwLock;
 use std::sync::Arc;
 use std::mem;
@@ -72,6 +72,18 @@ quick_error! {
     }
 }
 
+quick_error! {
+    /// The Error type for comparing against a past attempt.
+    #[derive(Debug)]
+    pub enum AttemptComparisonError {
+        /// There's no attempt with the index provided in the Attempt History.
+        UnknownAttempt {}
+        /// A custom comparison already has the name of the attempt's
+        /// comparison.
+        NameInUse {}
+    }
+}
+
 impl Timer {
     /// Creates a new Timer based on a Run object storing all the information
     /// about the splits. The Run object needs to have at least one segment, so
@@ -444,6 +456,63 @@ impl Timer {
         // TODO OnPreviousComparison
     }
 
+    /// Switches the current comparison to a comparison generated from the
+    /// attempt with the index provided. The comparison reconstructs the splits
+    /// of the attempt from the Segment History, so any past attempt can be
+    /// compared against, like the second best run or the best run of
+    /// yesterday. Only a single past attempt can be compared against at a
+    /// time, so the comparison of the attempt that was previously compared
+    /// against is removed. The comparison is not stored in the splits file.
+    /// If there's no attempt with the index provided in the Attempt History or
+    /// a custom comparison already has the name of the attempt's comparison,
+    /// the current comparison stays the same.
+    pub fn compare_against_attempt(&mut self, index: i32) -> Result<(), AttemptComparisonError> {
+        if !self.run.attempt_history().iter().any(|a| a.index() == index) {
+            return Err(AttemptComparisonError::UnknownAttempt);
+        }
+
+        let generator = PastAttempt::new(index);
+        if self.run
+            .custom_comparisons()
+            .iter()
+            .any(|c| c == generator.name())
+        {
+            return Err(AttemptComparisonError::NameInUse);
+        }
+
+        self.remove_attempt_comparison();
+        self.current_comparison = generator.name().to_owned();
+        self.run.comparison_generators_mut().push(Box::new(generator));
+        self.run.regenerate_comparisons();
+
+        Ok(())
+    }
+
+    /// Removes the comparison of the past attempt that is being compared
+    /// against, if there is one. If it is the current comparison, the Personal
+    /// Best becomes the current comparison.
+    pub fn clear_attempt_comparison(&mut self) {
+        self.remove_attempt_comparison();
+        if !self.run.comparisons().any(|c| c == self.current_comparison) {
+            self.current_comparison = personal_best::NAME.to_string();
+        }
+    }
+
+    fn remove_attempt_comparison(&mut self) {
+        let generator = {
+            let generators = self.run.comparison_generators_mut();
+            let index = generators
+                .iter()
+                .position(|g| PastAttempt::from_name(g.name()).is_some());
+            index.map(|i| generators.remove(i))
+        };
+        if let Some(generator) = generator {
+            for segment in self.run.segments_mut() {
+                segment.comparisons_mut().remove(generator.name());
+            }
+        }
+    }
+
     /// Returns the total duration of the current attempt. This is not affected
     /// by the start offset of the run. So if the start offset is -10s and the
     /// `start()` method was called 2s ago, the current time is -8s but the
@@ -505,3 +505,85 @@ fn clears_run_id_when_pbing() {
 
     assert_eq!(timer.run().metadata().run_id(), "");
 }
+
+#[test]
+fn compare_against_attempt() {
+    let s = TimeSpan::from_seconds;
+    let mut timer = Timer::new(run()).unwrap();
+
+    for splits in &[[1.0, 2.0, 3.0], [2.0, 4.0, 6.0]] {
+        timer.start();
+        timer.initialize_game_time();
+        timer.pause_game_time();
+        for &split in splits {
+            timer.set_game_time(s(split));
+            timer.split();
+        }
+        timer.reset(true);
+    }
+
+    timer.compare_against_attempt(1).unwrap();
+    assert_eq!(timer.current_comparison(), "Attempt #1");
+    assert_eq!(
+        timer.run().segment(1).comparison("Attempt #1").game_time,
+        Some(s(2.0))
+    );
+
+    timer.compare_against_attempt(2).unwrap();
+    assert_eq!(timer.current_comparison(), "Attempt #2");
+    assert_eq!(
+        timer.run().segment(2).comparison("Attempt #2").game_time,
+        Some(s(6.0))
+    );
+    assert!(!timer.run().comparisons().any(|c| c == "Attempt #1"));
+
+    assert!(timer.compare_against_attempt(3).is_err());
+    assert_eq!(timer.current_comparison(), "Attempt #2");
+}
+
+#[test]
+fn clear_attempt_comparison() {
+    let mut timer = Timer::new(run()).unwrap();
+    timer.start();
+    timer.split();
+    timer.reset(true);
+
+    timer.compare_against_attempt(1).unwrap();
+    timer.clear_attempt_comparison();
+    assert_eq!(timer.current_comparison(), "Personal Best");
+    assert!(!timer.run().comparisons().any(|c| c == "Attempt #1"));
+    assert!(
+        timer
+            .run()
+            .segments()
+            .iter()
+            .all(|s| s.comparison("Attempt #1").real_time.is_none())
+    );
+}
+
+#[test]
+fn compare_against_attempt_with_custom_comparison_of_the_same_name() {
+    let mut run = run();
+    run.add_custom_comparison("Attempt #1").unwrap();
+    let mut timer = Timer::new(run).unwrap();
+    timer.start();
+    timer.split();
+    timer.reset(true);
+
+    assert!(timer.compare_against_attempt(1).is_err());
+    assert_eq!(timer.current_comparison(), "Personal Best");
+    assert_eq!(
+        timer
+            .run()
+            .comparison_generators()
+            .iter()
+            .filter(|g| g.name() == "Attempt #1")
+            .count(),
+        0
+    );
+
+    timer.switch_to_next_comparison();
+    assert_eq!(timer.current_comparison(), "Attempt #1");
+    timer.clear_attempt_comparison();
+    assert_eq!(timer.current_comparison(), "Attempt #1");
+}
@@ -1,12 +1,19 @@
 extern crate livesplit_core;
 
 mod parse {
+    use std::{env, fs};
     use std::fs::File;
-    use std::io::{BufReader, Cursor};
-    use livesplit_core::Run;
+    use std::io::{BufReader, Cursor, Read, Write};
+    use livesplit_core::{ImageFormat, Run, Segment, TimeSpan};
+    use livesplit_core::comparison::{ComparisonGenerator, GoalTime, PercentileSegments,
+                                     RecentAttempts};
+    use livesplit_core::comparison::recent_attempts::{Kind, Window};
     use livesplit_core::run::parser::{composite, livesplit, llanfair, llanfair_gered,
                                       source_live_timer, splitterz, time_split_tracker, urn,
-                                      wsplit, TimerKind, llanfair2};
+                                      wsplit, LimitError, ParseLimits, TimerKind, llanfair2};
+    use livesplit_core::run::parser::composite::Confidence;
+    use livesplit_core::run::saver::livesplit as saver;
+    use livesplit_core::run::saver::file;
 
     fn file(path: &str) -> BufReader<File> {
         BufReader::new(File::open(path).unwrap())
@@ -61,6 +68,252 @@ mod parse {
         livesplit("tests/run_files/livesplit1.6_gametime.lss");
     }
 
+    const CORRUPTED_LSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Run version="1.7.0">
+  <GameIcon />
+  <GameName>Game</GameName>
+  <CategoryName>Any%</CategoryName>
+  <Offset>00:00:00</Offset>
+  <AttemptCount>2</AttemptCount>
+  <AttemptHistory>
+    <Attempt id="1">
+      <RealTime>00:01:00</RealTime>
+    </Attempt>
+    <Attempt id="2">
+      <RealTime>garbage</RealTime>
+    </Attempt>
+  </AttemptHistory>
+  <Segments>
+    <Segment>
+      <Name>Only Segment</Name>
+      <Icon />
+      <SplitTimes>
+        <SplitTime name="Personal Best">
+          <RealTime>00:01:00</RealTime>
+        </SplitTime>
+      </SplitTimes>
+      <BestSegmentTime>
+        <RealTime>00:01:00</RealTime>
+      </BestSegmentTime>
+      <SegmentHistory>
+        <Time id="1">
+          <RealTime>00:01:00</RealTime>
+        </Time>
+        <Time id="2">
+          <RealTime>not a time</RealTime>
+        </Time>
+      </SegmentHistory>
+    </Segment>
+  </Segments>
+  <AutoSplitterSettings />
+</Run>"#;
+
+    #[test]
+    fn livesplit_error_location() {
+        let error = livesplit::parse(Cursor::new(CORRUPTED_LSS), None).unwrap_err();
+        assert_eq!(error.path, "Run/AttemptHistory/Attempt[id=2]");
+        assert!(error.position > CORRUPTED_LSS.find("garbage").unwrap());
+    }
+
+    #[test]
+    fn livesplit_lenient() {
+        let parsed = livesplit::parse_lenient(Cursor::new(CORRUPTED_LSS), None).unwrap();
+        let run = parsed.run;
+
+        assert_eq!(run.attempt_history().len(), 1);
+        assert_eq!(run.attempt_history()[0].index(), 1);
+        assert!(run.segment(0).segment_history().get(1).is_some());
+        assert!(run.segment(0).segment_history().get(2).is_none());
+
+        assert_eq!(parsed.warnings.len(), 2);
+        assert_eq!(parsed.warnings[0].path, "Run/AttemptHistory/Attempt[id=2]");
+        assert_eq!(
+            parsed.warnings[1].path,
+            "Run/Segments/Segment[1]/SegmentHistory/Time[id=2]"
+        );
+    }
+
+    const FUTURE_LSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Run version="1.8.0">
+  <GameIcon />
+  <GameName>Game</GameName>
+  <CategoryName>Any%</CategoryName>
+  <Offset>00:00:00</Offset>
+  <AttemptCount>1</AttemptCount>
+  <AttemptHistory>
+    <Attempt id="1">
+      <RealTime>00:01:00</RealTime>
+      <Notes lang="en">Good run</Notes>
+    </Attempt>
+  </AttemptHistory>
+  <Segments>
+    <Segment>
+      <Name>Only Segment</Name>
+      <Icon />
+      <SplitTimes />
+      <BestSegmentTime />
+      <SegmentHistory />
+      <Color>#FF0000</Color>
+    </Segment>
+  </Segments>
+  <AutoSplitterSettings />
+  <LayoutPath>default.lsl</LayoutPath>
+</Run>"#;
+
+    #[test]
+    fn livesplit_keeps_unknown_elements() {
+        let run = livesplit::parse(Cursor::new(FUTURE_LSS), None).unwrap();
+
+        assert_eq!(run.unknown_elements(), &b"<LayoutPath>default.lsl</LayoutPath>"[..]);
+        assert_eq!(run.segment(0).unknown_elements(), &b"<Color>#FF0000</Color>"[..]);
+        assert_eq!(
+            run.attempt_history()[0].unknown_elements(),
+            &br#"<Notes lang="en">Good run</Notes>"#[..]
+        );
+
+        let mut saved = Vec::new();
+        saver::save_run(&run, &mut saved).unwrap();
+        let reparsed = livesplit::parse(Cursor::new(&saved), None).unwrap();
+
+        assert_eq!(reparsed.unknown_elements(), run.unknown_elements());
+        assert_eq!(
+            reparsed.segment(0).unknown_elements(),
+            run.segment(0).unknown_elements()
+        );
+        assert_eq!(reparsed.attempt_history(), run.attempt_history());
+
+        saved.clear();
+        let lost = saver::save_run_as(&run, &mut saved, saver::Version::V1_4_1).unwrap();
+        assert!(lost.contains(&saver::LostData::AttemptUnknownElements));
+    }
+
+    #[test]
+    fn livesplit_keeps_entities_in_unknown_elements() {
+        let lss = FUTURE_LSS.replace("default.lsl", "Tom &amp; Jerry &lt;3.lsl");
+        let run = livesplit::parse(Cursor::new(lss), None).unwrap();
+
+        assert_eq!(
+            run.unknown_elements(),
+            &b"<LayoutPath>Tom &amp; Jerry &lt;3.lsl</LayoutPath>"[..]
+        );
+
+        let mut saved = Vec::new();
+        saver::save_run(&run, &mut saved).unwrap();
+        let reparsed = livesplit::parse(Cursor::new(&saved), None).unwrap();
+
+        assert_eq!(reparsed.unknown_elements(), run.unknown_elements());
+    }
+
+    #[test]
+    fn livesplit_keeps_comparison_generators() {
+        let generators: Vec<Box<ComparisonGenerator>> = vec![
+            Box::new(PercentileSegments::new(25)),
+            Box::new(GoalTime::new(TimeSpan::from_seconds(5400.0))),
+            Box::new(GoalTime::new(TimeSpan::from_seconds(3599.125))),
+            Box::new(RecentAttempts::new(Kind::AverageSegments, Window::Days(14))),
+            Box::new(RecentAttempts::new(Kind::BalancedPB, Window::Attempts(1))),
+        ];
+
+        for generator in generators {
+            let parameters = generator.parameters();
+            assert!(parameters.is_some());
+            let mut run = Run::new();
+            run.push_segment(Segment::new("First"));
+            run.comparison_generators_mut().push(generator);
+
+            let mut saved = Vec::new();
+            saver::save_run(&run, &mut saved).unwrap();
+            let parsed = livesplit::parse(Cursor::new(saved), None).unwrap();
+
+            let count = parsed
+                .comparison_generators()
+                .iter()
+                .filter(|g| g.parameters() == parameters)
+                .count();
+            assert_eq!(count, 1, "{:?}", parameters);
+        }
+    }
+
+    #[test]
+    fn livesplit_older_versions_lose_comparison_generators() {
+        let mut run = Run::new();
+        run.push_segment(Segment::new("First"));
+        run.comparison_generators_mut()
+            .push(Box::new(PercentileSegments::new(25)));
+
+        let mut saved = Vec::new();
+        let lost = saver::save_run_as(&run, &mut saved, saver::Version::V1_6).unwrap();
+        assert_eq!(lost, [saver::LostData::ComparisonGenerators]);
+
+        let parsed = livesplit::parse(Cursor::new(saved), None).unwrap();
+        assert!(
+            parsed
+                .comparison_generators()
+                .iter()
+                .all(|g| g.name() != "25th Percentile Segments")
+        );
+    }
+
+    #[test]
+    fn livesplit_save_older_versions() {
+        let run = livesplit("tests/run_files/livesplit1.6_gametime.lss");
+
+        let mut saved = Vec::new();
+        let lost = saver::save_run_as(&run, &mut saved, saver::Version::latest()).unwrap();
+        assert!(lost.is_empty());
+
+        saved.clear();
+        let lost = saver::save_run_as(&run, &mut saved, saver::Version::V1_0).unwrap();
+        assert!(lost.contains(&saver::LostData::GameTimes));
+        assert!(lost.contains(&saver::LostData::AttemptTimestamps));
+
+        let old = livesplit::parse(Cursor::new(saved), None).unwrap();
+        assert_eq!(old.len(), run.len());
+        assert_eq!(old.attempt_history().len(), run.attempt_history().len());
+        for (old, new) in old.segments().iter().zip(run.segments()) {
+            assert_eq!(
+                old.personal_best_split_time().real_time,
+                new.personal_best_split_time().real_time
+            );
+            assert_eq!(old.personal_best_split_time().game_time, None);
+        }
+    }
+
+    #[test]
+    fn file_keeps_backups_and_recovers() {
+        let directory = env::temp_dir().join("livesplit_core_file_saving");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("splits.lss");
+
+        let mut run = livesplit("tests/run_files/livesplit1.6.lss");
+        for _ in 0..4 {
+            file::save_run(&run, &path, 2).unwrap();
+            run.set_attempt_count(run.attempt_count() + 1);
+        }
+        assert_eq!(file::backups(&path).unwrap().len(), 2);
+
+        // Backups created within the same millisecond don't replace each
+        // other.
+        for _ in 0..3 {
+            file::save_run(&run, &path, 10).unwrap();
+        }
+        assert_eq!(file::backups(&path).unwrap().len(), 5);
+
+        // Saving without backups leaves the existing ones alone.
+        file::save_run(&run, &path, 0).unwrap();
+        assert_eq!(file::backups(&path).unwrap().len(), 5);
+
+        let loaded = file::load(&path).unwrap();
+        assert!(loaded.recovered_from.is_none());
+
+        File::create(&path).unwrap().write_all(b"<Run").unwrap();
+        let loaded = file::load(&path).unwrap();
+        assert_eq!(loaded.recovered_from, Some(file::backups(&path).unwrap()[0].clone()));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
     #[test]
     fn llanfair() {
         llanfair::parse(file("tests/run_files/llanfair")).unwrap();
@@ -91,6 +344,25 @@ mod parse {
         parse_llanfair_gered("tests/run_files/llanfair_gered_icons.lfs");
     }
 
+    #[test]
+    fn llanfair_gered_icons_can_be_downscaled() {
+        let run = llanfair_gered::parse(file("tests/run_files/llanfair_gered_icons.lfs")).unwrap();
+        let mut icon = run.segments()
+            .iter()
+            .map(|s| s.icon().clone())
+            .find(|i| !i.is_empty())
+            .unwrap();
+        assert_eq!(icon.format(), ImageFormat::Png);
+        assert!(icon.url().starts_with("data:image/png;base64,"));
+
+        let (width, height) = icon.dimensions().unwrap();
+        assert!(width > 16 && height > 16);
+        assert!(icon.downscale(16));
+        let (width, height) = icon.dimensions().unwrap();
+        assert!(width <= 16 && height <= 16);
+        assert!(!icon.downscale(16));
+    }
+
     #[test]
     fn llanfair2() {
         parse_llanfair2("tests/run_files/llanfair2.xml")
@@ -151,4 +423,102 @@ mod parse {
         let urn = composite::parse(file("tests/run_files/urn.json"), None, false).unwrap();
         assert_eq!(urn.kind, TimerKind::Urn);
     }
+
+    #[test]
+    fn detect_most_plausible_format() {
+        let files = [
+            ("tests/run_files/livesplit1.6.lss", TimerKind::LiveSplit),
+            ("tests/run_files/llanfair", TimerKind::Llanfair),
+            ("tests/run_files/llanfair2.xml", TimerKind::Llanfair2),
+            ("tests/run_files/llanfair_gered.lfs", TimerKind::LlanfairGered),
+            ("tests/run_files/source_live_timer.json", TimerKind::SourceLiveTimer),
+            ("tests/run_files/splitterz", TimerKind::SplitterZ),
+            ("tests/run_files/timesplittracker.txt", TimerKind::TimeSplitTracker),
+            ("tests/run_files/urn.json", TimerKind::Urn),
+            ("tests/run_files/wsplit", TimerKind::WSplit),
+        ];
+
+        for &(path, kind) in &files {
+            let mut prefix = Vec::new();
+            file(path).read_to_end(&mut prefix).unwrap();
+            assert_eq!(composite::detect(&prefix)[0].kind, kind);
+            assert_eq!(composite::parse(file(path), None, false).unwrap().kind, kind);
+        }
+    }
+
+    #[test]
+    fn composite_reports_rejections() {
+        let path = "tests/run_files/livesplit_fuzz_crash.lss";
+        match composite::parse(file(path), None, false) {
+            Err(composite::Error::NoParserParsedIt(rejections)) => {
+                assert_eq!(rejections[0].kind, TimerKind::LiveSplit);
+                assert!(rejections.iter().all(|r| r.confidence > Confidence::Unlikely));
+            }
+            _ => panic!("The broken splits file should be rejected by all parsers"),
+        }
+    }
+
+    #[test]
+    fn livesplit_too_many_segments() {
+        let limits = ParseLimits {
+            max_segments: 1,
+            ..ParseLimits::unlimited()
+        };
+        let path = "tests/run_files/livesplit1.6.lss";
+        let error = livesplit::parse_with_limits(file(path), None, &limits).unwrap_err();
+        match error.kind {
+            livesplit::ErrorKind::Limit(LimitError::Segments) => {}
+            _ => panic!("Expected the segment limit to be exceeded"),
+        }
+    }
+
+    #[test]
+    fn livesplit_allocates_too_much() {
+        let limits = ParseLimits {
+            max_allocated_bytes: 1024,
+            ..ParseLimits::unlimited()
+        };
+        let path = "tests/run_files/livesplit1.6.lss";
+        let error = livesplit::parse_with_limits(file(path), None, &limits).unwrap_err();
+        match error.kind {
+            livesplit::ErrorKind::Limit(LimitError::Allocation) => {}
+            _ => panic!("Expected the allocation limit to be exceeded"),
+        }
+
+        livesplit::parse_with_limits(file(path), None, &ParseLimits::untrusted()).unwrap();
+    }
+
+    #[test]
+    fn livesplit_nested_too_deeply() {
+        let nested = format!(
+            "<Run version=\"1.7.0\"><Unknown>{}{}</Unknown></Run>",
+            "<a>".repeat(100),
+            "</a>".repeat(100)
+        );
+        let limits = ParseLimits {
+            max_nesting: 32,
+            ..ParseLimits::unlimited()
+        };
+        let error = livesplit::parse_with_limits(Cursor::new(nested), None, &limits).unwrap_err();
+        match error.kind {
+            livesplit::ErrorKind::Limit(LimitError::Nesting) => {}
+            _ => panic!("Expected the nesting limit to be exceeded"),
+        }
+    }
+
+    #[test]
+    fn composite_stops_at_limits() {
+        let limits = ParseLimits {
+            max_total_bytes: 1024,
+            ..ParseLimits::untrusted()
+        };
+        let path = "tests/run_files/livesplit1.6.lss";
+        match composite::parse_with_limits(file(path), None, false, &limits) {
+            Err(composite::Error::Limit(LimitError::TotalBytes)) => {}
+            _ => panic!("Expected the size limit to be exceeded"),
+        }
+
+        let path = "tests/run_files/wsplit";
+        wsplit::parse_with_limits(file(path), false, &ParseLimits::untrusted()).unwrap();
+    }
 }
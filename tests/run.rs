@@ -1,8 +1,32 @@
 extern crate livesplit_core;
 
 mod run {
-    use livesplit_core::Run;
-    use livesplit_core::run::ComparisonError;
+    use std::thread;
+    use std::io::Cursor;
+    use std::time::Duration;
+    use livesplit_core::{Image, ImageFormat, Run, RunEditor, Segment, TimeSpan, Timer};
+    use livesplit_core::run::{ComparisonError, MergeConflict, MAX_ICON_SIZE};
+    use livesplit_core::run::parser::livesplit as parser;
+    use livesplit_core::run::saver::livesplit as saver;
+
+    fn two_segments(second: &str) -> Run {
+        let mut run = Run::new();
+        run.push_segment(Segment::new("A"));
+        run.push_segment(Segment::new(second));
+        run
+    }
+
+    fn add_attempt(timer: &mut Timer, total: f64) {
+        timer.start();
+        timer.initialize_game_time();
+        timer.pause_game_time();
+        timer.set_game_time(TimeSpan::from_seconds(total / 2.0));
+        timer.split();
+        timer.set_game_time(TimeSpan::from_seconds(total));
+        timer.split();
+        timer.reset(true);
+        thread::sleep(Duration::from_millis(10));
+    }
 
     #[test]
     fn add_comparison() {
@@ -17,6 +41,166 @@ mod run {
         let c = run.add_custom_comparison("Best Segments");
         assert_eq!(c, Err(ComparisonError::DuplicateName));
     }
+
+    #[test]
+    fn merge_interleaves_attempts() {
+        let mut first = Timer::new(two_segments("B")).unwrap();
+        let mut second = Timer::new(two_segments("B")).unwrap();
+        add_attempt(&mut first, 20.0);
+        add_attempt(&mut second, 10.0);
+        add_attempt(&mut first, 30.0);
+
+        let mut run = first.run().clone();
+        let report = run.merge(second.run());
+        assert_eq!(report.conflicts, vec![]);
+        assert_eq!(report.merged_attempts, 1);
+
+        let indices = run.attempt_history()
+            .iter()
+            .map(|a| a.index())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![1, 2, 3]);
+        assert_eq!(run.attempt_count(), 3);
+        assert_eq!(
+            run.attempt_history()[1].started(),
+            second.run().attempt_history()[0].started()
+        );
+        assert_eq!(
+            run.segment(1).segment_history().get(2),
+            second.run().segment(1).segment_history().get(1)
+        );
+        assert_eq!(
+            run.segment(1).best_segment_time().game_time,
+            Some(TimeSpan::from_seconds(5.0))
+        );
+
+        // Merging the same attempts again doesn't duplicate them.
+        let report = run.merge(second.run());
+        assert_eq!(report.merged_attempts, 0);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(run.attempt_history().len(), 3);
+    }
+
+    #[test]
+    fn merge_reports_mismatched_segments() {
+        let mut other = Timer::new(two_segments("C")).unwrap();
+        add_attempt(&mut other, 10.0);
+
+        let mut run = two_segments("B");
+        let report = run.merge(other.run());
+        assert!(report.conflicts.contains(&MergeConflict::MissingSegment("C".into())));
+        assert!(report.conflicts.contains(&MergeConflict::ExtraSegment("B".into())));
+        assert!(report.conflicts.contains(&MergeConflict::PersonalBest));
+        assert_eq!(run.attempt_history().len(), 1);
+    }
+
+    #[test]
+    fn icons_are_stored_once() {
+        let mut run = Run::new();
+        for &name in &["A", "B", "C"] {
+            let mut segment = Segment::new(name);
+            segment.set_icon(&[1u8, 2, 3]);
+            run.push_segment(segment);
+        }
+        assert_eq!(run.icons().len(), 1);
+        assert_eq!(run.segment(0).icon().id(), run.segment(2).icon().id());
+
+        run.set_segment_icon(1, &[4u8, 5, 6]);
+        assert_eq!(run.icons().len(), 2);
+
+        run.segment_mut(1).set_icon(&[1u8, 2, 3]);
+        run.intern_icons();
+        assert_eq!(run.icons().len(), 1);
+        assert_eq!(run.segment(0).icon().id(), run.segment(1).icon().id());
+    }
+
+    #[test]
+    fn unused_icons_dont_affect_equality() {
+        let mut run = two_segments("B");
+        let original = run.clone();
+
+        run.set_game_icon(&[1u8, 2, 3]);
+        run.set_game_icon(&[]);
+        assert_eq!(run.icons().len(), 1);
+        assert_eq!(run, original);
+    }
+
+    /// Builds an uncompressed 24-bit bitmap of the size provided.
+    fn bitmap(width: u32, height: i32) -> Vec<u8> {
+        fn push(data: &mut Vec<u8>, value: u32, len: usize) {
+            for i in 0..len {
+                data.push((value >> (8 * i)) as u8);
+            }
+        }
+
+        let row_len = (width * 3 + 3) / 4 * 4;
+        let pixels_len = row_len * height.abs() as u32;
+        let mut data = b"BM".to_vec();
+        push(&mut data, 54 + pixels_len, 4);
+        push(&mut data, 0, 4);
+        push(&mut data, 54, 4);
+        push(&mut data, 40, 4);
+        push(&mut data, width, 4);
+        push(&mut data, height as u32, 4);
+        push(&mut data, 1, 2);
+        push(&mut data, 24, 2);
+        push(&mut data, 0, 4);
+        push(&mut data, pixels_len, 4);
+        data.extend_from_slice(&[0; 16]);
+        data.resize(54 + pixels_len as usize, 0x80);
+        data
+    }
+
+    #[test]
+    fn oversized_icons_are_only_downscaled_on_request() {
+        let mut run = Run::new();
+        let mut segment = Segment::new("A");
+        segment.set_icon(bitmap(300, 300));
+        run.push_segment(segment);
+        run.set_game_icon(bitmap(32, 32));
+
+        // Storing and parsing icons keeps them as they are.
+        assert_eq!(run.segment(0).icon().format(), ImageFormat::Bmp);
+        assert_eq!(run.segment(0).icon().dimensions(), Some((300, 300)));
+
+        let mut saved = Vec::new();
+        saver::save_run(&run, &mut saved).unwrap();
+        let mut parsed = parser::parse(Cursor::new(saved), None).unwrap();
+        assert_eq!(parsed.segment(0).icon(), run.segment(0).icon());
+
+        parsed.downscale_icons();
+        let icon = parsed.segment(0).icon();
+        assert_eq!(icon.format(), ImageFormat::Png);
+        assert_eq!(icon.dimensions(), Some((MAX_ICON_SIZE, MAX_ICON_SIZE)));
+        assert_eq!(parsed.game_icon().format(), ImageFormat::Bmp);
+        assert_eq!(parsed.game_icon().dimensions(), Some((32, 32)));
+
+        // Icons set through the Run Editor get downscaled.
+        let mut editor = RunEditor::new(parsed).unwrap();
+        editor.active_segment().set_icon(bitmap(512, 256));
+        assert_eq!(
+            editor.active_segment().icon().dimensions(),
+            Some((MAX_ICON_SIZE, MAX_ICON_SIZE / 2))
+        );
+    }
+
+    #[test]
+    fn huge_icon_headers_dont_get_decoded() {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        data.extend_from_slice(&[0, 0, 0xEA, 0x60, 0, 0, 0xEA, 0x60]);
+        let mut image = Image::new(&data);
+        assert_eq!(image.dimensions(), Some((60_000, 60_000)));
+        assert!(!image.downscale(MAX_ICON_SIZE));
+        assert_eq!(image.data(), &data[..]);
+    }
+
+    #[test]
+    fn bitmap_dimensions_dont_overflow() {
+        let mut data = bitmap(1, 1);
+        data[22..26].copy_from_slice(&[0, 0, 0, 0x80]);
+        let image = Image::new(&data);
+        assert_eq!(image.dimensions(), Some((1, 1 << 31)));
+    }
 }
 
 mod editor {
@@ -82,3 +266,584 @@ mod editor {
         assert_eq!(c, Err(RenameError::InvalidName(ComparisonError::DuplicateName)));
     }
 }
+
+mod journal {
+    use std::{env, fs};
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufReader, Cursor, Write};
+    use livesplit_core::{Run, Segment, TimeSpan, Timer};
+    use livesplit_core::run::journal;
+    use livesplit_core::run::saver::file;
+
+    fn run() -> Run {
+        let mut run = Run::new();
+        run.push_segment(Segment::new("A"));
+        run.push_segment(Segment::new("B"));
+        run
+    }
+
+    fn finish_attempt(timer: &mut Timer, first: f64, second: f64) -> journal::Entry {
+        timer.start();
+        timer.initialize_game_time();
+        timer.pause_game_time();
+        timer.set_game_time(TimeSpan::from_seconds(first));
+        timer.split();
+        timer.set_game_time(TimeSpan::from_seconds(second));
+        timer.split();
+        journal::reset(timer).unwrap()
+    }
+
+    #[test]
+    fn entries_replay_attempts() {
+        let mut timer = Timer::new(run()).unwrap();
+        let mut journal = Vec::new();
+        for &(first, second) in &[(5.0, 15.0), (4.0, 16.0)] {
+            let entry = finish_attempt(&mut timer, first, second);
+            journal::write_entry(&entry, &mut journal).unwrap();
+        }
+        assert!(journal::reset(&mut timer).is_none());
+
+        let mut replayed = run();
+        let applied = journal::apply(&mut replayed, Cursor::new(&journal)).unwrap();
+        assert_eq!(applied, 2);
+
+        let expected = timer.run();
+        assert_eq!(replayed.attempt_count(), expected.attempt_count());
+        assert_eq!(replayed.attempt_history(), expected.attempt_history());
+        for (replayed, expected) in replayed.segments().iter().zip(expected.segments()) {
+            assert_eq!(replayed.best_segment_time(), expected.best_segment_time());
+            assert_eq!(
+                replayed.personal_best_split_time(),
+                expected.personal_best_split_time()
+            );
+            assert_eq!(
+                replayed.segment_history().iter().collect::<Vec<_>>(),
+                expected.segment_history().iter().collect::<Vec<_>>()
+            );
+        }
+
+        // Applying the journal again doesn't duplicate anything.
+        let applied = journal::apply(&mut replayed, Cursor::new(&journal)).unwrap();
+        assert_eq!(applied, 0);
+
+        // A torn last line gets ignored.
+        journal.extend_from_slice(b"{\"attempt_count\":");
+        assert_eq!(journal::read_entries(Cursor::new(&journal)).unwrap().len(), 2);
+
+        // Malformed lines in the middle of the journal are reported though.
+        journal.extend_from_slice(b"\n");
+        let entry = finish_attempt(&mut timer, 6.0, 17.0);
+        journal::write_entry(&entry, &mut journal).unwrap();
+        match journal::read_entries(Cursor::new(&journal)) {
+            Err(journal::Error::CorruptEntry(3, _)) => {}
+            _ => panic!("The malformed line should be reported"),
+        }
+    }
+
+    #[test]
+    fn appending_after_a_torn_line() {
+        let directory = env::temp_dir().join("livesplit_core_journal_torn");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("splits.lss");
+
+        file::save_run(&run(), &path, 0).unwrap();
+        let mut timer = Timer::new(journal::load(&path).unwrap().run).unwrap();
+        let entry = finish_attempt(&mut timer, 5.0, 15.0);
+        journal::append(&entry, &path).unwrap();
+
+        // Simulate a crash while appending the second entry.
+        let mut torn = Vec::new();
+        journal::write_entry(&finish_attempt(&mut timer, 4.0, 14.0), &mut torn).unwrap();
+        let torn_len = torn.len() / 2;
+        OpenOptions::new()
+            .append(true)
+            .open(journal::path(&path))
+            .unwrap()
+            .write_all(&torn[..torn_len])
+            .unwrap();
+
+        let entry = finish_attempt(&mut timer, 6.0, 16.0);
+        journal::append(&entry, &path).unwrap();
+
+        let contents = BufReader::new(File::open(journal::path(&path)).unwrap());
+        assert_eq!(journal::read_entries(contents).unwrap().len(), 2);
+        let loaded = journal::load(&path).unwrap().run;
+        assert_eq!(loaded.attempt_history().len(), 2);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn compacting_folds_the_journal_into_the_splits_file() {
+        let directory = env::temp_dir().join("livesplit_core_journal");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("splits.lss");
+
+        file::save_run(&run(), &path, 0).unwrap();
+        let mut timer = Timer::new(journal::load(&path).unwrap().run).unwrap();
+        let entry = finish_attempt(&mut timer, 5.0, 15.0);
+        journal::append(&entry, &path).unwrap();
+
+        let loaded = journal::load(&path).unwrap().run;
+        assert_eq!(loaded.attempt_history().len(), 1);
+
+        journal::compact(&path, 0).unwrap();
+        assert!(!journal::path(&path).exists());
+        let compacted = file::load(&path).unwrap().run;
+        assert_eq!(compacted.attempt_history().len(), 1);
+        assert_eq!(
+            compacted.segments()[1].personal_best_split_time().game_time,
+            Some(TimeSpan::from_seconds(15.0))
+        );
+
+        // A corrupted journal is kept around instead of losing its entries.
+        let mut timer = Timer::new(compacted).unwrap();
+        let entry = finish_attempt(&mut timer, 6.0, 16.0);
+        journal::append(&entry, &path).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(journal::path(&path))
+            .unwrap()
+            .write_all(b"garbage\n")
+            .unwrap();
+        let entry = finish_attempt(&mut timer, 7.0, 17.0);
+        journal::append(&entry, &path).unwrap();
+
+        assert!(journal::compact(&path, 0).is_err());
+        assert!(journal::path(&path).exists());
+        assert_eq!(file::load(&path).unwrap().run.attempt_history().len(), 1);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
+
+mod diff {
+    use livesplit_core::{Run, Segment, Time, TimeSpan};
+    use livesplit_core::run::diff::{diff, Change};
+
+    fn run(names: &[&str]) -> Run {
+        let mut run = Run::new();
+        for &name in names {
+            run.push_segment(Segment::new(name));
+        }
+        run
+    }
+
+    #[test]
+    fn equal_runs_have_no_changes() {
+        let run = run(&["A", "B", "C"]);
+        assert!(diff(&run, &run.clone()).is_empty());
+    }
+
+    #[test]
+    fn reports_segment_changes() {
+        let old = run(&["A", "B", "C", "D"]);
+        let new = run(&["C", "A", "X", "E"]);
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::SegmentRenamed {
+                    old_index: 1,
+                    new_index: 2,
+                    old: "B".into(),
+                    new: "X".into(),
+                },
+                Change::SegmentMoved {
+                    old_index: 2,
+                    new_index: 0,
+                    name: "C".into(),
+                },
+                Change::SegmentRemoved {
+                    index: 3,
+                    name: "D".into(),
+                },
+                Change::SegmentAdded {
+                    index: 3,
+                    name: "E".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_time_changes() {
+        let old = run(&["A", "B"]);
+        let mut new = old.clone();
+        new.set_game_name("Game");
+        let time = Time::new().with_real_time(Some(TimeSpan::from_seconds(5.0)));
+        new.segment_mut(1).set_personal_best_split_time(time);
+        new.segment_mut(0).set_best_segment_time(time);
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::GameName {
+                    old: "".into(),
+                    new: "Game".into(),
+                },
+                Change::BestSegmentTime {
+                    index: 0,
+                    old: Time::default(),
+                    new: time,
+                },
+                Change::PersonalBestSplitTime {
+                    index: 1,
+                    old: Time::default(),
+                    new: time,
+                },
+            ]
+        );
+    }
+}
+
+mod lint {
+    use livesplit_core::{Run, Segment, Time, TimeSpan, TimingMethod};
+    use livesplit_core::run::{Fix, Issue};
+
+    fn time(seconds: f64) -> Time {
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(seconds)))
+    }
+
+    fn run() -> Run {
+        let mut run = Run::new();
+        for &(name, split_time) in &[("A", 10.0), ("B", 25.0)] {
+            let mut segment = Segment::new(name);
+            segment.set_personal_best_split_time(time(split_time));
+            segment.set_best_segment_time(time(10.0));
+            run.push_segment(segment);
+        }
+        run
+    }
+
+    #[test]
+    fn consistent_run_has_no_issues() {
+        let run = run();
+        assert!(run.lint().is_empty());
+    }
+
+    #[test]
+    fn finds_and_fixes_issues() {
+        let mut run = run();
+        run.segment_mut(1).set_best_segment_time(time(20.0));
+        run.segment_mut(1).segment_history_mut().insert(3, time(15.0));
+        run.custom_comparisons_mut().push("Personal Best".into());
+
+        let issues = run.lint();
+        assert_eq!(
+            issues,
+            vec![
+                Issue::SlowBestSegment {
+                    segment_index: 1,
+                    method: TimingMethod::RealTime,
+                    best_segment_time: Some(TimeSpan::from_seconds(20.0)),
+                    personal_best_segment_time: TimeSpan::from_seconds(15.0),
+                },
+                Issue::UnknownAttempt {
+                    segment_index: 1,
+                    attempt_index: 3,
+                },
+                Issue::DuplicateComparison("Personal Best".into()),
+            ]
+        );
+        assert_eq!(
+            issues[1].fix(),
+            Some(Fix::RemoveSegmentHistoryElement {
+                segment_index: 1,
+                attempt_index: 3,
+            })
+        );
+
+        for issue in &issues {
+            run.apply_fix(&issue.fix().unwrap());
+        }
+        assert!(run.lint().is_empty());
+        assert_eq!(
+            run.custom_comparisons(),
+            &["Personal Best".to_string(), "Personal Best (2)".to_string()][..]
+        );
+    }
+
+    #[test]
+    fn duplicate_comparisons_keep_their_times() {
+        let mut run = run();
+        run.custom_comparisons_mut().push("Goal".into());
+        run.custom_comparisons_mut().push("Goal".into());
+        *run.segment_mut(1).comparison_mut("Goal") = time(14.0);
+
+        let issues = run.lint();
+        assert_eq!(issues, vec![Issue::DuplicateComparison("Goal".into())]);
+        run.apply_fix(&issues[0].fix().unwrap());
+
+        assert!(run.lint().is_empty());
+        assert_eq!(run.segment(1).comparison("Goal"), time(14.0));
+        assert_eq!(run.segment(1).comparison("Goal (2)"), time(14.0));
+    }
+
+    #[test]
+    fn finds_mismatching_attempt_times() {
+        let mut run = run();
+        run.add_attempt_with_index(time(26.0), 1, None, None, None);
+        run.segment_mut(0).segment_history_mut().insert(1, time(10.0));
+        run.segment_mut(1).segment_history_mut().insert(1, time(15.0));
+
+        let issues = run.lint();
+        assert_eq!(
+            issues,
+            vec![Issue::AttemptTimeMismatch {
+                attempt_index: 1,
+                method: TimingMethod::RealTime,
+                attempt_time: Some(TimeSpan::from_seconds(26.0)),
+                history_time: TimeSpan::from_seconds(25.0),
+            }]
+        );
+
+        run.apply_fix(&issues[0].fix().unwrap());
+        assert_eq!(run.attempt_history()[0].time(), time(25.0));
+    }
+
+    #[test]
+    fn partial_game_time_has_no_fix() {
+        let mut run = run();
+        let mut split_time = time(10.0);
+        split_time.game_time = Some(TimeSpan::from_seconds(9.0));
+        run.segment_mut(0).set_personal_best_split_time(split_time);
+        run.segment_mut(0).set_best_segment_time(split_time);
+
+        let issues = run.lint();
+        assert_eq!(issues, vec![Issue::PartialGameTime { missing: vec![1] }]);
+        assert_eq!(issues[0].fix(), None);
+    }
+}
+
+mod attempts {
+    use std::io::Cursor;
+    use livesplit_core::{Run, Segment, Time, TimeSpan};
+    use livesplit_core::run::parser::livesplit as parser;
+    use livesplit_core::run::saver::livesplit as saver;
+
+    fn time(seconds: f64) -> Time {
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(seconds)))
+    }
+
+    fn run() -> Run {
+        let mut run = Run::new();
+        run.push_segment(Segment::new("A"));
+        run.push_segment(Segment::new("B"));
+        for &(index, first, second) in &[(1, 10.0, 15.0), (2, 12.0, 16.0)] {
+            run.add_attempt_with_index(time(first + second), index, None, None, None);
+            run.segment_mut(0)
+                .segment_history_mut()
+                .insert(index, time(first));
+            run.segment_mut(1)
+                .segment_history_mut()
+                .insert(index, time(second));
+        }
+        run.segment_mut(0).set_best_segment_time(time(10.0));
+        run.segment_mut(1).set_best_segment_time(time(15.0));
+        run.segment_mut(0).set_personal_best_split_time(time(10.0));
+        run.segment_mut(1).set_personal_best_split_time(time(25.0));
+        run
+    }
+
+    #[test]
+    fn removing_the_personal_best_replaces_it() {
+        let mut run = run();
+        assert!(run.remove_attempt(1));
+        assert!(!run.remove_attempt(1));
+
+        assert_eq!(run.attempt_history().len(), 1);
+        assert_eq!(run.segment(0).best_segment_time(), time(12.0));
+        assert_eq!(run.segment(1).best_segment_time(), time(16.0));
+        assert_eq!(run.segment(0).personal_best_split_time(), time(12.0));
+        assert_eq!(run.segment(1).personal_best_split_time(), time(28.0));
+    }
+
+    #[test]
+    fn invalidating_keeps_the_attempt() {
+        let mut run = run();
+        assert!(run.invalidate_attempt(2));
+
+        assert_eq!(run.attempt_history().len(), 2);
+        assert!(run.attempt_history()[1].is_invalid());
+        assert!(run.segment(0).segment_history().get(2).is_none());
+        assert_eq!(run.segment(0).best_segment_time(), time(10.0));
+        assert_eq!(run.segment(1).personal_best_split_time(), time(25.0));
+    }
+
+    #[test]
+    fn annotations_survive_saving() {
+        let mut run = run();
+        run.invalidate_attempt(2);
+        {
+            let attempt = &mut run.attempt_history_mut()[0];
+            attempt.set_note("Great & fast");
+            attempt.add_tag("PB");
+            attempt.add_tag("Marathon <3");
+            attempt.add_tag("PB");
+        }
+
+        let mut buf = Vec::new();
+        saver::save_run(&run, &mut buf).unwrap();
+        let parsed = parser::parse(Cursor::new(buf), None).unwrap();
+
+        let attempts = parsed.attempt_history();
+        assert_eq!(attempts[0].note(), "Great & fast");
+        assert_eq!(
+            attempts[0].tags(),
+            &["PB".to_string(), "Marathon <3".to_string()][..]
+        );
+        assert!(!attempts[0].is_invalid());
+        assert!(attempts[1].is_invalid());
+        assert!(attempts[1].tags().is_empty());
+    }
+}
+
+mod export {
+    use livesplit_core::{Run, Segment, Time, TimeSpan};
+    use livesplit_core::run::export::csv;
+
+    #[test]
+    fn writes_attempts_and_segment_history() {
+        let mut run = Run::new();
+        run.push_segment(Segment::new("A"));
+        run.push_segment(Segment::new("B, the second"));
+        run.add_attempt_with_index(
+            Time::new().with_real_time(Some(TimeSpan::from_seconds(25.5))),
+            1,
+            None,
+            None,
+            Some(TimeSpan::from_seconds(2.0)),
+        );
+        for &(segment_index, attempt_index, seconds) in &[(1, 1, 15.5), (0, 2, 12.0), (0, 1, 10.0)]
+        {
+            run.segment_mut(segment_index).segment_history_mut().insert(
+                attempt_index,
+                Time::new().with_real_time(Some(TimeSpan::from_seconds(seconds))),
+            );
+        }
+
+        let mut attempts = Vec::new();
+        csv::write_attempts(&run, &mut attempts).unwrap();
+        assert_eq!(
+            String::from_utf8(attempts).unwrap(),
+            "Attempt Index,Started,Ended,Real Time,Game Time,Pause Time\n1,,,25.5,,2\n"
+        );
+
+        let mut segment_history = Vec::new();
+        csv::write_segment_history(&run, &mut segment_history).unwrap();
+        assert_eq!(
+            String::from_utf8(segment_history).unwrap(),
+            "Attempt Index,Segment Index,Segment Name,Real Time,Game Time\n\
+             1,0,A,10,\n\
+             1,1,\"B, the second\",15.5,\n\
+             2,0,A,12,\n"
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn exports_into_sqlite_incrementally() {
+        use livesplit_core::rusqlite::Connection;
+        use livesplit_core::run::export::sqlite;
+
+        let mut run = Run::new();
+        run.set_game_name("Game");
+        run.push_segment(Segment::new("A"));
+        let mut connection = Connection::open_in_memory().unwrap();
+
+        for index in 1..3 {
+            let time = Time::new().with_real_time(Some(TimeSpan::from_seconds(10.0)));
+            run.add_attempt_with_index(time, index, None, None, None);
+            run.segment_mut(0).segment_history_mut().insert(index, time);
+
+            let report = sqlite::export(&mut connection, &run, "Runner").unwrap();
+            assert_eq!(report.new_attempts, 1);
+        }
+
+        let tables = [("runs", 1), ("segments", 1), ("attempts", 2), ("segment_times", 2)];
+        for &(table, expected) in &tables {
+            let count: i64 = connection
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), &[], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, expected);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn updates_edited_and_removed_attempts_in_sqlite() {
+        use livesplit_core::rusqlite::Connection;
+        use livesplit_core::run::export::sqlite;
+
+        let mut run = Run::new();
+        run.set_game_name("Game");
+        run.push_segment(Segment::new("A"));
+        for index in 1..3 {
+            let time = Time::new().with_real_time(Some(TimeSpan::from_seconds(10.0)));
+            run.add_attempt_with_index(time, index, None, None, None);
+            run.segment_mut(0).segment_history_mut().insert(index, time);
+        }
+        let mut connection = Connection::open_in_memory().unwrap();
+        sqlite::export(&mut connection, &run, "Runner").unwrap();
+
+        let edited = Time::new().with_real_time(Some(TimeSpan::from_seconds(8.0)));
+        run.attempt_history_mut()[0].set_time(edited);
+        *run.segment_mut(0).segment_history_mut().get_mut(1).unwrap() = edited;
+        run.attempt_history_mut().remove(1);
+        run.segment_mut(0).segment_history_mut().remove(2);
+        let report = sqlite::export(&mut connection, &run, "Runner").unwrap();
+        assert_eq!(report.new_attempts, 0);
+
+        for &(table, expected) in &[("attempts", 1), ("segment_times", 1)] {
+            let count: i64 = connection
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), &[], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, expected);
+        }
+        for &table in &["attempts", "segment_times"] {
+            let time: f64 = connection
+                .query_row(
+                    &format!("SELECT real_time FROM {} WHERE attempt_index = 1", table),
+                    &[],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(time, 8.0);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn replaces_sqlite_segment_times_when_segments_change() {
+        use livesplit_core::rusqlite::Connection;
+        use livesplit_core::run::export::sqlite;
+
+        let mut run = Run::new();
+        run.set_game_name("Game");
+        run.push_segment(Segment::new("A"));
+        let time = Time::new().with_real_time(Some(TimeSpan::from_seconds(10.0)));
+        run.add_attempt_with_index(time, 1, None, None, None);
+        run.segment_mut(0).segment_history_mut().insert(1, time);
+        let mut connection = Connection::open_in_memory().unwrap();
+        sqlite::export(&mut connection, &run, "Runner").unwrap();
+
+        run.segments_mut().insert(0, Segment::new("B"));
+        let segment_time = Time::new().with_real_time(Some(TimeSpan::from_seconds(4.0)));
+        run.segment_mut(0).segment_history_mut().insert(1, segment_time);
+        sqlite::export(&mut connection, &run, "Runner").unwrap();
+
+        for &(segment_index, expected) in &[(0, 4.0), (1, 10.0)] {
+            let time: f64 = connection
+                .query_row(
+                    "SELECT real_time FROM segment_times WHERE segment_index = ?1",
+                    &[&segment_index],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(time, expected);
+        }
+    }
+}